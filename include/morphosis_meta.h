@@ -0,0 +1,160 @@
+#ifndef MORPHOSIS_META_H
+#define MORPHOSIS_META_H
+
+/* This file is generated by cbindgen, run `make header` instead of editing it. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result of every FFI function.
+typedef enum MetamorphosisStatus {
+  METAMORPHOSIS_STATUS_OK = 0,
+  // A required pointer argument was null.
+  METAMORPHOSIS_STATUS_NULL_POINTER = 1,
+  // A string argument wasn't valid UTF-8.
+  METAMORPHOSIS_STATUS_INVALID_UTF8 = 2,
+  // A file couldn't be read or written, or an upstream couldn't be reached.
+  METAMORPHOSIS_STATUS_IO = 3,
+  // A JSON document couldn't be parsed or serialized.
+  METAMORPHOSIS_STATUS_PARSE = 4,
+  // The internal async runtime couldn't be created.
+  METAMORPHOSIS_STATUS_RUNTIME = 5,
+  // The library panicked, the handle should not be used anymore.
+  METAMORPHOSIS_STATUS_PANIC = 6,
+} MetamorphosisStatus;
+
+// The upstream an updater handle downloads metadata from.
+//
+// Only the declared values may be passed, any other value is undefined behavior.
+typedef enum MetamorphosisUpstream {
+  METAMORPHOSIS_UPSTREAM_MOJANG = 0,
+  METAMORPHOSIS_UPSTREAM_FORGE = 1,
+  METAMORPHOSIS_UPSTREAM_FABRIC = 2,
+  METAMORPHOSIS_UPSTREAM_LITELOADER = 3,
+} MetamorphosisUpstream;
+
+// What an updater does with versions which were removed upstream, see [`RemovalPolicy`].
+//
+// Only the declared values may be passed, any other value is undefined behavior.
+typedef enum MetamorphosisRemovalPolicy {
+  METAMORPHOSIS_REMOVAL_POLICY_KEEP = 0,
+  METAMORPHOSIS_REMOVAL_POLICY_ARCHIVE = 1,
+  METAMORPHOSIS_REMOVAL_POLICY_DELETE = 2,
+} MetamorphosisRemovalPolicy;
+
+// The format of a version file handle.
+//
+// Only the declared values may be passed, any other value is undefined behavior.
+typedef enum MetamorphosisVersionFormat {
+  METAMORPHOSIS_VERSION_FORMAT_MOJANG = 0,
+  METAMORPHOSIS_VERSION_FORMAT_POLY_MC = 1,
+} MetamorphosisVersionFormat;
+
+// An updater together with the runtime its updates run on.
+typedef struct MetamorphosisUpdater MetamorphosisUpdater;
+
+// A parsed Mojang or PolyMC version file.
+typedef struct MetamorphosisVersionFile MetamorphosisVersionFile;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the error message of the last failed call on this thread, or null if it succeeded.
+//
+// The string is owned by the library and valid until the next call on this thread.
+const char *metamorphosis_last_error(void);
+
+// Frees a string returned by this library. Passing null does nothing.
+//
+// # Safety
+//
+// `s` must be null or a string returned by this library, which wasn't freed yet.
+void metamorphosis_string_free(char *s);
+
+// Creates an updater for the given upstream, which caches metadata in `cache_directory`.
+//
+// In offline mode, the updater only uses responses from the HTTP cache.
+//
+// # Safety
+//
+// `upstream` and `removal_policy` must be declared values of their enums, `cache_directory` must be a
+// NUL-terminated string and `out` must be a valid pointer.
+enum MetamorphosisStatus metamorphosis_updater_new(enum MetamorphosisUpstream upstream,
+                                                   const char *cache_directory,
+                                                   enum MetamorphosisRemovalPolicy removal_policy,
+                                                   bool offline,
+                                                   struct MetamorphosisUpdater **out);
+
+// Runs an update, blocking until it is done, and returns its report as JSON.
+//
+// The report must be freed with [`metamorphosis_string_free`].
+//
+// # Safety
+//
+// `updater` must be a handle returned by [`metamorphosis_updater_new`] and `report_json` must be a valid pointer.
+enum MetamorphosisStatus metamorphosis_updater_update(struct MetamorphosisUpdater *updater,
+                                                      char **report_json);
+
+// Frees an updater. Passing null does nothing.
+//
+// # Safety
+//
+// `updater` must be null or a handle returned by [`metamorphosis_updater_new`], which wasn't freed yet.
+void metamorphosis_updater_free(struct MetamorphosisUpdater *updater);
+
+// Parses a version file of the given format.
+//
+// # Safety
+//
+// `format` must be a declared value of its enum, `json` must be a NUL-terminated string and `out` must be a valid
+// pointer.
+enum MetamorphosisStatus metamorphosis_version_file_parse(enum MetamorphosisVersionFormat format,
+                                                          const char *json,
+                                                          struct MetamorphosisVersionFile **out);
+
+// Returns the format of a version file.
+//
+// # Safety
+//
+// `version_file` must be a handle returned by this library and `format` must be a valid pointer.
+enum MetamorphosisStatus metamorphosis_version_file_format(const struct MetamorphosisVersionFile *version_file,
+                                                           enum MetamorphosisVersionFormat *format);
+
+// Converts a Mojang version file to a PolyMC version file of the given component.
+//
+// # Safety
+//
+// `version_file` must be a Mojang version file handle returned by this library, `uid`, `name` and
+// `version` must be NUL-terminated strings and `out` must be a valid pointer.
+enum MetamorphosisStatus metamorphosis_version_file_to_polymc(const struct MetamorphosisVersionFile *version_file,
+                                                              const char *uid,
+                                                              const char *name,
+                                                              const char *version,
+                                                              struct MetamorphosisVersionFile **out);
+
+// Serializes a version file to JSON.
+//
+// The JSON must be freed with [`metamorphosis_string_free`].
+//
+// # Safety
+//
+// `version_file` must be a handle returned by this library and `json` must be a valid pointer.
+enum MetamorphosisStatus metamorphosis_version_file_to_json(const struct MetamorphosisVersionFile *version_file,
+                                                            char **json);
+
+// Frees a version file. Passing null does nothing.
+//
+// # Safety
+//
+// `version_file` must be null or a handle returned by this library, which wasn't freed yet.
+void metamorphosis_version_file_free(struct MetamorphosisVersionFile *version_file);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MORPHOSIS_META_H */
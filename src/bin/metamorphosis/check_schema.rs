@@ -0,0 +1,463 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use morphosis_meta::models::{
+    fabric::{FabricInstallerDataV1, FabricJarInfo},
+    forge::{
+        DerivedForgeIndex, ForgeInstallerProfile, ForgeInstallerProfileV1_5,
+        ForgeInstallerProfileV2, ForgeLegacyInfoList, InstallerInfo,
+    },
+    liteloader::LiteloaderIndex,
+    mojang::{
+        MojangIndex, MojangVersionFile, MAX_MOJANG_SUPPORTED_VERSION,
+        MAX_SUPPORTED_COMPLIANCE_LEVEL,
+    },
+};
+
+use crate::verify::json_files;
+
+/// The only Fabric installer data version the models know.
+const FABRIC_INSTALLER_DATA_VERSION: i64 = 1;
+
+/// Everything about a cached file the models don't match, so they can be updated before parsing fails for real.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSchemaReport {
+    pub file: String,
+    /// Paths of keys the model doesn't know, which are dropped when the file is parsed.
+    pub unknown_keys: Vec<String>,
+    /// Values the model can't parse, e.g. because of a different type or an unknown enum variant.
+    pub unexpected_types: Vec<String>,
+    /// Format versions and compliance levels newer than the models support.
+    pub unsupported_versions: Vec<String>,
+}
+
+impl FileSchemaReport {
+    fn is_empty(&self) -> bool {
+        self.unknown_keys.is_empty()
+            && self.unexpected_types.is_empty()
+            && self.unsupported_versions.is_empty()
+    }
+}
+
+/// A model whose unknown top-level keys may be kept in an `extra` map, see the `extra-fields` feature.
+trait Model: DeserializeOwned + Serialize {
+    fn extra_keys(&self) -> Vec<String>;
+}
+
+macro_rules! impl_model {
+    ($($model:ty),*) => {
+        $(
+            impl Model for $model {
+                fn extra_keys(&self) -> Vec<String> {
+                    #[cfg(feature = "extra-fields")]
+                    return self.extra.keys().cloned().collect();
+                    #[cfg(not(feature = "extra-fields"))]
+                    Vec::new()
+                }
+            }
+        )*
+    };
+}
+
+impl_model!(
+    MojangIndex<'static>,
+    MojangVersionFile,
+    DerivedForgeIndex,
+    InstallerInfo,
+    ForgeLegacyInfoList,
+    ForgeInstallerProfile,
+    ForgeInstallerProfileV1_5,
+    ForgeInstallerProfileV2,
+    FabricInstallerDataV1,
+    FabricJarInfo,
+    LiteloaderIndex
+);
+
+/// A version field and the newest version the models support.
+type VersionCheck<'a> = (&'a str, i64);
+
+/// Parses every cached upstream file strictly and returns a report for each file which doesn't match its model.
+pub fn check_schema(cache_directory: &Path) -> Vec<FileSchemaReport> {
+    info!(
+        "Checking cache {} against the models...",
+        cache_directory.display()
+    );
+    let mojang_version_checks: &[VersionCheck] = &[
+        (
+            "minimumLauncherVersion",
+            *MAX_MOJANG_SUPPORTED_VERSION as i64,
+        ),
+        ("complianceLevel", *MAX_SUPPORTED_COMPLIANCE_LEVEL as i64),
+    ];
+
+    let mut reports = Vec::new();
+    check_file::<MojangIndex>(
+        &cache_directory.join("mojang/version_manifest_v2.json"),
+        &[(
+            "versions.*.complianceLevel",
+            *MAX_SUPPORTED_COMPLIANCE_LEVEL as i64,
+        )],
+        &mut reports,
+    );
+    check_directory::<MojangVersionFile>(
+        &cache_directory.join("mojang/versions"),
+        mojang_version_checks,
+        &mut reports,
+    );
+    check_file::<DerivedForgeIndex>(
+        &cache_directory.join("forge/derived_index.json"),
+        &[],
+        &mut reports,
+    );
+    check_directory::<InstallerInfo>(
+        &cache_directory.join("forge/installer_info"),
+        &[],
+        &mut reports,
+    );
+    check_file::<ForgeLegacyInfoList>(
+        &cache_directory.join("forge/legacy_info.json"),
+        &[],
+        &mut reports,
+    );
+    check_directory::<MojangVersionFile>(
+        &cache_directory.join("forge/version_manifests"),
+        mojang_version_checks,
+        &mut reports,
+    );
+    for path in json_files(&cache_directory.join("forge/installer_manifests")) {
+        check_forge_installer_profile(&path, &mut reports);
+    }
+    check_directory::<FabricJarInfo>(&cache_directory.join("fabric/jars"), &[], &mut reports);
+    check_directory::<FabricInstallerDataV1>(
+        &cache_directory.join("fabric/loader-installer-json"),
+        &[("version", FABRIC_INSTALLER_DATA_VERSION)],
+        &mut reports,
+    );
+    check_file::<LiteloaderIndex>(
+        &cache_directory.join("liteloader/versions.json"),
+        &[],
+        &mut reports,
+    );
+
+    reports
+}
+
+fn check_directory<T>(
+    directory: &Path,
+    version_checks: &[VersionCheck],
+    reports: &mut Vec<FileSchemaReport>,
+) where
+    T: Model,
+{
+    for path in json_files(directory) {
+        check_file::<T>(&path, version_checks, reports);
+    }
+}
+
+/// Checks a file against a model, if it exists.
+fn check_file<T>(path: &Path, version_checks: &[VersionCheck], reports: &mut Vec<FileSchemaReport>)
+where
+    T: Model,
+{
+    if !path.is_file() {
+        return;
+    }
+
+    let report = match std::fs::read_to_string(path) {
+        Ok(json) => check_json::<T>(path, &json, version_checks),
+        Err(e) => FileSchemaReport {
+            file: path.display().to_string(),
+            unexpected_types: vec![e.to_string()],
+            ..Default::default()
+        },
+    };
+    if !report.is_empty() {
+        reports.push(report);
+    }
+}
+
+/// Checks an installer profile against the model its format belongs to.
+fn check_forge_installer_profile(path: &Path, reports: &mut Vec<FileSchemaReport>) {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) => {
+            reports.push(FileSchemaReport {
+                file: path.display().to_string(),
+                unexpected_types: vec![e.to_string()],
+                ..Default::default()
+            });
+            return;
+        }
+    };
+
+    let report = check_forge_installer_profile_json(path, &json);
+    if !report.is_empty() {
+        reports.push(report);
+    }
+}
+
+/// Checks the contents of an installer profile, falling back to the 1.5 format if it isn't a valid version 2 profile.
+fn check_forge_installer_profile_json(path: &Path, json: &str) -> FileSchemaReport {
+    let version_checks: &[VersionCheck] = &[(
+        "versionInfo.minimumLauncherVersion",
+        *MAX_MOJANG_SUPPORTED_VERSION as i64,
+    )];
+    let has_install_section = serde_json::from_str::<Value>(json)
+        .map(|profile| profile.get("install").is_some())
+        .unwrap_or(false);
+    if has_install_section {
+        check_json::<ForgeInstallerProfile>(path, json, version_checks)
+    } else {
+        // 1.12.2-14.23.5.2851 is the only build using the 1.5 format, which is a looser version 2
+        let report = check_json::<ForgeInstallerProfileV2>(path, json, &[]);
+        if report.unexpected_types.is_empty() {
+            report
+        } else {
+            check_json::<ForgeInstallerProfileV1_5>(path, json, &[])
+        }
+    }
+}
+
+fn check_json<T>(path: &Path, json: &str, version_checks: &[VersionCheck]) -> FileSchemaReport
+where
+    T: Model,
+{
+    let mut report = FileSchemaReport {
+        file: path.display().to_string(),
+        ..Default::default()
+    };
+    let original: Value = match serde_json::from_str(json) {
+        Ok(original) => original,
+        Err(e) => {
+            report.unexpected_types.push(e.to_string());
+            return report;
+        }
+    };
+
+    for (field, max_supported) in version_checks {
+        for (path, version) in find_values(&original, field) {
+            if let Some(version) = version.as_i64().filter(|version| version > max_supported) {
+                report.unsupported_versions.push(format!(
+                    "{}: {} is newer than the supported {}",
+                    path, version, max_supported
+                ));
+            }
+        }
+    }
+
+    let deserializer = &mut serde_json::Deserializer::from_str(json);
+    match serde_path_to_error::deserialize::<_, T>(deserializer) {
+        Ok(model) => {
+            report.unknown_keys.extend(model.extra_keys());
+            match serde_json::to_value(&model) {
+                Ok(written) => find_unknown_keys(&original, &written, "", &mut report.unknown_keys),
+                Err(e) => report.unexpected_types.push(e.to_string()),
+            }
+        }
+        Err(e) => {
+            // unsupported versions already fail the validators, they don't need to be reported twice
+            let error_path = e.path().to_string();
+            let reported = report
+                .unsupported_versions
+                .iter()
+                .any(|version| version.starts_with(&format!("{}:", error_path)));
+            if !reported {
+                report.unexpected_types.push(e.to_string());
+            }
+        }
+    }
+    report.unknown_keys.sort();
+
+    report
+}
+
+/// Returns the values at a dot-separated path, where `*` matches every element of an array.
+fn find_values<'a>(value: &'a Value, path: &str) -> Vec<(String, &'a Value)> {
+    let mut values = vec![(String::new(), value)];
+    for segment in path.split('.') {
+        values = values
+            .into_iter()
+            .flat_map(|(path, value)| match (segment, value) {
+                ("*", Value::Array(elements)) => elements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, element)| (format!("{}[{}]", path, i), element))
+                    .collect(),
+                (key, Value::Object(map)) => map
+                    .get(key)
+                    .map(|value| (join_path(&path, key), value))
+                    .into_iter()
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+    }
+
+    values
+}
+
+/// Collects the keys of the original file which are missing after parsing and serializing it.
+fn find_unknown_keys(
+    original: &Value,
+    written: &Value,
+    path: &str,
+    unknown_keys: &mut Vec<String>,
+) {
+    match (original, written) {
+        (Value::Object(original), Value::Object(written)) => {
+            for (key, value) in original {
+                match written.get(key) {
+                    Some(written) => {
+                        find_unknown_keys(value, written, &join_path(path, key), unknown_keys)
+                    }
+                    // optional fields set to null aren't written back, but they are known
+                    None if value.is_null() => {}
+                    None => unknown_keys.push(join_path(path, key)),
+                }
+            }
+        }
+        (Value::Array(original), Value::Array(written)) => {
+            for (i, (value, written)) in original.iter().zip(written).enumerate() {
+                find_unknown_keys(value, written, &format!("{}[{}]", path, i), unknown_keys);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn check<T>(value: Value, version_checks: &[VersionCheck]) -> FileSchemaReport
+    where
+        T: Model,
+    {
+        check_json::<T>(Path::new("test.json"), &value.to_string(), version_checks)
+    }
+
+    fn mojang_index(entry: Value) -> Value {
+        let mut version = json!({
+            "id": "1.18.1",
+            "type": "release",
+            "url": "https://piston-meta.mojang.com/v1/packages/1.18.1.json",
+            "time": "2021-12-10T08:23:00+00:00",
+            "releaseTime": "2021-12-10T08:23:00+00:00",
+            "sha1": "b3f6a6b5e7f0cfd1d3c6a3d0e0b7fb8e3cd2d8a1",
+            "complianceLevel": 1
+        });
+        version
+            .as_object_mut()
+            .unwrap()
+            .extend(entry.as_object().unwrap().clone());
+        json!({
+            "latest": { "release": "1.18.1", "snapshot": "1.18.1" },
+            "versions": [version]
+        })
+    }
+
+    #[test]
+    fn finds_values_through_arrays() {
+        let value = json!({ "versions": [{ "level": 1 }, { "other": 2 }, { "level": 3 }] });
+
+        let values = find_values(&value, "versions.*.level");
+        assert_eq!(
+            values,
+            vec![
+                ("versions[0].level".to_string(), &json!(1)),
+                ("versions[2].level".to_string(), &json!(3)),
+            ]
+        );
+        assert!(find_values(&value, "versions.level").is_empty());
+    }
+
+    #[test]
+    fn reports_unknown_nested_keys() {
+        let mut index = mojang_index(json!({ "futureField": true }));
+        index["futureTopLevel"] = json!("new");
+
+        let report = check::<MojangIndex>(index, &[]);
+        assert_eq!(
+            report.unknown_keys,
+            vec!["futureTopLevel", "versions[0].futureField"]
+        );
+        assert!(report.unexpected_types.is_empty());
+    }
+
+    #[test]
+    fn reports_unexpected_types_once_with_their_path() {
+        let report = check::<MojangIndex>(mojang_index(json!({ "complianceLevel": "one" })), &[]);
+
+        assert_eq!(report.unexpected_types.len(), 1);
+        assert!(
+            report.unexpected_types[0].starts_with("versions[0].complianceLevel:"),
+            "{}",
+            report.unexpected_types[0]
+        );
+        assert!(report.unknown_keys.is_empty());
+    }
+
+    #[test]
+    fn reports_unsupported_versions_instead_of_type_errors() {
+        let max_launcher_version = *MAX_MOJANG_SUPPORTED_VERSION as i64;
+        let max_compliance_level = *MAX_SUPPORTED_COMPLIANCE_LEVEL as i64;
+        let version_file = json!({
+            "id": "99w99a",
+            "minimumLauncherVersion": max_launcher_version + 1,
+            "complianceLevel": max_compliance_level + 1
+        });
+
+        let report = check::<MojangVersionFile>(
+            version_file,
+            &[
+                ("minimumLauncherVersion", max_launcher_version),
+                ("complianceLevel", max_compliance_level),
+            ],
+        );
+        assert_eq!(report.unsupported_versions.len(), 2);
+        assert!(report.unsupported_versions[0].starts_with("minimumLauncherVersion:"));
+        assert!(report.unsupported_versions[1].starts_with("complianceLevel:"));
+        // the launcher version fails to parse, but it is already reported as unsupported
+        assert!(report.unexpected_types.is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_the_1_5_installer_profile_format() {
+        let profile = json!({
+            "spec": 0,
+            "profile": "forge",
+            "version": "1.12.2-forge-14.23.5.2851",
+            "minecraft": "1.12.2",
+            "data": {
+                "MAPPINGS": "[de.oceanlabs.mcp:mcp_config:1.12.2-20200226.224830@zip]"
+            }
+        });
+        assert!(!check::<ForgeInstallerProfileV2>(profile.clone(), &[])
+            .unexpected_types
+            .is_empty());
+        let report =
+            check_forge_installer_profile_json(Path::new("test.json"), &profile.to_string());
+        assert!(report.is_empty(), "{:?}", report);
+
+        // a profile matching neither format is reported against the 1.5 format
+        let report = check_forge_installer_profile_json(
+            Path::new("test.json"),
+            &json!({ "spec": "zero" }).to_string(),
+        );
+        assert_eq!(report.unexpected_types.len(), 1);
+        assert!(report.unexpected_types[0].starts_with("spec:"));
+    }
+}
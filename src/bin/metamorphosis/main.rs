@@ -0,0 +1,415 @@
+//! Command-line interface to update the upstream cache and to generate launcher metadata from it.
+
+#[macro_use]
+extern crate log;
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::StreamExt;
+use morphosis_meta::{
+    clients::HTTP_CACHE_DIRECTORY,
+    forge_installer::{InstallDirectories, InstallPlan, InstallSide},
+    models::{forge::ForgeInstallerProfileV2, mojang::Platform},
+    profile::LaunchProfile,
+    resolver::{ComponentRequest, Resolver},
+    FabricUpdater, ForgeUpdater, LiteloaderUpdater, MojangUpdater, PolyMCGenerator, RemovalPolicy,
+    UpdateReport,
+};
+
+mod check_schema;
+mod verify;
+
+/// Something failed, e.g. an upstream couldn't be reached or a file couldn't be written.
+const EXIT_FAILURE: u8 = 1;
+/// `verify` found files which are invalid.
+const EXIT_INVALID: u8 = 3;
+/// `diff` found files which would change.
+const EXIT_CHANGED: u8 = 4;
+/// `check-schema` found files which don't match the models.
+const EXIT_SCHEMA_DRIFT: u8 = 5;
+
+#[derive(Parser)]
+#[command(
+    name = "metamorphosis",
+    version,
+    about = "Updates Minecraft-related metadata and generates launcher metadata from it",
+    after_help = "Exit codes:\n  0  Success\n  1  Failure, e.g. an upstream couldn't be reached\n  2  Invalid usage\n  3  `verify` found invalid files\n  4  `diff` found changes\n  5  `check-schema` found files which don't match the models"
+)]
+struct Cli {
+    /// Directory the upstream metadata is cached in.
+    #[arg(long, global = true, default_value = "upstream")]
+    cache_dir: PathBuf,
+    /// Directory the generated metadata is written to.
+    #[arg(long, global = true, default_value = "polymc")]
+    output_dir: PathBuf,
+    /// Maximum number of upstreams which are updated at the same time.
+    #[arg(short = 'j', long, global = true, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    concurrency: u16,
+    /// Only answer requests from the HTTP cache, without contacting any upstream.
+    #[arg(long, global = true)]
+    offline: bool,
+    /// File with overrides of legacy Minecraft versions, which take precedence over the bundled ones.
+    #[arg(long, global = true)]
+    legacy_overrides: Option<PathBuf>,
+    /// File with patches of the libraries of generated versions.
+    #[arg(long, global = true)]
+    library_patches: Option<PathBuf>,
+    /// Also generate `forge-server/`, which describes how Forge servers are installed and started.
+    #[arg(long, global = true)]
+    forge_server: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Updates the cached metadata of one or all upstreams and prints a report for each.
+    Update {
+        #[arg(value_enum, default_value_t = Upstream::All)]
+        upstream: Upstream,
+        /// What happens to versions which were removed upstream: keep, archive or delete.
+        #[arg(long, default_value = "keep")]
+        removal_policy: RemovalPolicy,
+    },
+    /// Generates launcher metadata from the cache and prints a report.
+    Generate {
+        #[arg(value_enum)]
+        format: Format,
+    },
+    /// Checks that the cached and generated files are valid and that the generated indexes match their files.
+    Verify,
+    /// Parses the cached files strictly and prints unknown keys, unexpected types and unsupported versions by file.
+    CheckSchema,
+    /// Resolves components of the generated metadata and everything they require to a stack of versions.
+    Resolve {
+        /// Components as `uid` or `uid=version`, e.g. `net.minecraftforge=14.23.5.2860`.
+        #[arg(required = true)]
+        components: Vec<ComponentRequest>,
+        /// Print the launch profile the stack merges into instead of the stack.
+        #[arg(long)]
+        profile: bool,
+        /// Only keep the libraries and natives of the profile for a platform, e.g. `linux-aarch64`.
+        #[arg(long, requires = "profile")]
+        platform: Option<Platform>,
+    },
+    /// Prints the processors the Forge installer of a cached version runs, as Java invocations.
+    InstallPlan {
+        /// The long Forge version, e.g. `1.18.2-40.1.0`.
+        version: String,
+        /// client or server.
+        #[arg(long, default_value = "client")]
+        side: InstallSide,
+        /// Directory Forge is installed into.
+        #[arg(long, default_value = ".")]
+        root: PathBuf,
+        /// The vanilla jar of the side, instead of its default location in the root directory.
+        #[arg(long)]
+        minecraft_jar: Option<PathBuf>,
+    },
+    /// Prints what `generate polymc` would change in the output directory, without writing anything.
+    Diff,
+    /// Removes the generated metadata and, optionally, the caches.
+    Clean {
+        /// Also remove the upstream cache.
+        #[arg(long)]
+        cache: bool,
+        /// Also remove the HTTP cache.
+        #[arg(long)]
+        http_cache: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Upstream {
+    Mojang,
+    Forge,
+    Fabric,
+    Liteloader,
+    All,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Polymc,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let cli = Cli::parse();
+
+    match &cli.command {
+        Command::Update {
+            upstream,
+            removal_policy,
+        } => update(&cli, *upstream, *removal_policy).await,
+        Command::Generate { format } => match format {
+            Format::Polymc => generate(&cli, false),
+        },
+        Command::Verify => {
+            let problems = verify::verify(
+                &cli.cache_dir,
+                &cli.output_dir,
+                cli.legacy_overrides.as_deref(),
+                cli.library_patches.as_deref(),
+            );
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            if problems.is_empty() {
+                info!("All files are valid");
+                ExitCode::SUCCESS
+            } else {
+                error!("Found {} problems", problems.len());
+                ExitCode::from(EXIT_INVALID)
+            }
+        }
+        Command::CheckSchema => {
+            let reports = check_schema::check_schema(&cli.cache_dir);
+            print_json(&reports);
+            if reports.is_empty() {
+                info!("All cached files match the models");
+                ExitCode::SUCCESS
+            } else {
+                warn!("{} cached files don't match the models", reports.len());
+                ExitCode::from(EXIT_SCHEMA_DRIFT)
+            }
+        }
+        Command::Resolve {
+            components,
+            profile,
+            platform,
+        } => match Resolver::new(&cli.output_dir).resolve(components) {
+            Ok(stack) => {
+                if *profile {
+                    let launch_profile = LaunchProfile::from_stack(&stack);
+                    match platform {
+                        Some(platform) => print_json(&launch_profile.for_platform(platform)),
+                        None => print_json(&launch_profile),
+                    }
+                } else {
+                    print_json(&stack);
+                }
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                error!("Resolving the components failed: {}", e);
+                ExitCode::from(EXIT_FAILURE)
+            }
+        },
+        Command::InstallPlan {
+            version,
+            side,
+            root,
+            minecraft_jar,
+        } => install_plan(&cli, version, *side, root, minecraft_jar.as_deref()),
+        Command::Diff => generate(&cli, true),
+        Command::Clean { cache, http_cache } => {
+            let mut directories = vec![cli.output_dir.as_path()];
+            if *cache {
+                directories.push(cli.cache_dir.as_path());
+            }
+            if *http_cache {
+                directories.push(Path::new(HTTP_CACHE_DIRECTORY));
+            }
+            clean(&directories)
+        }
+    }
+}
+
+/// Runs the updaters of the given upstreams and prints their reports.
+async fn update(cli: &Cli, upstream: Upstream, removal_policy: RemovalPolicy) -> ExitCode {
+    let upstreams = match upstream {
+        Upstream::All => vec![
+            Upstream::Mojang,
+            Upstream::Forge,
+            Upstream::Fabric,
+            Upstream::Liteloader,
+        ],
+        upstream => vec![upstream],
+    };
+
+    let results = futures::stream::iter(upstreams)
+        .map(|upstream| run_updater(cli, upstream, removal_policy))
+        .buffered(cli.concurrency.into())
+        .collect::<Vec<std::io::Result<UpdateReport>>>()
+        .await;
+
+    let mut reports = Vec::new();
+    let mut failed = false;
+    for result in results {
+        match result {
+            Ok(report) => reports.push(report),
+            Err(e) => {
+                error!("{}", e);
+                failed = true;
+            }
+        }
+    }
+    print_json(&reports);
+
+    if failed {
+        ExitCode::from(EXIT_FAILURE)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+async fn run_updater(
+    cli: &Cli,
+    upstream: Upstream,
+    removal_policy: RemovalPolicy,
+) -> std::io::Result<UpdateReport> {
+    let cache_dir = &cli.cache_dir;
+    let result = match upstream {
+        Upstream::Mojang => {
+            MojangUpdater::new(cache_dir)
+                .with_removal_policy(removal_policy)
+                .with_offline_mode(cli.offline)
+                .generate_meta_cache()
+                .await
+        }
+        Upstream::Forge => {
+            ForgeUpdater::new(cache_dir)
+                .with_removal_policy(removal_policy)
+                .with_offline_mode(cli.offline)
+                .generate_meta_cache()
+                .await
+        }
+        Upstream::Fabric => {
+            FabricUpdater::new(cache_dir)
+                .with_removal_policy(removal_policy)
+                .with_offline_mode(cli.offline)
+                .generate_meta_cache()
+                .await
+        }
+        Upstream::Liteloader => {
+            LiteloaderUpdater::new(cache_dir)
+                .with_removal_policy(removal_policy)
+                .with_offline_mode(cli.offline)
+                .generate_meta_cache()
+                .await
+        }
+        Upstream::All => unreachable!("all upstreams are updated one by one"),
+    };
+
+    result.map_err(|e| {
+        let name = upstream.to_possible_value().unwrap();
+        std::io::Error::new(
+            e.kind(),
+            format!("Updating {} failed: {}", name.get_name(), e),
+        )
+    })
+}
+
+/// Generates PolyMC metadata, or only compares it with the output directory in a dry run.
+fn generate(cli: &Cli, dry_run: bool) -> ExitCode {
+    let mut generator = PolyMCGenerator::new(&cli.cache_dir, &cli.output_dir)
+        .with_dry_run(dry_run)
+        .with_forge_server(cli.forge_server);
+    if let Some(legacy_overrides) = &cli.legacy_overrides {
+        generator = generator.with_legacy_overrides(legacy_overrides);
+    }
+    if let Some(library_patches) = &cli.library_patches {
+        generator = generator.with_library_patches(library_patches);
+    }
+    let report = generator.generate();
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            error!("Generating PolyMC metadata failed: {}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+    print_json(&report);
+
+    let changed = report.has_changes()
+        || !report.files_written.is_empty()
+        || !report.files_removed.is_empty();
+    if dry_run && changed {
+        ExitCode::from(EXIT_CHANGED)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Removes the given directories, skipping the ones which don't exist.
+fn clean(directories: &[&Path]) -> ExitCode {
+    let mut failed = false;
+    for directory in directories {
+        if !directory.exists() {
+            continue;
+        }
+
+        info!("Removing {}...", directory.display());
+        if let Err(e) = std::fs::remove_dir_all(directory) {
+            error!("Removing {} failed: {}", directory.display(), e);
+            failed = true;
+        }
+    }
+
+    if failed {
+        ExitCode::from(EXIT_FAILURE)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Prints the install plan of a cached Forge installer profile.
+fn install_plan(
+    cli: &Cli,
+    version: &str,
+    side: InstallSide,
+    root: &Path,
+    minecraft_jar: Option<&Path>,
+) -> ExitCode {
+    let profile_path = cli
+        .cache_dir
+        .join(format!("forge/installer_manifests/{}.json", version));
+    let profile: ForgeInstallerProfileV2 = match std::fs::read(&profile_path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| serde_json::from_slice(&contents).map_err(|e| e.to_string()))
+    {
+        Ok(profile) => profile,
+        Err(e) => {
+            error!(
+                "{} isn't a cached v2 installer profile: {}",
+                profile_path.display(),
+                e
+            );
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    let installer = cli
+        .cache_dir
+        .join(format!("forge/jars/forge-{}-installer.jar", version));
+    let mut directories = InstallDirectories::new(root, installer);
+    if let Some(minecraft_jar) = minecraft_jar {
+        directories = directories.with_minecraft_jar(minecraft_jar);
+    }
+    match InstallPlan::new(&profile, side, &directories) {
+        Ok(plan) => {
+            print_json(&plan);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            error!("Resolving the installer processors failed: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+fn print_json<T>(value: &T)
+where
+    T: serde::Serialize,
+{
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).expect("reports are always serializable")
+    );
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+use morphosis_meta::models::{
+    fabric::{FabricInstallerDataV1, FabricJarInfo},
+    forge::{
+        DerivedForgeIndex, ForgeInstallerProfile, ForgeInstallerProfileV1_5,
+        ForgeInstallerProfileV2, ForgeLegacyInfoList, InstallerInfo,
+    },
+    liteloader::LiteloaderIndex,
+    mojang::{MojangIndex, MojangVersionFile},
+    patch::LibraryPatchIndex,
+    polymc::LegacyOverrideIndex,
+};
+use morphosis_meta::validators::polymc::{self, Severity};
+
+/// Checks the cache and the output directory and returns a description of every problem found.
+pub fn verify(
+    cache_directory: &Path,
+    output_directory: &Path,
+    legacy_overrides: Option<&Path>,
+    library_patches: Option<&Path>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    verify_cache(cache_directory, &mut problems);
+    verify_legacy_overrides(cache_directory, legacy_overrides, &mut problems);
+    if let Err(e) = LibraryPatchIndex::load(library_patches) {
+        problems.push(e.to_string());
+    }
+    if output_directory.is_dir() {
+        verify_output(output_directory, &mut problems);
+    }
+
+    problems
+}
+
+/// Checks that every cached upstream file can be parsed by its model.
+fn verify_cache(cache_directory: &Path, problems: &mut Vec<String>) {
+    info!("Verifying cache {}...", cache_directory.display());
+    let index_path = cache_directory.join("mojang/version_manifest_v2.json");
+    check_file::<MojangIndex>(&index_path, problems);
+    if let Ok(index) = read_json::<MojangIndex>(&index_path) {
+        // snapshots of an unknown development cycle can't be ordered against releases
+        for entry in &index.versions {
+            if let Err(e) = entry.minecraft_version().snapshot_target() {
+                problems.push(format!("{}: {}", index_path.display(), e));
+            }
+        }
+    }
+    check_directory::<MojangVersionFile>(&cache_directory.join("mojang/versions"), problems);
+    check_file::<DerivedForgeIndex>(&cache_directory.join("forge/derived_index.json"), problems);
+    check_directory::<InstallerInfo>(&cache_directory.join("forge/installer_info"), problems);
+    check_file::<ForgeLegacyInfoList>(&cache_directory.join("forge/legacy_info.json"), problems);
+    check_directory::<MojangVersionFile>(
+        &cache_directory.join("forge/version_manifests"),
+        problems,
+    );
+    for path in json_files(&cache_directory.join("forge/installer_manifests")) {
+        // installer profiles come in several formats, any of them is fine
+        let parses = read_json::<ForgeInstallerProfile>(&path).is_ok()
+            || read_json::<ForgeInstallerProfileV2>(&path).is_ok()
+            || read_json::<ForgeInstallerProfileV1_5>(&path).is_ok();
+        if !parses {
+            problems.push(format!(
+                "{}: not a known Forge installer profile",
+                path.display()
+            ));
+        }
+    }
+    check_directory::<FabricJarInfo>(&cache_directory.join("fabric/jars"), problems);
+    check_directory::<FabricInstallerDataV1>(
+        &cache_directory.join("fabric/loader-installer-json"),
+        problems,
+    );
+    check_file::<LiteloaderIndex>(&cache_directory.join("liteloader/versions.json"), problems);
+}
+
+/// Checks that every legacy override belongs to a Minecraft version of the cached Mojang index.
+fn verify_legacy_overrides(
+    cache_directory: &Path,
+    legacy_overrides: Option<&Path>,
+    problems: &mut Vec<String>,
+) {
+    let overrides = match LegacyOverrideIndex::load(legacy_overrides) {
+        Ok(overrides) => overrides,
+        Err(e) => {
+            let path = legacy_overrides.unwrap_or(Path::new("legacy overrides"));
+            problems.push(format!("{}: {}", path.display(), e));
+            return;
+        }
+    };
+    let index_path = cache_directory.join("mojang/version_manifest_v2.json");
+    if let Ok(index) = read_json::<MojangIndex>(&index_path) {
+        for version in overrides.unknown_versions(&index) {
+            problems.push(format!(
+                "legacy overrides: {} isn't a known Minecraft version",
+                version
+            ));
+        }
+        for version in overrides.missing_versions(&index) {
+            problems.push(format!("legacy overrides: {} has no override", version));
+        }
+    }
+}
+
+/// Checks the generated files with the PolyMC validator, only its errors are problems.
+fn verify_output(output_directory: &Path, problems: &mut Vec<String>) {
+    info!("Verifying output {}...", output_directory.display());
+    for diagnostic in polymc::validate_directory(output_directory) {
+        match diagnostic.severity {
+            Severity::Error => problems.push(diagnostic.to_string()),
+            Severity::Warning => warn!("{}", diagnostic),
+        }
+    }
+}
+
+fn read_json<T>(path: &Path) -> Result<T, String>
+where
+    T: DeserializeOwned,
+{
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    serde_json::from_reader(std::io::BufReader::new(file)).map_err(|e| e.to_string())
+}
+
+/// Returns all JSON files in a directory, or nothing if it doesn't exist.
+pub fn json_files(directory: &Path) -> Vec<PathBuf> {
+    let mut files = std::fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|extension| extension == "json") == Some(true))
+        .collect::<Vec<PathBuf>>();
+    files.sort();
+    files
+}
+
+/// Parses a file, if it exists.
+fn check_file<T>(path: &Path, problems: &mut Vec<String>)
+where
+    T: DeserializeOwned,
+{
+    if !path.is_file() {
+        return;
+    }
+
+    if let Err(e) = read_json::<T>(path) {
+        problems.push(format!("{}: {}", path.display(), e));
+    }
+}
+
+/// Parses every JSON file of a directory.
+fn check_directory<T>(directory: &Path, problems: &mut Vec<String>)
+where
+    T: DeserializeOwned,
+{
+    for path in json_files(directory) {
+        check_file::<T>(&path, problems);
+    }
+}
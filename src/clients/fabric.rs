@@ -1,32 +1,31 @@
-use std::io::{Read, Write};
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use chrono::DateTime;
-use http_cache_reqwest::{CACacheManager, Cache, HttpCache};
-use reqwest::Client;
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
-
-use crate::models::fabric::FabricJarInfo;
-
-fn get_maven_url(maven_key: &str, server: &str, ext: &str) -> String {
-    let maven_parts = maven_key.splitn(3, ':').collect::<Vec<&str>>();
-    let maven_ver_url = format!(
-        "{}{}/{}/{}/",
-        server,
-        maven_parts[0].replace('.', "/"),
-        maven_parts[1],
-        maven_parts[2]
-    );
-    let maven_url = format!(
-        "{}{}-{}{}",
-        maven_ver_url, maven_parts[1], maven_parts[2], ext
-    );
-    maven_url
+use reqwest_middleware::ClientWithMiddleware;
+
+use crate::models::{
+    fabric::FabricJarInfo,
+    misc::GradleSpecifier,
+    report::{RemovalPolicy, UpdateReport},
+};
+
+use super::{build_client, maven::MavenClient, JarInfo};
+
+/// Parses the Maven coordinate of a Fabric meta entry and points it at a file with the given extension.
+fn maven_specifier(maven_key: &str, extension: &str) -> std::io::Result<GradleSpecifier> {
+    let mut specifier: GradleSpecifier = maven_key.parse().map_err(std::io::Error::other)?;
+    specifier.extension = Some(extension.to_string());
+    Ok(specifier)
 }
 
 pub struct FabricUpdater {
     client: ClientWithMiddleware,
+    maven: MavenClient,
     cache_directory: PathBuf,
+    report: Mutex<UpdateReport>,
+    removal_policy: RemovalPolicy,
 }
 
 impl FabricUpdater {
@@ -34,15 +33,7 @@ impl FabricUpdater {
     where
         P: AsRef<Path>,
     {
-        let client = ClientBuilder::new(Client::new())
-            .with(Cache(HttpCache {
-                mode: http_cache_reqwest::CacheMode::Default,
-                manager: CACacheManager {
-                    path: "./http_cache".to_string(),
-                },
-                options: None,
-            }))
-            .build();
+        let client = build_client(false);
         // ensure the cache path and some subdirectories exist
         std::fs::create_dir_all(cache_directory.as_ref().join("fabric/meta-v2")).unwrap();
         std::fs::create_dir_all(
@@ -54,11 +45,62 @@ impl FabricUpdater {
         std::fs::create_dir_all(cache_directory.as_ref().join("fabric/jars")).unwrap();
 
         Self {
+            maven: MavenClient::with_client(client.clone(), "https://maven.fabricmc.net/"),
             client,
             cache_directory: cache_directory.as_ref().to_path_buf(),
+            report: Mutex::new(UpdateReport::new("fabric")),
+            removal_policy: RemovalPolicy::default(),
         }
     }
 
+    /// Sets what happens to versions which were removed upstream.
+    pub fn with_removal_policy(mut self, removal_policy: RemovalPolicy) -> Self {
+        self.removal_policy = removal_policy;
+        self
+    }
+
+    /// Only answers requests from the HTTP cache, without contacting upstream.
+    pub fn with_offline_mode(mut self, offline: bool) -> Self {
+        self.client = build_client(offline);
+        self.maven = MavenClient::with_client(self.client.clone(), self.maven.repository_url());
+        self
+    }
+
+    fn record_download(&self, bytes: usize) {
+        self.report.lock().unwrap().record_download(bytes as u64);
+    }
+
+    fn record_file<P>(&self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.report
+            .lock()
+            .unwrap()
+            .record_file(path, &self.cache_directory);
+    }
+
+    /// Reads a cached meta index, keyed by the Maven coordinate of each entry.
+    ///
+    /// This includes entries which were removed upstream and kept by the removal policy.
+    fn read_meta_index(&self, component: &str) -> HashMap<String, serde_json::Value> {
+        let index_path = self
+            .cache_directory
+            .join(format!("fabric/meta-v2/{}.json", component));
+        let index: Vec<serde_json::Value> = std::fs::File::open(index_path)
+            .ok()
+            .and_then(|file| serde_json::from_reader(file).ok())
+            .unwrap_or_default();
+
+        index
+            .into_iter()
+            .filter_map(|entry| {
+                let maven = entry.get("maven")?.as_str()?.to_string();
+                Some((maven, entry))
+            })
+            .collect()
+    }
+
     async fn download_json_file<P>(&self, path: P, url: &str) -> std::io::Result<serde_json::Value>
     where
         P: AsRef<Path>,
@@ -68,14 +110,17 @@ impl FabricUpdater {
             .get(url)
             .send()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+            .map_err(std::io::Error::other)?
             .error_for_status()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
-            .json::<serde_json::Value>()
+            .map_err(std::io::Error::other)?
+            .bytes()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-        let mut file = std::fs::File::create(path)?;
+            .map_err(std::io::Error::other)?;
+        self.record_download(response.len());
+        let response: serde_json::Value = serde_json::from_slice(&response)?;
+        let mut file = std::fs::File::create(&path)?;
         serde_json::to_writer_pretty(&mut file, &response)?;
+        self.record_file(&path);
         Ok(response)
     }
 
@@ -88,16 +133,15 @@ impl FabricUpdater {
             .get(url)
             .send()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+            .map_err(std::io::Error::other)?
             .error_for_status()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-        let mut file = std::fs::File::create(path)?;
+            .map_err(std::io::Error::other)?;
+        let mut file = std::fs::File::create(&path)?;
         // write response.bytes() to file
-        let bytes = response
-            .bytes()
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        let bytes = response.bytes().await.map_err(std::io::Error::other)?;
+        self.record_download(bytes.len());
         file.write_all(&bytes)?;
+        self.record_file(&path);
 
         Ok(())
     }
@@ -108,60 +152,37 @@ impl FabricUpdater {
     {
         let jar_path = format!("{}.jar", path.as_ref().to_str().unwrap());
         self.download_binary_file(&jar_path, url).await?;
-        let mut timestamp =
-            chrono::DateTime::from_utc(chrono::NaiveDateTime::from_timestamp(0, 0), chrono::Utc);
-        let mut jar_file = zip::ZipArchive::new(std::fs::File::open(&jar_path)?)?;
-        for i in 0..jar_file.len() {
-            let mut file = jar_file.by_index(i)?;
-            let file_last_modified = file.last_modified();
-            let file_last_modified = chrono::DateTime::<chrono::Utc>::from_utc(
-                chrono::NaiveDateTime::new(
-                    chrono::NaiveDate::from_ymd(
-                        file_last_modified.year().into(),
-                        file_last_modified.month().into(),
-                        file_last_modified.day().into(),
-                    ),
-                    chrono::NaiveTime::from_hms(
-                        file_last_modified.hour().into(),
-                        file_last_modified.minute().into(),
-                        file_last_modified.second().into(),
-                    ),
-                ),
-                chrono::Utc,
-            );
-            if file_last_modified > timestamp {
-                timestamp = file_last_modified;
-            }
-        }
-
-        let sha1_hash = ring::digest::digest(
-            &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
-            &std::fs::read(&jar_path)?,
-        );
-        let sha1 = data_encoding::HEXLOWER.encode(sha1_hash.as_ref());
-        let sha256_hash = ring::digest::digest(&ring::digest::SHA256, &std::fs::read(&jar_path)?);
-        let sha256 = data_encoding::HEXLOWER.encode(sha256_hash.as_ref());
-        let size = std::fs::metadata(&jar_path)?.len();
+        let jar_info = JarInfo::read(&jar_path)?;
 
         let data = FabricJarInfo {
-            release_time: Some(timestamp),
-            sha1: Some(sha1),
-            sha256: Some(sha256),
-            size: Some(size),
+            release_time: Some(jar_info.release_time),
+            sha1: Some(jar_info.sha1),
+            sha256: Some(jar_info.sha256),
+            size: Some(jar_info.size),
+            #[cfg(feature = "extra-fields")]
+            extra: Default::default(),
         };
-        let mut file = std::fs::File::create(format!("{}.json", path.as_ref().to_str().unwrap()))?;
+        let info_path = format!("{}.json", path.as_ref().to_str().unwrap());
+        let mut file = std::fs::File::create(&info_path)?;
         serde_json::to_writer_pretty(&mut file, &data)?;
+        self.record_file(&info_path);
 
         Ok(())
     }
 
-    pub async fn generate_meta_cache(&self) -> std::io::Result<()> {
+    /// Updates the Fabric metadata and returns a report of what changed.
+    pub async fn generate_meta_cache(&self) -> std::io::Result<UpdateReport> {
+        *self.report.lock().unwrap() = UpdateReport::new("fabric");
+
         for component in &["intermediary", "loader"] {
+            let previous_index = self.read_meta_index(component);
             info!("Downloading JSON for {} meta...", component);
-            let index = self
+            let index_path = self
+                .cache_directory
+                .join(format!("fabric/meta-v2/{}.json", component));
+            let mut index = self
                 .download_json_file(
-                    self.cache_directory
-                        .join(format!("fabric/meta-v2/{}.json", component)),
+                    &index_path,
                     &format!("https://meta.fabricmc.net/v2/versions/{}", component),
                 )
                 .await?;
@@ -169,7 +190,7 @@ impl FabricUpdater {
                 let it_value = it_value.as_object().unwrap();
                 let it_maven = it_value.get("maven").unwrap().as_str().unwrap();
                 info!("Downloading jar for artifact {}...", it_maven);
-                let jar_maven_url = get_maven_url(it_maven, "https://maven.fabricmc.net/", ".jar");
+                let jar_maven_url = self.maven.artifact_url(&maven_specifier(it_maven, "jar")?);
                 self.process_jar_file(
                     self.cache_directory
                         .join(format!("fabric/jars/{}", it_maven.replace(':', "."))),
@@ -177,6 +198,55 @@ impl FabricUpdater {
                 )
                 .await?;
             }
+
+            let current_index = self.read_meta_index(component);
+            self.report
+                .lock()
+                .unwrap()
+                .record_changes(&upstream_entries(&previous_index), &current_index);
+
+            let mut missing_entries = previous_index
+                .into_iter()
+                .filter(|(maven, _)| !current_index.contains_key(maven))
+                .collect::<Vec<(String, serde_json::Value)>>();
+            missing_entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            info!(
+                "Applying removal policy {:?} to {} {} versions missing upstream...",
+                self.removal_policy,
+                missing_entries.len(),
+                component
+            );
+            if self.removal_policy.keeps_entries() {
+                if !missing_entries.is_empty() {
+                    let index_entries = index.as_array_mut().unwrap();
+                    for (_, mut entry) in missing_entries {
+                        entry["removed"] = serde_json::Value::Bool(true);
+                        index_entries.push(entry);
+                    }
+                    let mut file = std::fs::File::create(&index_path)?;
+                    serde_json::to_writer_pretty(&mut file, &index)?;
+                    self.record_file(&index_path);
+                }
+            } else {
+                for (maven, entry) in missing_entries {
+                    let jar_path = format!("fabric/jars/{}", maven.replace(':', "."));
+                    let mut removed_files = vec![
+                        PathBuf::from(format!("{}.jar", jar_path)),
+                        PathBuf::from(format!("{}.json", jar_path)),
+                    ];
+                    if let Some(version) = entry.get("version").and_then(|v| v.as_str()) {
+                        removed_files.push(PathBuf::from(format!(
+                            "fabric/loader-installer-json/{}.json",
+                            version
+                        )));
+                    }
+                    self.removal_policy.apply_to_files(
+                        &self.cache_directory,
+                        &removed_files,
+                        &mut self.report.lock().unwrap(),
+                    )?;
+                }
+            }
         }
 
         let loader_json =
@@ -184,14 +254,17 @@ impl FabricUpdater {
         let loader_version_index: serde_json::Value = serde_json::from_reader(loader_json)?;
         let loader_version_index = loader_version_index.as_array().unwrap();
         for it_value in loader_version_index {
+            if is_removed(it_value) {
+                continue;
+            }
+
             let it_value = it_value.as_object().unwrap();
             let it_maven = it_value.get("maven").unwrap().as_str().unwrap();
-            let maven_url = get_maven_url(
-                it_maven,
-                "https://maven.fabricmc.net/",
-                ".json",
+            let maven_url = self.maven.artifact_url(&maven_specifier(it_maven, "json")?);
+            info!(
+                "Downloading installer JSON for artifact {} from {}...",
+                it_maven, &maven_url
             );
-            info!("Downloading installer JSON for artifact {} from {}...", it_maven, &maven_url);
             self.download_json_file(
                 self.cache_directory.join(format!(
                     "fabric/loader-installer-json/{}.json",
@@ -202,6 +275,22 @@ impl FabricUpdater {
             .await?;
         }
 
-        Ok(())
+        Ok(std::mem::take(&mut *self.report.lock().unwrap()))
     }
 }
+
+/// Returns `true` if the meta entry was removed upstream and only kept by the removal policy.
+fn is_removed(entry: &serde_json::Value) -> bool {
+    entry.get("removed").and_then(|removed| removed.as_bool()) == Some(true)
+}
+
+/// Filters a meta index down to the entries which existed upstream when it was written.
+fn upstream_entries(
+    index: &HashMap<String, serde_json::Value>,
+) -> HashMap<String, serde_json::Value> {
+    index
+        .iter()
+        .filter(|(_, entry)| !is_removed(entry))
+        .map(|(maven, entry)| (maven.clone(), entry.clone()))
+        .collect()
+}
@@ -1,31 +1,37 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     io::{Read, Write},
     path::{Path, PathBuf},
+    sync::Mutex,
 };
 
-use http_cache_reqwest::{CACacheManager, Cache, HttpCache};
-use reqwest::Client;
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_middleware::ClientWithMiddleware;
 
 use crate::models::{
     forge::{
-        DerivedForgeIndex, ForgeEntry, ForgeFile, ForgeInstallerProfile, ForgeInstallerProfileV1_5,
-        ForgeInstallerProfileV2, ForgeMCVersionInfo, ForgeVersion, InstallerInfo,
+        changelog_excerpt, DerivedForgeIndex, ForgeBranchPromotions, ForgeEntry, ForgeFile,
+        ForgeInstallerProfile, ForgeInstallerProfileV1_5, ForgeInstallerProfileV2, ForgeLegacyInfo,
+        ForgeLegacyInfoList, ForgeMCVersionInfo, ForgePromotionKey, ForgePromotionKind,
+        ForgeVersion, InstallerInfo,
     },
+    maven::MavenMetadata,
     mojang::MojangVersionFile,
+    report::{RemovalPolicy, UpdateReport},
 };
 
-static FORGE_LEGACY_INFO: &str = include_str!("static_files/forge_legacyinfo.json");
+use super::{build_client, maven::MavenClient, JarInfo};
 
 lazy_static! {
-    static ref PROMOTED_KEY_REGEX: regex::Regex = regex::Regex::new("(?P<mc>[^-]+)-(?P<promotion>(latest)|(recommended))(-(?P<branch>[a-zA-Z0-9\\.]+))?").unwrap();
+    static ref HASH_PROCESSING_REGEX: regex::Regex = regex::Regex::new(r"\W").unwrap();
     static ref VERSION_REGEX: regex::Regex = regex::Regex::new("^(?P<mc>[0-9a-zA-Z_\\.]+)-(?P<ver>[0-9\\.]+\\.(?P<build>[0-9]+))(-(?P<branch>[a-zA-Z0-9\\.]+))?$").unwrap();
 }
 
 pub struct ForgeUpdater {
     client: ClientWithMiddleware,
+    maven: MavenClient,
     cache_directory: PathBuf,
+    report: Mutex<UpdateReport>,
+    removal_policy: RemovalPolicy,
 }
 
 impl ForgeUpdater {
@@ -33,15 +39,7 @@ impl ForgeUpdater {
     where
         P: AsRef<Path>,
     {
-        let client = ClientBuilder::new(Client::new())
-            .with(Cache(HttpCache {
-                mode: http_cache_reqwest::CacheMode::Default,
-                manager: CACacheManager {
-                    path: "./http_cache".to_string(),
-                },
-                options: None,
-            }))
-            .build();
+        let client = build_client(false);
         // ensure the cache path and some subdirectories exist
         std::fs::create_dir_all(cache_directory.as_ref().join("forge/jars")).unwrap();
         std::fs::create_dir_all(cache_directory.as_ref().join("forge/installer_info")).unwrap();
@@ -49,41 +47,104 @@ impl ForgeUpdater {
             .unwrap();
         std::fs::create_dir_all(cache_directory.as_ref().join("forge/version_manifests")).unwrap();
         std::fs::create_dir_all(cache_directory.as_ref().join("forge/files_manifests")).unwrap();
+        std::fs::create_dir_all(cache_directory.as_ref().join("forge/changelogs")).unwrap();
 
         Self {
+            maven: MavenClient::with_client(client.clone(), "https://maven.minecraftforge.net/"),
             client,
             cache_directory: cache_directory.as_ref().to_path_buf(),
+            report: Mutex::new(UpdateReport::new("forge")),
+            removal_policy: RemovalPolicy::default(),
         }
     }
 
-    pub async fn generate_meta_cache(&self) -> std::io::Result<()> {
-        info!("Downloading remote version list from Forge...");
-        let remote_list = self
+    /// Sets what happens to versions which were removed upstream.
+    pub fn with_removal_policy(mut self, removal_policy: RemovalPolicy) -> Self {
+        self.removal_policy = removal_policy;
+        self
+    }
+
+    /// Only answers requests from the HTTP cache, without contacting upstream.
+    pub fn with_offline_mode(mut self, offline: bool) -> Self {
+        self.client = build_client(offline);
+        self.maven = MavenClient::with_client(self.client.clone(), self.maven.repository_url());
+        self
+    }
+
+    fn record_download(&self, bytes: usize) {
+        self.report.lock().unwrap().record_download(bytes as u64);
+    }
+
+    fn record_file<P>(&self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.report
+            .lock()
+            .unwrap()
+            .record_file(path, &self.cache_directory);
+    }
+
+    /// Downloads and parses the JSON document at the given URL.
+    async fn download_json(&self, url: &str) -> std::io::Result<serde_json::Value> {
+        let response = self
             .client
-            .get("https://files.minecraftforge.net/net/minecraftforge/forge/maven-metadata.json")
+            .get(url)
             .send()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?
-            .json::<serde_json::Value>()
+            .map_err(|e| std::io::Error::other(format!("{}", e)))?
+            .bytes()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?;
+            .map_err(|e| std::io::Error::other(format!("{}", e)))?;
+        self.record_download(response.len());
+
+        Ok(serde_json::from_slice(&response)?)
+    }
+
+    /// Writes the given value as pretty-printed JSON to the given path.
+    fn write_json<P, T>(&self, path: P, value: &T) -> std::io::Result<()>
+    where
+        P: AsRef<Path>,
+        T: serde::Serialize,
+    {
+        let file = std::fs::File::create(&path)?;
+        serde_json::to_writer_pretty(file, value)?;
+        self.record_file(&path);
+
+        Ok(())
+    }
+
+    /// Updates the Forge metadata and returns a report of what changed.
+    pub async fn generate_meta_cache(&self) -> std::io::Result<UpdateReport> {
+        *self.report.lock().unwrap() = UpdateReport::new("forge");
+        let index_path = self.cache_directory.join("forge/derived_index.json");
+        let previous_versions = std::fs::File::open(&index_path)
+            .ok()
+            .and_then(|file| serde_json::from_reader::<_, DerivedForgeIndex>(file).ok())
+            .and_then(|index| index.versions)
+            .unwrap_or_default();
+
+        info!("Downloading remote version list from the Forge Maven...");
+        let remote_list = version_list(
+            &self
+                .maven
+                .get_metadata("net.minecraftforge", "forge")
+                .await?,
+        );
 
         info!("Downloading promotion list from Forge...");
         let promotions_list = self
-            .client
-            .get("https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json")
-            .send()
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?
-            .json::<serde_json::Value>()
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?;
+            .download_json(
+                "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json",
+            )
+            .await?;
         let mut new_index = DerivedForgeIndex {
             mc_versions: Some(HashMap::new()),
             versions: Some(HashMap::new()),
+            #[cfg(feature = "extra-fields")]
+            extra: Default::default(),
         };
 
-        let mut recommended: Vec<String> = Vec::new();
         let promos = promotions_list
             .as_object()
             .unwrap()
@@ -92,72 +153,52 @@ impl ForgeUpdater {
             .as_object()
             .unwrap();
         info!("Processing promotions...");
+        let mut promotions: Vec<(ForgePromotionKey, String)> = Vec::new();
         for (promo_key, short_version) in promos {
-            let key_match = PROMOTED_KEY_REGEX.captures(promo_key);
-            if key_match.is_none() {
-                info!(
-                    "Skipping promo key {}, key was not in the right format",
-                    promo_key
-                );
-                continue;
-            }
-            let key_match = key_match.unwrap();
-            if key_match.name("mc").is_none() {
-                info!(
-                    "Skipping promo key {}, it has no Minecraft version",
-                    promo_key
-                );
-                continue;
-            }
-            if key_match.name("branch").is_some() {
-                info!("Skipping promo key {}, it has a branch", promo_key);
-                continue;
-            } else if key_match.name("promotion").unwrap().as_str() == "recommended" {
-                info!(
-                    "Adding recommendation for version {}",
-                    short_version.as_str().unwrap()
-                );
-                recommended.push(short_version.as_str().unwrap().to_string());
-            } else if key_match.name("promotion").unwrap().as_str() == "latest" {
-                continue;
-            } else {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Unknown promotion type: {}", promo_key),
-                ));
-            }
+            let key = match promo_key.parse::<ForgePromotionKey>() {
+                Ok(key) => key,
+                Err(e) => {
+                    info!("Skipping promo key {}: {}", promo_key, e);
+                    continue;
+                }
+            };
+            let short_version = match short_version.as_str() {
+                Some(short_version) => short_version,
+                None => {
+                    warn!(
+                        "Skipping promo key {}, its version isn't a string",
+                        promo_key
+                    );
+                    continue;
+                }
+            };
+            promotions.push((key, short_version.to_string()));
         }
 
         for (mc_version, value) in remote_list.as_object().unwrap() {
             if !value.is_array() {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Invalid metadata format while processing version {} (MC version value was not an array)", mc_version),
+                return Err(std::io::Error::other(format!("Invalid metadata format while processing version {} (MC version value was not an array)", mc_version),
                 ));
             }
             let value = value.as_array().unwrap();
             for long_version in value {
                 if !long_version.is_string() {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Invalid metadata format while processing version {} (Forge version is not a string)", mc_version),
+                    return Err(std::io::Error::other(format!("Invalid metadata format while processing version {} (Forge version is not a string)", mc_version),
                     ));
                 }
                 let long_version = long_version.as_str().unwrap();
                 let version_match = VERSION_REGEX.captures(long_version);
                 if version_match.is_none() {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Invalid metadata while processing version {} (Version doesn't match regex)", mc_version),
+                    return Err(std::io::Error::other(format!("Invalid metadata while processing version {} (Version doesn't match regex)", mc_version),
                     ));
                 }
                 let version_match = version_match.unwrap();
                 let mc_group = version_match.name("mc").unwrap();
                 if mc_group.as_str() != mc_version {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Invalid metadata while processing version {} (MC version doesn't match)", mc_version),
-                    ));
+                    return Err(std::io::Error::other(format!(
+                        "Invalid metadata while processing version {} (MC version doesn't match)",
+                        mc_version
+                    )));
                 }
                 info!(
                     "Downloading manifest for MC version {}, Forge version {}",
@@ -175,8 +216,6 @@ impl ForgeUpdater {
                 let version = version_match.name("ver").unwrap().as_str();
                 let branch = version_match.name("branch").map(|x| x.as_str().to_string());
 
-                let is_recommended = recommended.contains(&version.to_string());
-
                 let entry = ForgeEntry {
                     long_version: long_version.to_string(),
                     mc_version: mc_version.to_string(),
@@ -184,8 +223,10 @@ impl ForgeUpdater {
                     version: version.to_string(),
                     branch,
                     latest: Some(false),
-                    recommended: Some(is_recommended),
+                    recommended: Some(false),
                     files: Some(files),
+                    removed: None,
+                    changelog: None,
                 };
 
                 new_index
@@ -205,6 +246,7 @@ impl ForgeUpdater {
                             latest: None,
                             recommended: None,
                             versions: Some(Vec::new()),
+                            branches: None,
                         },
                     );
                 }
@@ -218,53 +260,200 @@ impl ForgeUpdater {
                     .as_mut()
                     .unwrap()
                     .push(long_version.to_string());
-                if let Some(recommended) = entry.recommended {
-                    if recommended {
-                        new_index
-                            .mc_versions
-                            .as_mut()
-                            .unwrap()
-                            .get_mut(mc_version)
-                            .unwrap()
-                            .recommended
-                            .replace(long_version.to_string());
-                    }
-                }
+            }
+        }
+
+        let missing_versions = previous_versions
+            .values()
+            .filter(|entry| {
+                !new_index
+                    .versions
+                    .as_ref()
+                    .unwrap()
+                    .contains_key(&entry.long_version)
+            })
+            .cloned()
+            .collect::<Vec<ForgeEntry>>();
+        info!(
+            "Applying removal policy {:?} to {} versions missing upstream...",
+            self.removal_policy,
+            missing_versions.len()
+        );
+        for mut entry in missing_versions {
+            if self.removal_policy.keeps_entries() {
+                entry.removed = Some(true);
+                entry.latest = Some(false);
+                entry.recommended = Some(false);
+                new_index
+                    .mc_versions
+                    .as_mut()
+                    .unwrap()
+                    .entry(entry.mc_version.clone())
+                    .or_insert_with(|| ForgeMCVersionInfo {
+                        latest: None,
+                        recommended: None,
+                        versions: Some(Vec::new()),
+                        branches: None,
+                    })
+                    .versions
+                    .as_mut()
+                    .unwrap()
+                    .push(entry.long_version.clone());
+                new_index
+                    .versions
+                    .as_mut()
+                    .unwrap()
+                    .insert(entry.long_version.clone(), entry);
+            } else {
+                let removed_files = self.cached_files(&entry);
+                self.removal_policy.apply_to_files(
+                    &self.cache_directory,
+                    &removed_files,
+                    &mut self.report.lock().unwrap(),
+                )?;
             }
         }
 
         info!("Post-processing promotions...");
-        for (mc_version, mut info) in new_index.mc_versions.as_mut().unwrap() {
-            let latest_version = info.versions.as_ref().unwrap().iter().last().unwrap();
-            info.latest = Some(latest_version.clone());
-            new_index
-                .versions
-                .as_mut()
-                .unwrap()
-                .get_mut(latest_version)
-                .unwrap()
-                .latest = Some(true);
-            info!(
-                "Added {} as latest version for MC version {}",
-                latest_version, mc_version
+        let versions = new_index.versions.as_mut().unwrap();
+        for (mc_version, info) in new_index.mc_versions.as_mut().unwrap() {
+            // don't rely on the upstream order, sort the builds by their version instead
+            let mc_version_builds = info.versions.as_mut().unwrap();
+            mc_version_builds.sort_by(|a, b| versions[a].cmp_version(&versions[b]));
+
+            // builds which were removed upstream can't be promoted anymore
+            let available_builds = mc_version_builds
+                .iter()
+                .filter(|long_version| versions[*long_version].removed != Some(true))
+                .collect::<Vec<_>>();
+            let in_branch = |long_version: &String, branch: Option<&str>| {
+                branch.is_none() || versions[long_version].branch.as_deref() == branch
+            };
+            let promoted = |kind: ForgePromotionKind, branch: Option<&str>| {
+                let (_, short_version) = promotions.iter().find(|(key, _)| {
+                    &key.mc_version == mc_version
+                        && key.kind == kind
+                        && key.branch.as_deref() == branch
+                })?;
+                let long_version = promoted_build(
+                    &available_builds,
+                    &*versions,
+                    mc_version,
+                    short_version,
+                    branch,
+                );
+                if long_version.is_none() {
+                    warn!(
+                        "Promoted Forge version {} of MC version {} wasn't found",
+                        short_version, mc_version
+                    );
+                }
+                long_version
+            };
+            let newest = |branch: Option<&str>| {
+                available_builds
+                    .iter()
+                    .rev()
+                    .find(|long_version| in_branch(long_version, branch))
+                    .map(|long_version| long_version.to_string())
+            };
+
+            info.latest = promoted(ForgePromotionKind::Latest, None).or_else(|| newest(None));
+            info.recommended = promoted(ForgePromotionKind::Recommended, None);
+
+            let mut branch_names = available_builds
+                .iter()
+                .filter_map(|long_version| versions[*long_version].branch.clone())
+                .collect::<BTreeSet<_>>();
+            branch_names.extend(
+                promotions
+                    .iter()
+                    .filter(|(key, _)| &key.mc_version == mc_version)
+                    .filter_map(|(key, _)| key.branch.clone()),
             );
+            let branches = branch_names
+                .into_iter()
+                .map(|branch| {
+                    let branch_promotions = ForgeBranchPromotions {
+                        latest: promoted(ForgePromotionKind::Latest, Some(&branch))
+                            .or_else(|| newest(Some(&branch))),
+                        recommended: promoted(ForgePromotionKind::Recommended, Some(&branch)),
+                    };
+                    (branch, branch_promotions)
+                })
+                .collect::<BTreeMap<_, _>>();
+
+            let latest_versions = info
+                .latest
+                .iter()
+                .chain(
+                    branches
+                        .values()
+                        .filter_map(|branch| branch.latest.as_ref()),
+                )
+                .cloned()
+                .collect::<Vec<_>>();
+            let recommended_versions = info
+                .recommended
+                .iter()
+                .chain(
+                    branches
+                        .values()
+                        .filter_map(|branch| branch.recommended.as_ref()),
+                )
+                .cloned()
+                .collect::<Vec<_>>();
+            info.branches = (!branches.is_empty()).then_some(branches);
+
+            for long_version in latest_versions {
+                info!(
+                    "Added {} as latest version for MC version {}",
+                    long_version, mc_version
+                );
+                versions.get_mut(&long_version).unwrap().latest = Some(true);
+            }
+            for long_version in recommended_versions {
+                info!(
+                    "Added {} as recommended version for MC version {}",
+                    long_version, mc_version
+                );
+                versions.get_mut(&long_version).unwrap().recommended = Some(true);
+            }
         }
 
-        info!("Dumping index files...");
-        let maven_file =
-            std::fs::File::create(self.cache_directory.join("forge/maven-metadata.json"))?;
-        serde_json::to_writer_pretty(maven_file, &remote_list)?;
+        info!("Extracting changelogs...");
+        for entry in versions.values_mut() {
+            if entry.removed == Some(true) {
+                continue;
+            }
+            let version: ForgeVersion = entry.clone().into();
+            if let Some(changelog) = self.download_changelog(&version).await {
+                entry.changelog = changelog_excerpt(&changelog, &entry.version);
+            }
+        }
 
-        let promotions_file =
-            std::fs::File::create(self.cache_directory.join("forge/promotion_slim.json"))?;
-        serde_json::to_writer_pretty(promotions_file, &promotions_list)?;
+        self.report.lock().unwrap().record_changes(
+            &entry_values(&previous_versions),
+            &entry_values(new_index.versions.as_ref().unwrap()),
+        );
 
-        let index_file =
-            std::fs::File::create(self.cache_directory.join("forge/derived_index.json"))?;
-        serde_json::to_writer_pretty(index_file, &new_index)?;
+        info!("Dumping index files...");
+        self.write_json(
+            self.cache_directory.join("forge/maven-metadata.json"),
+            &remote_list,
+        )?;
+        self.write_json(
+            self.cache_directory.join("forge/promotion_slim.json"),
+            &promotions_list,
+        )?;
+        self.write_json(&index_path, &new_index)?;
 
         info!("Downloading installers and dumping profiles...");
-        for (id, entry) in new_index.versions.as_ref().unwrap() {
+        for entry in new_index.versions.as_ref().unwrap().values() {
+            if entry.removed == Some(true) {
+                continue;
+            }
+
             let version: ForgeVersion = entry.clone().into();
             if version.url().is_none() {
                 info!("Skipping build {}: No valid files", entry.build);
@@ -305,35 +494,28 @@ impl ForgeUpdater {
                         .send()
                         .await
                         .map_err(|e| {
-                            std::io::Error::new(
-                                std::io::ErrorKind::Other,
-                                format!(
-                                    "Failed to download installer for version {}: {}",
-                                    version.long_version, e
-                                ),
-                            )
+                            std::io::Error::other(format!(
+                                "Failed to download installer for version {}: {}",
+                                version.long_version, e
+                            ))
                         })?;
                     if !version_installer.status().is_success() {
-                        return Err(std::io::Error::new(
-                            std::io::ErrorKind::Other,
-                            format!(
-                                "Failed to download installer for version {}: {}",
-                                version.long_version,
-                                version_installer.status()
-                            ),
-                        ));
+                        return Err(std::io::Error::other(format!(
+                            "Failed to download installer for version {}: {}",
+                            version.long_version,
+                            version_installer.status()
+                        )));
                     }
                     let mut installer_file = std::fs::File::create(&jar_file_path)?;
                     let version_installer = version_installer.bytes().await.map_err(|e| {
-                        std::io::Error::new(
-                            std::io::ErrorKind::Other,
-                            format!(
-                                "Failed to download installer for version {}: {}",
-                                version.long_version, e
-                            ),
-                        )
+                        std::io::Error::other(format!(
+                            "Failed to download installer for version {}: {}",
+                            version.long_version, e
+                        ))
                     })?;
                     installer_file.write_all(&version_installer)?;
+                    self.record_download(version_installer.len());
+                    self.record_file(&jar_file_path);
                 }
 
                 info!(
@@ -347,19 +529,13 @@ impl ForgeUpdater {
                     if let Ok(version_json_entry) = zip.by_name("version.json") {
                         let version_json_data: serde_json::Result<MojangVersionFile> =
                             serde_json::from_reader(version_json_entry);
-                        if version_json_data.is_err() {
+                        if let Ok(version_json_data) = version_json_data {
+                            self.write_json(&version_json_file_path, &version_json_data)?;
+                        } else {
                             warn!(
                                 "Failed to parse version.json for version {}",
                                 version.long_version
                             );
-                        } else {
-                            let version_json_data = version_json_data.unwrap();
-                            let mut version_json_file =
-                                std::fs::File::create(&version_json_file_path)?;
-                            serde_json::to_writer_pretty(
-                                &mut version_json_file,
-                                &version_json_data,
-                            )?;
                         }
                     }
 
@@ -378,38 +554,17 @@ impl ForgeUpdater {
                             ForgeInstallerProfileV1_5,
                         > = serde_json::from_str(&install_profile_data_str);
 
-                        if install_profile_data.is_ok() {
-                            let install_profile_data = install_profile_data.unwrap();
-                            let mut install_profile_file =
-                                std::fs::File::create(&profile_file_path)?;
-                            serde_json::to_writer_pretty(
-                                &mut install_profile_file,
-                                &install_profile_data,
-                            )?;
-                        } else if install_profile_data_v2.is_ok() {
-                            let install_profile_data_v2 = install_profile_data_v2.unwrap();
-                            let mut install_profile_file =
-                                std::fs::File::create(&profile_file_path)?;
-                            serde_json::to_writer_pretty(
-                                &mut install_profile_file,
-                                &install_profile_data_v2,
-                            )?;
-                        } else if install_profile_data_v1_5.is_ok() {
-                            let install_profile_data_v1_5 = install_profile_data_v1_5.unwrap();
-                            let mut install_profile_file =
-                                std::fs::File::create(&profile_file_path)?;
-                            serde_json::to_writer_pretty(
-                                &mut install_profile_file,
-                                &install_profile_data_v1_5,
-                            )?;
+                        if let Ok(install_profile_data) = install_profile_data {
+                            self.write_json(&profile_file_path, &install_profile_data)?;
+                        } else if let Ok(install_profile_data_v2) = install_profile_data_v2 {
+                            self.write_json(&profile_file_path, &install_profile_data_v2)?;
+                        } else if let Ok(install_profile_data_v1_5) = install_profile_data_v1_5 {
+                            self.write_json(&profile_file_path, &install_profile_data_v1_5)?;
                         } else if version.is_supported() {
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::Other,
-                                format!(
-                                    "Failed to parse install_profile.json for version {}",
-                                    version.long_version
-                                ),
-                            ));
+                            return Err(std::io::Error::other(format!(
+                                "Failed to parse install_profile.json for version {}",
+                                version.long_version
+                            )));
                         } else {
                             warn!(
                                 "Failed to parse install_profile.json for version {}",
@@ -438,107 +593,578 @@ impl ForgeUpdater {
                         sha1_hash: Some(sha1),
                         sha256_hash: Some(sha256),
                         size: Some(size),
+                        #[cfg(feature = "extra-fields")]
+                        extra: Default::default(),
                     };
-                    let mut installer_info_file = std::fs::File::create(&installer_info_file_path)?;
-                    serde_json::to_writer_pretty(&mut installer_info_file, &installer_info)?;
+                    self.write_json(&installer_info_file_path, &installer_info)?;
                 }
             }
         }
 
-        // write static legacy info if it doesn't exist
-        if !PathBuf::new()
-            .join("static/forge-legacyinfo.json")
-            .is_file()
-        {
-            let mut forge_legacyinfo_file =
-                std::fs::File::create(&PathBuf::new().join("static/forge-legacyinfo.json"))?;
-            let _ = forge_legacyinfo_file.write(FORGE_LEGACY_INFO.as_bytes())?;
+        info!("Building legacy info...");
+        self.update_legacy_info(new_index.versions.as_ref().unwrap().values())
+            .await?;
+
+        Ok(std::mem::take(&mut *self.report.lock().unwrap()))
+    }
+
+    /// Adds the release time and hashes of the universal jars of builds which predate the installer to
+    /// `forge/legacy_info.json`, downloading the jars which aren't cached.
+    ///
+    /// Builds which are already listed are kept, so every jar is only processed once.
+    async fn update_legacy_info<'a, I>(&self, entries: I) -> std::io::Result<()>
+    where
+        I: Iterator<Item = &'a ForgeEntry>,
+    {
+        let legacy_info_path = self.cache_directory.join("forge/legacy_info.json");
+        let mut legacy_info_list: ForgeLegacyInfoList = if legacy_info_path.is_file() {
+            serde_json::from_reader(std::fs::File::open(&legacy_info_path)?)?
+        } else {
+            ForgeLegacyInfoList {
+                number: None,
+                #[cfg(feature = "extra-fields")]
+                extra: Default::default(),
+            }
+        };
+        let legacy_info = legacy_info_list.number.get_or_insert_with(HashMap::new);
+
+        let mut changed = !legacy_info_path.is_file();
+        for entry in entries {
+            let version: ForgeVersion = entry.clone().into();
+            if entry.removed == Some(true)
+                || version.uses_installer()
+                || !version.is_supported()
+                || legacy_info.contains_key(&version.build.to_string())
+            {
+                continue;
+            }
+
+            let jar_file_path = self
+                .cache_directory
+                .join(format!("forge/jars/{}", version.file_name().unwrap()));
+            if !jar_file_path.is_file() {
+                info!("Downloading Forge version {}...", version.long_version);
+                let response = self
+                    .client
+                    .get(version.url().unwrap())
+                    .send()
+                    .await
+                    .map_err(std::io::Error::other)?
+                    .error_for_status()
+                    .map_err(std::io::Error::other)?;
+                let bytes = response.bytes().await.map_err(std::io::Error::other)?;
+                self.record_download(bytes.len());
+                std::fs::write(&jar_file_path, &bytes)?;
+                self.record_file(&jar_file_path);
+            }
+
+            legacy_info.insert(
+                version.build.to_string(),
+                read_legacy_info(&version, &jar_file_path)?,
+            );
+            changed = true;
+        }
+
+        if changed {
+            self.write_json(&legacy_info_path, &legacy_info_list)?;
         }
 
         Ok(())
     }
 
+    /// Returns the changelog of a version, downloading it if it isn't cached.
+    ///
+    /// Changelogs are optional, so failures are only logged. Builds without a changelog are cached as an empty file,
+    /// so they aren't requested again.
+    async fn download_changelog(&self, version: &ForgeVersion) -> Option<String> {
+        let changelog_url = version.changelog_url.as_ref()?;
+        let changelog_path = self
+            .cache_directory
+            .join(format!("forge/changelogs/{}.txt", version.long_version));
+        if let Ok(changelog) = std::fs::read_to_string(&changelog_path) {
+            return (!changelog.is_empty()).then_some(changelog);
+        }
+
+        info!(
+            "Downloading changelog for version {}...",
+            version.long_version
+        );
+        let response = match self.client.get(changelog_url).send().await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                warn!(
+                    "Failed to download changelog for version {}: {}",
+                    version.long_version,
+                    response.status()
+                );
+                // the build has no changelog, other failures are retried on the next run
+                if response.status().is_client_error() {
+                    match std::fs::write(&changelog_path, "") {
+                        Ok(()) => self.record_file(&changelog_path),
+                        Err(e) => warn!(
+                            "Failed to write changelog for version {}: {}",
+                            version.long_version, e
+                        ),
+                    }
+                }
+                return None;
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to download changelog for version {}: {}",
+                    version.long_version, e
+                );
+                return None;
+            }
+        };
+        let changelog = match response.bytes().await {
+            Ok(changelog) => changelog,
+            Err(e) => {
+                warn!(
+                    "Failed to download changelog for version {}: {}",
+                    version.long_version, e
+                );
+                return None;
+            }
+        };
+        self.record_download(changelog.len());
+        let changelog = String::from_utf8_lossy(&changelog).into_owned();
+        match std::fs::write(&changelog_path, &changelog) {
+            Ok(()) => self.record_file(&changelog_path),
+            Err(e) => warn!(
+                "Failed to write changelog for version {}: {}",
+                version.long_version, e
+            ),
+        }
+
+        Some(changelog)
+    }
+
+    /// Returns the paths of all cached files of the given version, relative to the cache directory.
+    fn cached_files(&self, entry: &ForgeEntry) -> Vec<PathBuf> {
+        let version: ForgeVersion = entry.clone().into();
+        let mut files = vec![
+            PathBuf::from(format!(
+                "forge/installer_info/{}.json",
+                version.long_version
+            )),
+            PathBuf::from(format!(
+                "forge/installer_manifests/{}.json",
+                version.long_version
+            )),
+            PathBuf::from(format!(
+                "forge/version_manifests/{}.json",
+                version.long_version
+            )),
+            PathBuf::from(format!(
+                "forge/files_manifests/{}.json",
+                version.long_version
+            )),
+            PathBuf::from(format!("forge/changelogs/{}.txt", version.long_version)),
+        ];
+        if let Some(file_name) = version.file_name() {
+            files.push(PathBuf::from(format!("forge/jars/{}", file_name)));
+        }
+
+        files
+    }
+
+    /// Returns every file of a Forge build, from its cached or downloaded file manifest, sorted by classifier and
+    /// extension.
     pub async fn download_single_forge_file_manifest(
         &self,
         long_version: &str,
-    ) -> std::io::Result<HashMap<String, ForgeFile>> {
+    ) -> std::io::Result<Vec<ForgeFile>> {
         let manifest_path = self
             .cache_directory
             .join(format!("forge/files_manifests/{}.json", long_version));
         let mut from_file = false;
         let files_json: serde_json::Value;
         if manifest_path.is_file() {
-            files_json =
-                serde_json::from_reader(std::fs::File::open(&manifest_path).unwrap()).unwrap();
+            files_json = serde_json::from_reader(std::fs::File::open(&manifest_path)?)?;
             from_file = true;
             info!("Using cached file manifest for version {}", long_version);
         } else {
             files_json = self
-                .client
-                .get(format!(
+                .download_json(&format!(
                     "https://files.minecraftforge.net/net/minecraftforge/forge/{}/meta.json",
                     long_version
                 ))
-                .send()
-                .await
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?
-                .json::<serde_json::Value>()
-                .await
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?;
+                .await?;
         }
 
-        let mut file_map: HashMap<String, ForgeFile> = HashMap::new();
-        for (classifier, extension_obj) in files_json
-            .as_object()
-            .unwrap()
-            .get("classifiers")
-            .unwrap()
-            .as_object()
-            .unwrap()
-        {
-            let extension_obj = extension_obj.as_object().expect("a json object");
-            let mut index = 0;
-            let mut inserted = false;
-            while index < extension_obj.len() {
-                let (extension, hash) = extension_obj.iter().last().unwrap();
-                if !hash.is_string() {
-                    warn!("{}: Skipping missing hash for {}", long_version, extension);
-                    info!("{}", serde_json::to_string_pretty(&extension_obj).unwrap());
-                    index += 1;
-                    continue;
-                }
-                let hash = hash.as_str().unwrap();
+        let files = parse_file_manifest(long_version, &files_json)?;
+
+        if !from_file {
+            std::fs::write(&manifest_path, serde_json::to_string_pretty(&files_json)?)?;
+            self.record_file(&manifest_path);
+        }
 
-                let processing_regex = regex::Regex::new(r"\W").unwrap();
-                let processed_hash = processing_regex.replacen(hash, 1, "");
-                if processed_hash.len() != 32 {
-                    warn!("{}: Skipping invalid hash for {}", long_version, extension);
-                    index += 1;
+        Ok(files)
+    }
+}
+
+/// Returns every file listed in the file manifest of a Forge build, sorted by classifier and extension.
+///
+/// Files without a valid MD5 hash are skipped.
+fn parse_file_manifest(
+    long_version: &str,
+    files_json: &serde_json::Value,
+) -> std::io::Result<Vec<ForgeFile>> {
+    let classifiers = files_json
+        .get("classifiers")
+        .and_then(|classifiers| classifiers.as_object())
+        .ok_or_else(|| {
+            std::io::Error::other(format!(
+                "{}: The file manifest has no classifiers",
+                long_version
+            ))
+        })?;
+    let mut files = Vec::new();
+    for (classifier, extensions) in classifiers {
+        let extensions = extensions.as_object().ok_or_else(|| {
+            std::io::Error::other(format!(
+                "{}: The extensions of classifier {} are not an object",
+                long_version, classifier
+            ))
+        })?;
+        for (extension, hash) in extensions {
+            let hash = match hash.as_str() {
+                Some(hash) => hash,
+                None => {
+                    warn!(
+                        "{}: Skipping missing hash for {}.{}",
+                        long_version, classifier, extension
+                    );
                     continue;
                 }
+            };
 
-                let file = ForgeFile {
-                    classifier: classifier.to_string(),
-                    hash: processed_hash.to_string(),
-                    extension: extension.to_string(),
-                };
-                if !inserted {
-                    file_map.insert(classifier.to_string(), file);
-                    index += 1;
-                    inserted = true;
-                } else {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("{}: Duplicate classifier {}", long_version, classifier),
-                    ));
-                }
+            let processed_hash = HASH_PROCESSING_REGEX.replacen(hash, 1, "");
+            if processed_hash.len() != 32 {
+                warn!(
+                    "{}: Skipping invalid hash for {}.{}",
+                    long_version, classifier, extension
+                );
+                continue;
             }
+
+            files.push(ForgeFile {
+                classifier: classifier.to_string(),
+                hash: processed_hash.to_string(),
+                extension: extension.to_string(),
+            });
         }
+    }
+    files.sort_by(|a, b| (&a.classifier, &a.extension).cmp(&(&b.classifier, &b.extension)));
 
-        if !from_file {
-            std::fs::write(manifest_path, serde_json::to_string_pretty(&files_json)?)?;
+    Ok(files)
+}
+
+/// Reads the legacy info of a build from its jar, which the generator prefers over the bundled `FORGE_LEGACY_INFO`.
+fn read_legacy_info(
+    version: &ForgeVersion,
+    jar_file_path: &Path,
+) -> std::io::Result<ForgeLegacyInfo> {
+    let jar_info = JarInfo::read(jar_file_path)?;
+
+    Ok(ForgeLegacyInfo {
+        release_time: Some(jar_info.release_time),
+        size: Some(legacy_info_size(version, jar_info.size)?),
+        sha256: Some(jar_info.sha256),
+        sha1: Some(jar_info.sha1),
+    })
+}
+
+/// Converts the size of a jar to the size of the legacy info, rejecting sizes which don't fit instead of truncating
+/// them.
+fn legacy_info_size(version: &ForgeVersion, size: u64) -> std::io::Result<i32> {
+    i32::try_from(size).map_err(|_| {
+        std::io::Error::other(format!(
+            "Forge version {} is {} bytes, which doesn't fit the size of the legacy info",
+            version.long_version, size
+        ))
+    })
+}
+
+/// Groups the Forge versions of the Maven metadata by Minecraft version, keeping the upstream order.
+fn version_list(metadata: &MavenMetadata) -> serde_json::Value {
+    let mut version_list = serde_json::Map::new();
+    for long_version in metadata.versions() {
+        let mc_version = match VERSION_REGEX.captures(long_version) {
+            Some(version_match) => version_match.name("mc").unwrap().as_str(),
+            None => {
+                warn!(
+                    "Skipping Forge version {}, it doesn't match the regex",
+                    long_version
+                );
+                continue;
+            }
+        };
+        version_list
+            .entry(mc_version)
+            .or_insert_with(|| serde_json::Value::Array(Vec::new()))
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::Value::String(long_version.clone()));
+    }
+
+    serde_json::Value::Object(version_list)
+}
+
+/// Maps every Forge version of the index which still exists upstream to its serialized entry,
+/// so entries can be compared.
+fn entry_values(versions: &HashMap<String, ForgeEntry>) -> HashMap<String, serde_json::Value> {
+    versions
+        .iter()
+        .filter(|(_, entry)| entry.removed != Some(true))
+        .map(|(long_version, entry)| {
+            (
+                long_version.clone(),
+                serde_json::to_value(entry).unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+/// Finds the build a promotion points to, matching its Minecraft version, branch and short version.
+///
+/// Promotions without a branch are for the builds without a branch or with the Minecraft version as branch, e.g.
+/// `1.7.10-10.13.4.1614-1.7.10`. Builds of other branches are only used if none of these has the version.
+fn promoted_build(
+    builds: &[&String],
+    versions: &HashMap<String, ForgeEntry>,
+    mc_version: &str,
+    short_version: &str,
+    branch: Option<&str>,
+) -> Option<String> {
+    let find = |branch_matches: &dyn Fn(Option<&str>) -> bool| {
+        builds
+            .iter()
+            .rev()
+            .find(|long_version| {
+                let entry = &versions[**long_version];
+                entry.mc_version == mc_version
+                    && entry.version == short_version
+                    && branch_matches(entry.branch.as_deref())
+            })
+            .map(|long_version| long_version.to_string())
+    };
+
+    match branch {
+        Some(branch) => find(&|build_branch| build_branch == Some(branch)),
+        None => find(&|build_branch| build_branch.is_none() || build_branch == Some(mc_version))
+            .or_else(|| find(&|_| true)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_maven_versions_by_minecraft_version() {
+        let metadata: MavenMetadata =
+            include_str!("../../tests/data/maven/forge-maven-metadata.xml")
+                .parse()
+                .unwrap();
+        assert_eq!(
+            version_list(&metadata),
+            serde_json::json!({
+                "1.7.10": ["1.7.10-10.13.4.1614-1.7.10"],
+                "1.7.10_pre4": ["1.7.10_pre4-10.12.2.1149-prerelease"],
+                "1.12.2": ["1.12.2-14.23.5.2860"],
+                "1.18.1": ["1.18.1-39.0.5"],
+                "1.18.2": ["1.18.2-40.0.54", "1.18.2-40.1.0"],
+            })
+        );
+    }
+
+    fn entry(
+        long_version: &str,
+        mc_version: &str,
+        version: &str,
+        branch: Option<&str>,
+    ) -> ForgeEntry {
+        ForgeEntry {
+            long_version: long_version.to_string(),
+            mc_version: mc_version.to_string(),
+            version: version.to_string(),
+            build: 0,
+            branch: branch.map(str::to_string),
+            latest: None,
+            recommended: None,
+            files: None,
+            removed: None,
+            changelog: None,
         }
+    }
+
+    #[test]
+    fn matches_promotions_on_branch() {
+        let versions: HashMap<String, ForgeEntry> = [
+            entry(
+                "1.7.10-10.13.4.1614-1.7.10",
+                "1.7.10",
+                "10.13.4.1614",
+                Some("1.7.10"),
+            ),
+            entry(
+                "1.7.10-10.13.4.1614-new",
+                "1.7.10",
+                "10.13.4.1614",
+                Some("new"),
+            ),
+            entry(
+                "1.7.10-10.13.4.1614-old",
+                "1.7.10",
+                "10.13.4.1614",
+                Some("old"),
+            ),
+            entry(
+                "1.7.10_pre4-10.12.2.1149-prerelease",
+                "1.7.10_pre4",
+                "10.12.2.1149",
+                Some("prerelease"),
+            ),
+        ]
+        .into_iter()
+        .map(|entry| (entry.long_version.clone(), entry))
+        .collect();
+        let mut long_versions: Vec<&String> = versions.keys().collect();
+        long_versions.sort();
+        let promoted = |mc_version, short_version, branch| {
+            promoted_build(&long_versions, &versions, mc_version, short_version, branch)
+        };
+
+        assert_eq!(
+            promoted("1.7.10", "10.13.4.1614", None).as_deref(),
+            Some("1.7.10-10.13.4.1614-1.7.10")
+        );
+        assert_eq!(
+            promoted("1.7.10", "10.13.4.1614", Some("new")).as_deref(),
+            Some("1.7.10-10.13.4.1614-new")
+        );
+        assert_eq!(
+            promoted("1.7.10", "10.13.4.1614", Some("old")).as_deref(),
+            Some("1.7.10-10.13.4.1614-old")
+        );
+        assert_eq!(promoted("1.7.10", "10.13.4.1614", Some("beta")), None);
+        // without builds of the default branch, the promotion falls back to other branches
+        assert_eq!(
+            promoted("1.7.10_pre4", "10.12.2.1149", None).as_deref(),
+            Some("1.7.10_pre4-10.12.2.1149-prerelease")
+        );
+        assert_eq!(promoted("1.7.10_pre4", "10.13.4.1614", None), None);
+    }
+
+    /// Writes a jar whose entries were last modified at the given times.
+    fn write_jar(path: &Path, modified: &[(u16, u8, u8, u8, u8, u8)]) {
+        let mut jar = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (i, (year, month, day, hour, minute, second)) in modified.iter().enumerate() {
+            let last_modified =
+                zip::DateTime::from_date_and_time(*year, *month, *day, *hour, *minute, *second)
+                    .unwrap();
+            jar.start_file(
+                format!("net/minecraft/src/Class{}.class", i),
+                zip::write::FileOptions::default().last_modified_time(last_modified),
+            )
+            .unwrap();
+            jar.write_all(b"class").unwrap();
+        }
+        std::fs::write(path, jar.finish().unwrap().into_inner()).unwrap();
+    }
+
+    #[test]
+    fn reads_the_legacy_info_of_a_jar() {
+        let path = std::env::temp_dir().join(format!(
+            "morphosis-meta-legacy-info-{}.zip",
+            std::process::id()
+        ));
+        write_jar(
+            &path,
+            &[
+                (2012, 8, 1, 10, 0, 0),
+                (2012, 8, 3, 18, 30, 12),
+                (2012, 7, 30, 9, 0, 0),
+            ],
+        );
+        let data = std::fs::read(&path).unwrap();
+        let version: ForgeVersion = entry("1.3.2-4.3.5.318", "1.3.2", "4.3.5.318", None).into();
+
+        let legacy_info = read_legacy_info(&version, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // the newest entry is the release time
+        assert_eq!(
+            legacy_info.release_time.unwrap().to_rfc3339(),
+            "2012-08-03T18:30:12+00:00"
+        );
+        assert_eq!(legacy_info.size, Some(data.len() as i32));
+        assert_eq!(
+            legacy_info.sha1.unwrap(),
+            data_encoding::HEXLOWER.encode(
+                ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, &data).as_ref()
+            )
+        );
+        assert_eq!(
+            legacy_info.sha256.unwrap(),
+            data_encoding::HEXLOWER
+                .encode(ring::digest::digest(&ring::digest::SHA256, &data).as_ref())
+        );
+    }
+
+    #[test]
+    fn rejects_legacy_info_sizes_which_overflow() {
+        let version: ForgeVersion = entry("1.3.2-4.3.5.318", "1.3.2", "4.3.5.318", None).into();
+
+        assert_eq!(
+            legacy_info_size(&version, i32::MAX as u64).unwrap(),
+            i32::MAX
+        );
+        let error = legacy_info_size(&version, i32::MAX as u64 + 1).unwrap_err();
+        assert!(error.to_string().contains("1.3.2-4.3.5.318"));
+    }
+
+    #[test]
+    fn parses_every_extension_of_a_classifier() {
+        let manifest = serde_json::json!({
+            "classifiers": {
+                "installer": {
+                    "jar": "7b3d8f9d8ad1b0e7d5ce0b6c1e4a3f21",
+                    "json": "0c1f6e2ab7d94c4a9f6e7d1b3a2c5e48"
+                },
+                "changelog": { "txt": "d41d8cd98f00b204e9800998ecf8427e" },
+                "universal": { "jar": null, "zip": "too-short" }
+            }
+        });
+
+        let files = parse_file_manifest("1.18.2-40.1.0", &manifest).unwrap();
+        let files: Vec<_> = files
+            .iter()
+            .map(|file| {
+                (
+                    file.classifier.as_str(),
+                    file.extension.as_str(),
+                    file.hash.as_str(),
+                )
+            })
+            .collect();
+        // files without a valid hash are skipped
+        assert_eq!(
+            files,
+            vec![
+                ("changelog", "txt", "d41d8cd98f00b204e9800998ecf8427e"),
+                ("installer", "jar", "7b3d8f9d8ad1b0e7d5ce0b6c1e4a3f21"),
+                ("installer", "json", "0c1f6e2ab7d94c4a9f6e7d1b3a2c5e48"),
+            ]
+        );
 
-        Ok(file_map)
+        assert!(parse_file_manifest("1.18.2-40.1.0", &serde_json::json!({})).is_err());
+        assert!(parse_file_manifest(
+            "1.18.2-40.1.0",
+            &serde_json::json!({ "classifiers": { "installer": "jar" } })
+        )
+        .is_err());
     }
 }
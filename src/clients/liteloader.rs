@@ -1,14 +1,21 @@
-use std::path::{PathBuf, Path};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use http_cache_reqwest::{Cache, HttpCache, CACacheManager};
-use reqwest::Client;
-use reqwest_middleware::{ClientWithMiddleware, ClientBuilder};
+use reqwest_middleware::ClientWithMiddleware;
 
-use crate::models::liteloader::LiteloaderIndex;
+use crate::models::{
+    liteloader::{LiteloaderEntry, LiteloaderIndex},
+    report::{RemovalPolicy, UpdateReport},
+};
+
+use super::build_client;
 
 pub struct LiteloaderUpdater {
     client: ClientWithMiddleware,
     cache_directory: PathBuf,
+    removal_policy: RemovalPolicy,
 }
 
 impl LiteloaderUpdater {
@@ -16,40 +23,132 @@ impl LiteloaderUpdater {
     where
         P: AsRef<Path>,
     {
-        let client = ClientBuilder::new(Client::new())
-            .with(Cache(HttpCache {
-                mode: http_cache_reqwest::CacheMode::Default,
-                manager: CACacheManager {
-                    path: "./http_cache".to_string(),
-                },
-                options: None,
-            }))
-            .build();
+        let client = build_client(false);
         // ensure the cache path and some subdirectories exist
         std::fs::create_dir_all(cache_directory.as_ref().join("liteloader")).unwrap();
 
         Self {
             client,
             cache_directory: cache_directory.as_ref().to_path_buf(),
+            removal_policy: RemovalPolicy::default(),
         }
     }
 
-    pub async fn generate_meta_cache(&self) -> std::io::Result<()> {
+    /// Sets what happens to versions which were removed upstream.
+    pub fn with_removal_policy(mut self, removal_policy: RemovalPolicy) -> Self {
+        self.removal_policy = removal_policy;
+        self
+    }
+
+    /// Only answers requests from the HTTP cache, without contacting upstream.
+    pub fn with_offline_mode(mut self, offline: bool) -> Self {
+        self.client = build_client(offline);
+        self
+    }
+
+    /// Updates the Liteloader metadata and returns a report of what changed.
+    pub async fn generate_meta_cache(&self) -> std::io::Result<UpdateReport> {
+        let mut report = UpdateReport::new("liteloader");
+        let versions_path = self.cache_directory.join("liteloader/versions.json");
+        let previous_index = std::fs::File::open(&versions_path)
+            .ok()
+            .and_then(|file| serde_json::from_reader::<_, LiteloaderIndex>(file).ok());
+        let previous_versions = previous_index
+            .as_ref()
+            .map(version_values)
+            .unwrap_or_default();
+
         info!("Downloading Liteloader index");
-        let liteloader_versions = self.client
+        let liteloader_versions = self
+            .client
             .get("https://dl.liteloader.com/versions/versions.json")
             .send()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+            .map_err(std::io::Error::other)?
             .error_for_status()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
-            .json::<LiteloaderIndex>()
+            .map_err(std::io::Error::other)?
+            .bytes()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-        
-        let versions_file = std::fs::File::create(self.cache_directory.join("liteloader/versions.json"))?;
+            .map_err(std::io::Error::other)?;
+        report.record_download(liteloader_versions.len() as u64);
+        let mut liteloader_versions: LiteloaderIndex =
+            serde_json::from_slice(&liteloader_versions)?;
+        report.record_changes(&previous_versions, &version_values(&liteloader_versions));
+
+        let current_versions = liteloader_versions
+            .versions
+            .get_or_insert_with(HashMap::new);
+        let missing_versions = previous_index
+            .and_then(|index| index.versions)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(mc_version, _)| !current_versions.contains_key(mc_version))
+            .collect::<HashMap<String, LiteloaderEntry>>();
+        info!(
+            "Applying removal policy {:?} to {} versions missing upstream...",
+            self.removal_policy,
+            missing_versions.len()
+        );
+        if self.removal_policy.keeps_entries() {
+            for (mc_version, mut entry) in missing_versions {
+                entry.removed = Some(true);
+                current_versions.insert(mc_version, entry);
+            }
+        } else if !missing_versions.is_empty() {
+            if self.removal_policy == RemovalPolicy::Archive {
+                // the entries only exist in the shared index, so they are archived as well
+                let archive_path = self
+                    .cache_directory
+                    .join("archive/liteloader/versions.json");
+                let mut archived_versions: HashMap<String, LiteloaderEntry> =
+                    std::fs::File::open(&archive_path)
+                        .ok()
+                        .and_then(|file| serde_json::from_reader(file).ok())
+                        .unwrap_or_default();
+                archived_versions.extend(missing_versions.clone());
+                std::fs::create_dir_all(archive_path.parent().unwrap())?;
+                let archive_file = std::fs::File::create(&archive_path)?;
+                serde_json::to_writer_pretty(archive_file, &archived_versions)?;
+                report.record_file(&archive_path, &self.cache_directory);
+            }
+            for mc_version in missing_versions.keys() {
+                self.removal_policy.apply_to_files(
+                    &self.cache_directory,
+                    &cached_files(mc_version),
+                    &mut report,
+                )?;
+            }
+        }
+
+        let versions_file = std::fs::File::create(&versions_path)?;
         serde_json::to_writer_pretty(versions_file, &liteloader_versions)?;
+        report.record_file(&versions_path, &self.cache_directory);
 
-        Ok(())
+        Ok(report)
     }
-}
\ No newline at end of file
+}
+
+/// Returns the paths of the cached files of a Minecraft version, relative to the cache directory.
+///
+/// LiteLoader publishes everything in `liteloader/versions.json`, so there are no files per version yet and removed
+/// versions only leave the index, or are moved to the archived index.
+fn cached_files(_mc_version: &str) -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// Maps every Minecraft version of the index which still exists upstream to its serialized entry,
+/// so entries can be compared.
+fn version_values(index: &LiteloaderIndex) -> HashMap<String, serde_json::Value> {
+    index
+        .versions
+        .iter()
+        .flatten()
+        .filter(|(_, entry)| entry.removed != Some(true))
+        .map(|(mc_version, entry)| {
+            (
+                mc_version.clone(),
+                serde_json::to_value(entry).unwrap_or_default(),
+            )
+        })
+        .collect()
+}
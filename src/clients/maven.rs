@@ -0,0 +1,74 @@
+use reqwest_middleware::ClientWithMiddleware;
+
+use crate::models::{maven::MavenMetadata, misc::GradleSpecifier};
+
+use super::build_client;
+
+/// A generic client for a single Maven repository.
+#[derive(Clone)]
+pub struct MavenClient {
+    client: ClientWithMiddleware,
+    repository_url: String,
+}
+
+impl MavenClient {
+    pub fn new(repository_url: &str) -> Self {
+        let client = build_client(false);
+
+        Self::with_client(client, repository_url)
+    }
+
+    /// Creates a Maven client which shares the HTTP client of an updater.
+    pub fn with_client(client: ClientWithMiddleware, repository_url: &str) -> Self {
+        // normalize the base URL, so joining paths never produces double or missing slashes
+        let repository_url = format!("{}/", repository_url.trim_end_matches('/'));
+
+        Self {
+            client,
+            repository_url,
+        }
+    }
+
+    /// Returns the base URL of the repository, always ending with a slash.
+    pub fn repository_url(&self) -> &str {
+        &self.repository_url
+    }
+
+    /// Returns the URL of the artifact described by the given specifier.
+    pub fn artifact_url(&self, specifier: &GradleSpecifier) -> String {
+        format!("{}{}", self.repository_url, specifier.path())
+    }
+
+    /// Returns the URL of the `maven-metadata.xml` file for the given artifact.
+    pub fn metadata_url(&self, group: &str, artifact: &str) -> String {
+        format!(
+            "{}{}/{}/maven-metadata.xml",
+            self.repository_url,
+            group.replace('.', "/"),
+            artifact
+        )
+    }
+
+    /// Downloads and parses the `maven-metadata.xml` file for the given artifact.
+    pub async fn get_metadata(
+        &self,
+        group: &str,
+        artifact: &str,
+    ) -> std::io::Result<MavenMetadata> {
+        let url = self.metadata_url(group, artifact);
+        info!("Downloading Maven metadata from {}...", url);
+        let metadata = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(std::io::Error::other)?
+            .error_for_status()
+            .map_err(std::io::Error::other)?
+            .text()
+            .await
+            .map_err(std::io::Error::other)?;
+
+        metadata.parse().map_err(std::io::Error::other)
+    }
+}
@@ -1,4 +1,93 @@
+#[cfg(any(feature = "forge", feature = "fabric"))]
+use std::path::Path;
+
+use http_cache_reqwest::{CACacheManager, Cache, CacheMode, HttpCache};
+use reqwest::Client;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+
+#[cfg(feature = "fabric")]
 pub mod fabric;
+#[cfg(feature = "forge")]
 pub mod forge;
+#[cfg(feature = "liteloader")]
+pub mod liteloader;
+pub mod maven;
+#[cfg(feature = "mojang")]
 pub mod mojang;
-pub mod liteloader;
\ No newline at end of file
+
+/// Directory the HTTP responses of the updaters are cached in.
+pub const HTTP_CACHE_DIRECTORY: &str = "./http_cache";
+
+/// Builds the HTTP client used by the updaters, which caches responses in [`HTTP_CACHE_DIRECTORY`].
+///
+/// In offline mode, requests are only answered from the cache and fail if the response isn't cached.
+pub(crate) fn build_client(offline: bool) -> ClientWithMiddleware {
+    let mode = if offline {
+        CacheMode::OnlyIfCached
+    } else {
+        CacheMode::Default
+    };
+
+    ClientBuilder::new(Client::new())
+        .with(Cache(HttpCache {
+            mode,
+            manager: CACacheManager {
+                path: HTTP_CACHE_DIRECTORY.to_string(),
+            },
+            options: None,
+        }))
+        .build()
+}
+
+/// The release time and hashes of a downloaded jar.
+#[cfg(any(feature = "forge", feature = "fabric"))]
+pub(crate) struct JarInfo {
+    /// The modification time of the newest entry of the jar.
+    pub release_time: chrono::DateTime<chrono::Utc>,
+    pub sha1: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+#[cfg(any(feature = "forge", feature = "fabric"))]
+impl JarInfo {
+    pub fn read<P>(path: P) -> std::io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let mut release_time = chrono::DateTime::<chrono::Utc>::UNIX_EPOCH;
+        let mut jar_file = zip::ZipArchive::new(std::fs::File::open(&path)?)?;
+        for i in 0..jar_file.len() {
+            let file = jar_file.by_index(i)?;
+            let file_last_modified = file.last_modified();
+            let file_last_modified = chrono::NaiveDate::from_ymd_opt(
+                file_last_modified.year().into(),
+                file_last_modified.month().into(),
+                file_last_modified.day().into(),
+            )
+            .and_then(|date| {
+                date.and_hms_opt(
+                    file_last_modified.hour().into(),
+                    file_last_modified.minute().into(),
+                    file_last_modified.second().into(),
+                )
+            })
+            .map(|date_time| date_time.and_utc())
+            .unwrap_or(release_time);
+            if file_last_modified > release_time {
+                release_time = file_last_modified;
+            }
+        }
+
+        let data = std::fs::read(&path)?;
+        let sha1_hash = ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, &data);
+        let sha256_hash = ring::digest::digest(&ring::digest::SHA256, &data);
+
+        Ok(Self {
+            release_time,
+            sha1: data_encoding::HEXLOWER.encode(sha1_hash.as_ref()),
+            sha256: data_encoding::HEXLOWER.encode(sha256_hash.as_ref()),
+            size: data.len() as u64,
+        })
+    }
+}
@@ -3,17 +3,23 @@ use std::{
     collections::HashMap,
     io::{Read, Write},
     path::{Path, PathBuf},
+    sync::Mutex,
 };
 
-use http_cache_reqwest::{CACacheManager, Cache, HttpCache};
-use reqwest::Client;
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_middleware::ClientWithMiddleware;
 
-use crate::models::mojang::MojangIndex;
+use crate::models::{
+    mojang::MojangIndex,
+    report::{RemovalPolicy, UpdateReport},
+};
+
+use super::build_client;
 
 pub struct MojangUpdater {
     client: ClientWithMiddleware,
     cache_directory: PathBuf,
+    report: Mutex<UpdateReport>,
+    removal_policy: RemovalPolicy,
 }
 
 impl MojangUpdater {
@@ -21,15 +27,7 @@ impl MojangUpdater {
     where
         P: AsRef<Path>,
     {
-        let client = ClientBuilder::new(Client::new())
-            .with(Cache(HttpCache {
-                mode: http_cache_reqwest::CacheMode::Default,
-                manager: CACacheManager {
-                    path: "./http_cache".to_string(),
-                },
-                options: None,
-            }))
-            .build();
+        let client = build_client(false);
         // ensure the cache path and some subdirectories exist
         std::fs::create_dir_all(cache_directory.as_ref().join("mojang/versions")).unwrap();
         std::fs::create_dir_all(cache_directory.as_ref().join("mojang/assets")).unwrap();
@@ -37,10 +35,24 @@ impl MojangUpdater {
         MojangUpdater {
             client,
             cache_directory: cache_directory.as_ref().to_path_buf(),
+            report: Mutex::new(UpdateReport::new("mojang")),
+            removal_policy: RemovalPolicy::default(),
         }
     }
 
-    fn get_local_mojang_index(&self) -> MojangIndex {
+    /// Sets what happens to versions which were removed upstream.
+    pub fn with_removal_policy(mut self, removal_policy: RemovalPolicy) -> Self {
+        self.removal_policy = removal_policy;
+        self
+    }
+
+    /// Only answers requests from the HTTP cache, without contacting upstream.
+    pub fn with_offline_mode(mut self, offline: bool) -> Self {
+        self.client = build_client(offline);
+        self
+    }
+
+    fn get_local_mojang_index(&self) -> MojangIndex<'_> {
         info!("Loading local Mojang index...");
         let local_versions: MojangIndex;
         // check if upstream/mojang/version_manifest_v2.json exists,
@@ -59,6 +71,8 @@ impl MojangUpdater {
                 latest: HashMap::new(),
                 versions: Vec::new(),
                 version_map: RefCell::new(HashMap::new()),
+                #[cfg(feature = "extra-fields")]
+                extra: Default::default(),
             };
         }
 
@@ -74,17 +88,34 @@ impl MojangUpdater {
             .get("https://launchermeta.mojang.com/mc/game/version_manifest_v2.json")
             .send()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            .map_err(std::io::Error::other)?
+            .bytes()
+            .await
+            .map_err(std::io::Error::other)?;
         info!("Downloaded remote Mojang index!");
+        self.record_download(response.len());
 
-        response
-            .json()
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+        serde_json::from_slice(&response).map_err(std::io::Error::other)
+    }
+
+    fn record_download(&self, bytes: usize) {
+        self.report.lock().unwrap().record_download(bytes as u64);
     }
 
-    /// Updates the Mojang metadata.
-    pub async fn generate_meta_cache(&self) -> std::io::Result<()> {
+    fn record_file<P>(&self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.report
+            .lock()
+            .unwrap()
+            .record_file(path, &self.cache_directory);
+    }
+
+    /// Updates the Mojang metadata and returns a report of what changed.
+    pub async fn generate_meta_cache(&self) -> std::io::Result<UpdateReport> {
+        *self.report.lock().unwrap() = UpdateReport::new("mojang");
+
         // Get the local Mojang index
         let local_index = self.get_local_mojang_index();
 
@@ -113,6 +144,25 @@ impl MojangUpdater {
             "Found {} new versions, which aren't in the local index!",
             new_versions.len()
         );
+        // Create a list of versions that are in the local Mojang index but not in the remote Mojang index,
+        // versions which were already marked as removed during a previous run are not reported again
+        let mut removed_versions = local_version_ids
+            .iter()
+            .filter(|id| !remote_version_ids.contains(id))
+            .filter(|id| local_index.version_map.borrow()[*id].removed != Some(true))
+            .cloned()
+            .collect::<Vec<String>>();
+        removed_versions.sort_by(|a, b| {
+            let local_version_map = local_index.version_map.borrow();
+            local_version_map
+                .get(a)
+                .unwrap()
+                .cmp_version(local_version_map.get(b).unwrap())
+        });
+        info!(
+            "Found {} versions, which were removed from the remote index!",
+            removed_versions.len()
+        );
         // Create a list of versions that are in the local and remote Mojang index
         let common_versions = local_version_ids
             .iter()
@@ -124,28 +174,60 @@ impl MojangUpdater {
             common_versions.len()
         );
         info!("Checking if any of the common versions are outdated...");
+        let mut outdated_versions = Vec::new();
         for id in common_versions {
-            // check if the remote version time is newer than the local version time
-            let version_map = remote_index.version_map.borrow();
-            let remote_version = version_map.get(&id).unwrap();
-            let local_version = version_map.get(&id).unwrap();
+            let remote_version_map = remote_index.version_map.borrow();
+            let local_version_map = local_index.version_map.borrow();
+            let remote_version = remote_version_map.get(&id).unwrap();
+            let local_version = local_version_map.get(&id).unwrap();
 
-            if remote_version.time > local_version.time {
+            // the remote version is outdated if it is newer or its contents changed without a time bump
+            let is_newer = remote_version.time > local_version.time;
+            let hash_changed = match (&remote_version.sha1, &local_version.sha1) {
+                (Some(remote_sha1), Some(local_sha1)) => remote_sha1 != local_sha1,
+                _ => false,
+            };
+            if is_newer || hash_changed {
                 info!("Version {} is outdated, adding to update list.", id);
-                new_versions.push(id);
+                outdated_versions.push(id);
             }
         }
 
+        // process versions in release order, so logs, downloads and the report are deterministic
+        {
+            let remote_version_map = remote_index.version_map.borrow();
+            let release_order = |a: &String, b: &String| {
+                remote_version_map
+                    .get(a)
+                    .unwrap()
+                    .cmp_version(remote_version_map.get(b).unwrap())
+            };
+            new_versions.sort_by(release_order);
+            outdated_versions.sort_by(release_order);
+        }
+        {
+            let mut report = self.report.lock().unwrap();
+            report.added = new_versions.clone();
+            report.updated = outdated_versions.clone();
+            report.removed = removed_versions.clone();
+        }
+        new_versions.extend(outdated_versions);
+
         let mut asset_map: HashMap<String, String> = HashMap::new();
         for id in new_versions {
-            let version_map = remote_index.version_map.borrow();
-            let version = version_map.get(&id).unwrap();
+            let version_url = remote_index
+                .version_map
+                .borrow()
+                .get(&id)
+                .unwrap()
+                .url
+                .clone();
             info!("Downloading version file {}...", id);
             let (asset_id, asset_url) = self
                 .download_version_file(
                     self.cache_directory
                         .join(format!("mojang/versions/{}.json", id)),
-                    &version.url,
+                    &version_url,
                 )
                 .await?;
             asset_map.insert(asset_id, asset_url);
@@ -161,14 +243,55 @@ impl MojangUpdater {
             .await?;
         }
 
+        info!(
+            "Applying removal policy {:?} to {} removed versions...",
+            self.removal_policy,
+            removed_versions.len()
+        );
+        let mut new_index = MojangIndex {
+            latest: remote_index.latest.clone(),
+            versions: remote_index.versions.clone(),
+            version_map: RefCell::new(HashMap::new()),
+            #[cfg(feature = "extra-fields")]
+            extra: remote_index.extra.clone(),
+        };
+        if self.removal_policy.keeps_entries() {
+            // carry over every version missing upstream, including the ones removed in earlier runs
+            new_index.versions.extend(
+                local_index
+                    .versions
+                    .iter()
+                    .filter(|version| !remote_version_ids.contains(&version.id))
+                    .map(|version| {
+                        let mut version = version.clone();
+                        version.removed = Some(true);
+                        version
+                    }),
+            );
+            new_index.versions.sort_by(|a, b| b.cmp_version(a));
+        } else {
+            // this also cleans up versions which were kept by a previous run with another policy
+            let removed_files = local_version_ids
+                .iter()
+                .filter(|id| !remote_version_ids.contains(id))
+                .map(|id| PathBuf::from(format!("mojang/versions/{}.json", id)))
+                .collect::<Vec<PathBuf>>();
+            self.removal_policy.apply_to_files(
+                &self.cache_directory,
+                &removed_files,
+                &mut self.report.lock().unwrap(),
+            )?;
+        }
+
         info!("Saving new Mojang index...");
         // write the new Mojang index to disk
-        let mut file =
-            std::fs::File::create(self.cache_directory.join("mojang/version_manifest_v2.json"))?;
-        file.write_all(serde_json::to_string(&remote_index).unwrap().as_bytes())?;
+        let index_path = self.cache_directory.join("mojang/version_manifest_v2.json");
+        let mut file = std::fs::File::create(&index_path)?;
+        file.write_all(serde_json::to_string(&new_index).unwrap().as_bytes())?;
+        self.record_file(&index_path);
         info!("Generation done!");
 
-        Ok(())
+        Ok(std::mem::take(&mut *self.report.lock().unwrap()))
     }
 
     /// Downloads and saves the Mojang version file at the given URL, saves it in the specified path
@@ -186,35 +309,35 @@ impl MojangUpdater {
             .get(url)
             .send()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?;
+            .map_err(|e| std::io::Error::other(format!("{}", e)))?;
         if !response.status().is_success() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!(
-                    "Downloading version file at {} returned status code {}",
-                    url,
-                    response.status()
-                ),
-            ));
+            return Err(std::io::Error::other(format!(
+                "Downloading version file at {} returned status code {}",
+                url,
+                response.status()
+            )));
         }
 
         let version_json = response
-            .json::<serde_json::Value>()
+            .bytes()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?;
-        let asset_id = version_json["assetIndex"]["id"].as_str().ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::Other, "asset index id not found")
-        })?;
-        let asset_url = version_json["assetIndex"]["url"].as_str().ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::Other, "asset index url not found")
-        })?;
-
-        let mut file = std::fs::File::create(path)?;
+            .map_err(|e| std::io::Error::other(format!("{}", e)))?;
+        self.record_download(version_json.len());
+        let version_json: serde_json::Value = serde_json::from_slice(&version_json)?;
+        let asset_id = version_json["assetIndex"]["id"]
+            .as_str()
+            .ok_or_else(|| std::io::Error::other("asset index id not found"))?;
+        let asset_url = version_json["assetIndex"]["url"]
+            .as_str()
+            .ok_or_else(|| std::io::Error::other("asset index url not found"))?;
+
+        let mut file = std::fs::File::create(&path)?;
         file.write_all(
             serde_json::to_string_pretty(&version_json)
                 .unwrap()
                 .as_bytes(),
         )?;
+        self.record_file(&path);
 
         Ok((asset_id.to_string(), asset_url.to_string()))
     }
@@ -228,24 +351,24 @@ impl MojangUpdater {
             .get(url)
             .send()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?;
+            .map_err(|e| std::io::Error::other(format!("{}", e)))?;
         if !response.status().is_success() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!(
-                    "Downloading asset file at {} returned status code {}",
-                    url,
-                    response.status()
-                ),
-            ));
+            return Err(std::io::Error::other(format!(
+                "Downloading asset file at {} returned status code {}",
+                url,
+                response.status()
+            )));
         }
         let json = response
-            .json::<serde_json::Value>()
+            .bytes()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?;
+            .map_err(|e| std::io::Error::other(format!("{}", e)))?;
+        self.record_download(json.len());
+        let json: serde_json::Value = serde_json::from_slice(&json)?;
 
-        let mut file = std::fs::File::create(path)?;
+        let mut file = std::fs::File::create(&path)?;
         file.write_all(serde_json::to_string_pretty(&json).unwrap().as_bytes())?;
+        self.record_file(&path);
 
         Ok(())
     }
@@ -0,0 +1,576 @@
+//! C ABI for the updaters and the version file models.
+//!
+//! Every function returns a [`MetamorphosisStatus`]. If it isn't `Ok`, a description of the error can be
+//! retrieved with [`metamorphosis_last_error`]. Handles and strings returned by the library must be freed
+//! with the matching `*_free` function.
+//!
+//! Enum arguments are passed as Rust enums, so C callers must only pass their declared values, any other value is
+//! undefined behavior.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    ptr,
+};
+
+use tokio::runtime::Runtime;
+
+use crate::{
+    models::{mojang::MojangVersionFile, polymc::PolyMCVersionFile},
+    FabricUpdater, ForgeUpdater, LiteloaderUpdater, MojangUpdater, RemovalPolicy,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Result of every FFI function.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetamorphosisStatus {
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// A string argument wasn't valid UTF-8.
+    InvalidUtf8 = 2,
+    /// A file couldn't be read or written, or an upstream couldn't be reached.
+    Io = 3,
+    /// A JSON document couldn't be parsed or serialized.
+    Parse = 4,
+    /// The internal async runtime couldn't be created.
+    Runtime = 5,
+    /// The library panicked, the handle should not be used anymore.
+    Panic = 6,
+}
+
+/// The upstream an updater handle downloads metadata from.
+///
+/// Only the declared values may be passed, any other value is undefined behavior.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetamorphosisUpstream {
+    Mojang = 0,
+    Forge = 1,
+    Fabric = 2,
+    Liteloader = 3,
+}
+
+/// What an updater does with versions which were removed upstream, see [`RemovalPolicy`].
+///
+/// Only the declared values may be passed, any other value is undefined behavior.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetamorphosisRemovalPolicy {
+    Keep = 0,
+    Archive = 1,
+    Delete = 2,
+}
+
+impl From<MetamorphosisRemovalPolicy> for RemovalPolicy {
+    fn from(policy: MetamorphosisRemovalPolicy) -> Self {
+        match policy {
+            MetamorphosisRemovalPolicy::Keep => RemovalPolicy::Keep,
+            MetamorphosisRemovalPolicy::Archive => RemovalPolicy::Archive,
+            MetamorphosisRemovalPolicy::Delete => RemovalPolicy::Delete,
+        }
+    }
+}
+
+/// The format of a version file handle.
+///
+/// Only the declared values may be passed, any other value is undefined behavior.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetamorphosisVersionFormat {
+    Mojang = 0,
+    PolyMC = 1,
+}
+
+enum Updater {
+    Mojang(MojangUpdater),
+    Forge(ForgeUpdater),
+    Fabric(FabricUpdater),
+    Liteloader(LiteloaderUpdater),
+}
+
+/// An updater together with the runtime its updates run on.
+pub struct MetamorphosisUpdater {
+    runtime: Runtime,
+    updater: Updater,
+}
+
+/// A parsed Mojang or PolyMC version file.
+pub enum MetamorphosisVersionFile {
+    Mojang(Box<MojangVersionFile>),
+    PolyMC(Box<PolyMCVersionFile>),
+}
+
+/// An error which is reported through the status code and the last error string.
+struct FfiError {
+    status: MetamorphosisStatus,
+    message: String,
+}
+
+impl FfiError {
+    fn new(status: MetamorphosisStatus, message: impl ToString) -> Self {
+        Self {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<std::io::Error> for FfiError {
+    fn from(e: std::io::Error) -> Self {
+        Self::new(MetamorphosisStatus::Io, e)
+    }
+}
+
+impl From<serde_json::Error> for FfiError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::new(MetamorphosisStatus::Parse, e)
+    }
+}
+
+/// Runs an FFI function body, storing its error and catching panics, so they never cross the C boundary.
+fn run<F>(f: F) -> MetamorphosisStatus
+where
+    F: FnOnce() -> Result<(), FfiError>,
+{
+    let result = catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(FfiError::new(MetamorphosisStatus::Panic, message))
+    });
+
+    match result {
+        Ok(()) => {
+            set_last_error(None);
+            MetamorphosisStatus::Ok
+        }
+        Err(e) => {
+            set_last_error(Some(e.message));
+            e.status
+        }
+    }
+}
+
+fn set_last_error(message: Option<String>) {
+    // interior NUL bytes can't be represented, so they are dropped
+    let message = message
+        .map(|message| CString::new(message.replace('\0', "")).expect("NUL bytes were removed"));
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+}
+
+/// Converts a C string argument to a `&str`.
+///
+/// # Safety
+///
+/// `s` must be null or point to a NUL-terminated string.
+unsafe fn str_argument<'a>(s: *const c_char) -> Result<&'a str, FfiError> {
+    if s.is_null() {
+        return Err(FfiError::new(
+            MetamorphosisStatus::NullPointer,
+            "string argument is null",
+        ));
+    }
+
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| FfiError::new(MetamorphosisStatus::InvalidUtf8, e))
+}
+
+/// Checks that an out pointer isn't null.
+fn out_argument<T>(out: *mut T) -> Result<(), FfiError> {
+    if out.is_null() {
+        Err(FfiError::new(
+            MetamorphosisStatus::NullPointer,
+            "out argument is null",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Converts a handle argument to a reference.
+///
+/// # Safety
+///
+/// `handle` must be null or a valid pointer returned by this library.
+unsafe fn handle_argument<'a, T>(handle: *const T) -> Result<&'a T, FfiError> {
+    handle
+        .as_ref()
+        .ok_or_else(|| FfiError::new(MetamorphosisStatus::NullPointer, "handle argument is null"))
+}
+
+fn to_c_string(s: String) -> Result<*mut c_char, FfiError> {
+    CString::new(s)
+        .map(CString::into_raw)
+        .map_err(|e| FfiError::new(MetamorphosisStatus::Parse, e))
+}
+
+/// Returns the error message of the last failed call on this thread, or null if it succeeded.
+///
+/// The string is owned by the library and valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn metamorphosis_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map(|message| message.as_ptr())
+            .unwrap_or(ptr::null())
+    })
+}
+
+/// Frees a string returned by this library. Passing null does nothing.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library, which wasn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn metamorphosis_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Creates an updater for the given upstream, which caches metadata in `cache_directory`.
+///
+/// In offline mode, the updater only uses responses from the HTTP cache.
+///
+/// # Safety
+///
+/// `upstream` and `removal_policy` must be declared values of their enums, `cache_directory` must be a
+/// NUL-terminated string and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn metamorphosis_updater_new(
+    upstream: MetamorphosisUpstream,
+    cache_directory: *const c_char,
+    removal_policy: MetamorphosisRemovalPolicy,
+    offline: bool,
+    out: *mut *mut MetamorphosisUpdater,
+) -> MetamorphosisStatus {
+    run(|| {
+        out_argument(out)?;
+        let cache_directory = PathBuf::from(str_argument(cache_directory)?);
+        let removal_policy = removal_policy.into();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| FfiError::new(MetamorphosisStatus::Runtime, e))?;
+        let updater = match upstream {
+            MetamorphosisUpstream::Mojang => Updater::Mojang(
+                MojangUpdater::new(&cache_directory)
+                    .with_removal_policy(removal_policy)
+                    .with_offline_mode(offline),
+            ),
+            MetamorphosisUpstream::Forge => Updater::Forge(
+                ForgeUpdater::new(&cache_directory)
+                    .with_removal_policy(removal_policy)
+                    .with_offline_mode(offline),
+            ),
+            MetamorphosisUpstream::Fabric => Updater::Fabric(
+                FabricUpdater::new(&cache_directory)
+                    .with_removal_policy(removal_policy)
+                    .with_offline_mode(offline),
+            ),
+            MetamorphosisUpstream::Liteloader => Updater::Liteloader(
+                LiteloaderUpdater::new(&cache_directory)
+                    .with_removal_policy(removal_policy)
+                    .with_offline_mode(offline),
+            ),
+        };
+
+        *out = Box::into_raw(Box::new(MetamorphosisUpdater { runtime, updater }));
+        Ok(())
+    })
+}
+
+/// Runs an update, blocking until it is done, and returns its report as JSON.
+///
+/// The report must be freed with [`metamorphosis_string_free`].
+///
+/// # Safety
+///
+/// `updater` must be a handle returned by [`metamorphosis_updater_new`] and `report_json` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn metamorphosis_updater_update(
+    updater: *mut MetamorphosisUpdater,
+    report_json: *mut *mut c_char,
+) -> MetamorphosisStatus {
+    run(|| {
+        out_argument(report_json)?;
+        let updater = handle_argument(updater)?;
+        let report = updater.runtime.block_on(async {
+            match &updater.updater {
+                Updater::Mojang(updater) => updater.generate_meta_cache().await,
+                Updater::Forge(updater) => updater.generate_meta_cache().await,
+                Updater::Fabric(updater) => updater.generate_meta_cache().await,
+                Updater::Liteloader(updater) => updater.generate_meta_cache().await,
+            }
+        })?;
+
+        *report_json = to_c_string(serde_json::to_string(&report)?)?;
+        Ok(())
+    })
+}
+
+/// Frees an updater. Passing null does nothing.
+///
+/// # Safety
+///
+/// `updater` must be null or a handle returned by [`metamorphosis_updater_new`], which wasn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn metamorphosis_updater_free(updater: *mut MetamorphosisUpdater) {
+    if !updater.is_null() {
+        drop(Box::from_raw(updater));
+    }
+}
+
+/// Parses a version file of the given format.
+///
+/// # Safety
+///
+/// `format` must be a declared value of its enum, `json` must be a NUL-terminated string and `out` must be a valid
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn metamorphosis_version_file_parse(
+    format: MetamorphosisVersionFormat,
+    json: *const c_char,
+    out: *mut *mut MetamorphosisVersionFile,
+) -> MetamorphosisStatus {
+    run(|| {
+        out_argument(out)?;
+        let json = str_argument(json)?;
+        let version_file = match format {
+            MetamorphosisVersionFormat::Mojang => {
+                MetamorphosisVersionFile::Mojang(serde_json::from_str(json)?)
+            }
+            MetamorphosisVersionFormat::PolyMC => {
+                MetamorphosisVersionFile::PolyMC(serde_json::from_str(json)?)
+            }
+        };
+
+        *out = Box::into_raw(Box::new(version_file));
+        Ok(())
+    })
+}
+
+/// Returns the format of a version file.
+///
+/// # Safety
+///
+/// `version_file` must be a handle returned by this library and `format` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn metamorphosis_version_file_format(
+    version_file: *const MetamorphosisVersionFile,
+    format: *mut MetamorphosisVersionFormat,
+) -> MetamorphosisStatus {
+    run(|| {
+        out_argument(format)?;
+        *format = match handle_argument(version_file)? {
+            MetamorphosisVersionFile::Mojang(_) => MetamorphosisVersionFormat::Mojang,
+            MetamorphosisVersionFile::PolyMC(_) => MetamorphosisVersionFormat::PolyMC,
+        };
+        Ok(())
+    })
+}
+
+/// Converts a Mojang version file to a PolyMC version file of the given component.
+///
+/// # Safety
+///
+/// `version_file` must be a Mojang version file handle returned by this library, `uid`, `name` and
+/// `version` must be NUL-terminated strings and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn metamorphosis_version_file_to_polymc(
+    version_file: *const MetamorphosisVersionFile,
+    uid: *const c_char,
+    name: *const c_char,
+    version: *const c_char,
+    out: *mut *mut MetamorphosisVersionFile,
+) -> MetamorphosisStatus {
+    run(|| {
+        out_argument(out)?;
+        let mojang_file = match handle_argument(version_file)? {
+            MetamorphosisVersionFile::Mojang(mojang_file) => mojang_file,
+            MetamorphosisVersionFile::PolyMC(_) => {
+                return Err(FfiError::new(
+                    MetamorphosisStatus::Parse,
+                    "version file is already a PolyMC version file",
+                ))
+            }
+        };
+        let pmc_file = PolyMCVersionFile::from_mojang_file(
+            mojang_file,
+            str_argument(name)?.to_string(),
+            str_argument(uid)?.to_string(),
+            str_argument(version)?.to_string(),
+        )
+        .map_err(|e| FfiError::new(MetamorphosisStatus::Parse, e))?;
+
+        *out = Box::into_raw(Box::new(MetamorphosisVersionFile::PolyMC(Box::new(
+            pmc_file,
+        ))));
+        Ok(())
+    })
+}
+
+/// Serializes a version file to JSON.
+///
+/// The JSON must be freed with [`metamorphosis_string_free`].
+///
+/// # Safety
+///
+/// `version_file` must be a handle returned by this library and `json` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn metamorphosis_version_file_to_json(
+    version_file: *const MetamorphosisVersionFile,
+    json: *mut *mut c_char,
+) -> MetamorphosisStatus {
+    run(|| {
+        out_argument(json)?;
+        let serialized = match handle_argument(version_file)? {
+            MetamorphosisVersionFile::Mojang(file) => serde_json::to_string(file)?,
+            MetamorphosisVersionFile::PolyMC(file) => serde_json::to_string(file)?,
+        };
+
+        *json = to_c_string(serialized)?;
+        Ok(())
+    })
+}
+
+/// Frees a version file. Passing null does nothing.
+///
+/// # Safety
+///
+/// `version_file` must be null or a handle returned by this library, which wasn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn metamorphosis_version_file_free(
+    version_file: *mut MetamorphosisVersionFile,
+) {
+    if !version_file.is_null() {
+        drop(Box::from_raw(version_file));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> Option<String> {
+        let message = metamorphosis_last_error();
+        (!message.is_null()).then(|| {
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
+    fn c_string(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    #[test]
+    fn converts_a_mojang_version_file_to_polymc_json() {
+        let json = c_string(include_str!("../tests/data/mojang/versions/1.18.1.json"));
+        let (uid, name, version) = (
+            c_string("net.minecraft"),
+            c_string("Minecraft"),
+            c_string("1.18.1"),
+        );
+
+        unsafe {
+            let mut mojang_file = ptr::null_mut();
+            let status = metamorphosis_version_file_parse(
+                MetamorphosisVersionFormat::Mojang,
+                json.as_ptr(),
+                &mut mojang_file,
+            );
+            assert_eq!(status, MetamorphosisStatus::Ok);
+            assert_eq!(last_error(), None);
+
+            let mut pmc_file = ptr::null_mut();
+            let status = metamorphosis_version_file_to_polymc(
+                mojang_file,
+                uid.as_ptr(),
+                name.as_ptr(),
+                version.as_ptr(),
+                &mut pmc_file,
+            );
+            assert_eq!(status, MetamorphosisStatus::Ok);
+            let mut format = MetamorphosisVersionFormat::Mojang;
+            assert_eq!(
+                metamorphosis_version_file_format(pmc_file, &mut format),
+                MetamorphosisStatus::Ok
+            );
+            assert_eq!(format, MetamorphosisVersionFormat::PolyMC);
+
+            let mut pmc_json = ptr::null_mut();
+            let status = metamorphosis_version_file_to_json(pmc_file, &mut pmc_json);
+            assert_eq!(status, MetamorphosisStatus::Ok);
+            let serialized: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(pmc_json).to_str().unwrap()).unwrap();
+            assert_eq!(serialized["uid"], "net.minecraft");
+            assert_eq!(serialized["version"], "1.18.1");
+
+            metamorphosis_string_free(pmc_json);
+            metamorphosis_version_file_free(pmc_file);
+            metamorphosis_version_file_free(mojang_file);
+        }
+    }
+
+    #[test]
+    fn rejects_null_and_invalid_utf8_strings() {
+        let invalid_utf8 = CString::new(vec![b'{', 0xff, b'}']).unwrap();
+
+        unsafe {
+            let mut version_file = ptr::null_mut();
+            let status = metamorphosis_version_file_parse(
+                MetamorphosisVersionFormat::PolyMC,
+                ptr::null(),
+                &mut version_file,
+            );
+            assert_eq!(status, MetamorphosisStatus::NullPointer);
+            assert_eq!(last_error().as_deref(), Some("string argument is null"));
+
+            let status = metamorphosis_version_file_parse(
+                MetamorphosisVersionFormat::PolyMC,
+                invalid_utf8.as_ptr(),
+                &mut version_file,
+            );
+            assert_eq!(status, MetamorphosisStatus::InvalidUtf8);
+            assert!(last_error().unwrap().contains("invalid utf-8"));
+            assert!(version_file.is_null());
+        }
+    }
+
+    #[test]
+    fn leaves_the_out_argument_alone_on_parse_errors() {
+        let json = c_string(r#"{"formatVersion": "one"}"#);
+        let mut previous = MetamorphosisVersionFile::PolyMC(Box::new(PolyMCVersionFile::new(
+            "Minecraft".to_string(),
+            "1.18.1".to_string(),
+            "net.minecraft".to_string(),
+        )));
+        let previous_ptr: *mut MetamorphosisVersionFile = &mut previous;
+
+        unsafe {
+            let mut version_file = previous_ptr;
+            let status = metamorphosis_version_file_parse(
+                MetamorphosisVersionFormat::PolyMC,
+                json.as_ptr(),
+                &mut version_file,
+            );
+            assert_eq!(status, MetamorphosisStatus::Parse);
+            assert_eq!(version_file, previous_ptr);
+            assert!(last_error().is_some());
+        }
+    }
+}
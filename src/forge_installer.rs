@@ -0,0 +1,512 @@
+//! Resolves the processors of a v2 Forge installer profile into the Java invocations the Forge installer would run,
+//! so the installation can be reproduced without it.
+//!
+//! Tokens are replaced the way the installer does it: `{KEY}` is replaced with the data entry `KEY`, `'text'` is
+//! literal text and `\` escapes the next character. Arguments written as `[group:artifact:version]` are paths of
+//! libraries.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Serialize;
+
+use crate::models::{
+    forge::{ForgeInstallerProfileV2, ProcessorSpec},
+    misc::GradleSpecifier,
+    mojang::MojangLibrary,
+};
+
+custom_error! {
+    /// Errors that can occur when resolving the processors of a Forge installer profile.
+    pub InstallerError
+        InvalidSide { side: String } = "Invalid side '{side}', expected client or server",
+        UnsupportedProfile = "The installer profile has no spec, only installers since 1.12.2-14.23.5.2851 have processors",
+        MissingMinecraftVersion = "The installer profile has no Minecraft version",
+        MissingJar { processor: usize } = "Processor {processor} has no jar",
+        InvalidArtifact { artifact: String } = "Invalid artifact '{artifact}'",
+        UnknownData { key: String, token: String } = "Unknown data {{{key}}} in '{token}'",
+        InvalidToken { token: String } = "Unclosed token or trailing escape in '{token}'",
+}
+
+/// The side the installation is for.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallSide {
+    Client,
+    Server,
+}
+
+impl InstallSide {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Client => "client",
+            Self::Server => "server",
+        }
+    }
+}
+
+impl FromStr for InstallSide {
+    type Err = InstallerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "client" => Ok(Self::Client),
+            "server" => Ok(Self::Server),
+            _ => Err(InstallerError::InvalidSide {
+                side: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl Display for InstallSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Where the installation happens, the paths are used in the arguments of the processors as given.
+#[derive(Debug, Clone)]
+pub struct InstallDirectories {
+    root: PathBuf,
+    installer: PathBuf,
+    libraries: Option<PathBuf>,
+    minecraft_jar: Option<PathBuf>,
+    extract: Option<PathBuf>,
+}
+
+impl InstallDirectories {
+    /// Installs into `root` with the installer jar at `installer`.
+    pub fn new<R, I>(root: R, installer: I) -> Self
+    where
+        R: AsRef<Path>,
+        I: AsRef<Path>,
+    {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            installer: installer.as_ref().to_path_buf(),
+            libraries: None,
+            minecraft_jar: None,
+            extract: None,
+        }
+    }
+
+    /// The library directory, `{root}/libraries` by default.
+    pub fn with_libraries<P>(mut self, libraries: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.libraries = Some(libraries.as_ref().to_path_buf());
+        self
+    }
+
+    /// The vanilla jar of the side, by default `{root}/versions/{version}/{version}.jar` for the client and the
+    /// `serverJarPath` of the profile or `{root}/minecraft_server.{version}.jar` for the server.
+    pub fn with_minecraft_jar<P>(mut self, minecraft_jar: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.minecraft_jar = Some(minecraft_jar.as_ref().to_path_buf());
+        self
+    }
+
+    /// The directory files of the installer jar are extracted to, `{root}/forge-installer` by default.
+    pub fn with_extract<P>(mut self, extract: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.extract = Some(extract.as_ref().to_path_buf());
+        self
+    }
+
+    fn libraries(&self) -> PathBuf {
+        self.libraries
+            .clone()
+            .unwrap_or_else(|| self.root.join("libraries"))
+    }
+
+    fn extract(&self) -> PathBuf {
+        self.extract
+            .clone()
+            .unwrap_or_else(|| self.root.join("forge-installer"))
+    }
+}
+
+/// A Java invocation of a processor: `java -cp {classpath} {Main-Class of jar} {args}`.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessorInvocation {
+    pub jar: GradleSpecifier,
+    /// The processor jar first, its main class is the `Main-Class` of its manifest.
+    pub classpath: Vec<PathBuf>,
+    pub args: Vec<String>,
+    /// The files the processor writes and their expected SHA-1 hashes.
+    pub outputs: BTreeMap<String, String>,
+}
+
+/// The steps of a Forge installation for one side.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallPlan {
+    pub side: InstallSide,
+    pub minecraft_version: String,
+    /// Libraries to download before running the processors.
+    pub libraries: Vec<MojangLibrary>,
+    /// Files of the installer jar to extract before running the processors, as path in the jar -> destination.
+    pub extract: BTreeMap<String, PathBuf>,
+    pub processors: Vec<ProcessorInvocation>,
+}
+
+impl InstallPlan {
+    /// Resolves the processors of an installer profile which run on a side, in order.
+    pub fn new(
+        profile: &ForgeInstallerProfileV2,
+        side: InstallSide,
+        directories: &InstallDirectories,
+    ) -> Result<Self, InstallerError> {
+        // legacy profiles parse as well, every field is optional
+        if profile.spec.is_none() {
+            return Err(InstallerError::UnsupportedProfile);
+        }
+        let minecraft_version = profile
+            .minecraft
+            .clone()
+            .ok_or(InstallerError::MissingMinecraftVersion)?;
+        let libraries = directories.libraries();
+        let extract_directory = directories.extract();
+
+        let mut data = HashMap::new();
+        data.insert("SIDE".to_string(), side.to_string());
+        data.insert("MINECRAFT_VERSION".to_string(), minecraft_version.clone());
+        data.insert("ROOT".to_string(), path_string(&directories.root));
+        data.insert("INSTALLER".to_string(), path_string(&directories.installer));
+        data.insert("LIBRARY_DIR".to_string(), path_string(&libraries));
+        let minecraft_jar = match (&directories.minecraft_jar, side) {
+            (Some(minecraft_jar), _) => path_string(minecraft_jar),
+            (None, InstallSide::Client) => path_string(
+                &directories
+                    .root
+                    .join("versions")
+                    .join(&minecraft_version)
+                    .join(format!("{}.jar", minecraft_version)),
+            ),
+            (None, InstallSide::Server) => match &profile.server_jar_path {
+                Some(server_jar_path) => replace_tokens(server_jar_path, &data)?,
+                None => path_string(
+                    &directories
+                        .root
+                        .join(format!("minecraft_server.{}.jar", minecraft_version)),
+                ),
+            },
+        };
+        data.insert("MINECRAFT_JAR".to_string(), minecraft_jar);
+
+        let mut extract = BTreeMap::new();
+        for (key, spec) in profile.data.iter().flatten() {
+            let value = match side {
+                InstallSide::Client => &spec.client,
+                InstallSide::Server => &spec.server,
+            };
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+            let value = if let Some(artifact) = bracketed(value, '[', ']') {
+                path_string(&artifact_path(&libraries, artifact)?)
+            } else if let Some(literal) = bracketed(value, '\'', '\'') {
+                literal.to_string()
+            } else {
+                let destination = extract_directory.join(value.trim_start_matches('/'));
+                let destination_string = path_string(&destination);
+                extract.insert(value.clone(), destination);
+                destination_string
+            };
+            data.insert(key.clone(), value);
+        }
+
+        let processors = profile
+            .processors
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, processor)| runs_on(processor, side))
+            .map(|(i, processor)| resolve_processor(i, processor, &libraries, &data))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            side,
+            minecraft_version,
+            libraries: profile.libraries.clone().unwrap_or_default(),
+            extract,
+            processors,
+        })
+    }
+}
+
+/// Returns `true` if a processor runs on a side, processors without sides run on both.
+pub(crate) fn runs_on(processor: &ProcessorSpec, side: InstallSide) -> bool {
+    processor
+        .sides
+        .as_ref()
+        .map(|sides| sides.iter().any(|s| s == side.as_str()))
+        .unwrap_or(true)
+}
+
+fn resolve_processor(
+    index: usize,
+    processor: &ProcessorSpec,
+    libraries: &Path,
+    data: &HashMap<String, String>,
+) -> Result<ProcessorInvocation, InstallerError> {
+    let jar = processor
+        .jar
+        .as_deref()
+        .ok_or(InstallerError::MissingJar { processor: index })?;
+    let jar = parse_artifact(jar)?;
+
+    let mut classpath = vec![libraries.join(jar.path())];
+    for artifact in processor.classpath.iter().flatten() {
+        classpath.push(artifact_path(libraries, artifact)?);
+    }
+
+    let mut args = Vec::new();
+    for arg in processor.args.iter().flatten() {
+        let arg = match bracketed(arg, '[', ']') {
+            Some(artifact) => path_string(&artifact_path(libraries, artifact)?),
+            None => replace_tokens(arg, data)?,
+        };
+        args.push(arg);
+    }
+
+    let mut outputs = BTreeMap::new();
+    for (file, sha1) in processor.outputs.iter().flatten() {
+        outputs.insert(replace_tokens(file, data)?, replace_tokens(sha1, data)?);
+    }
+
+    Ok(ProcessorInvocation {
+        jar,
+        classpath,
+        args,
+        outputs,
+    })
+}
+
+/// Replaces `{KEY}` with data entries and `'text'` with the text.
+pub(crate) fn replace_tokens(
+    token: &str,
+    data: &HashMap<String, String>,
+) -> Result<String, InstallerError> {
+    let invalid_token = || InstallerError::InvalidToken {
+        token: token.to_string(),
+    };
+    let mut result = String::new();
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.push(chars.next().ok_or_else(invalid_token)?),
+            '{' | '\'' => {
+                let end = if c == '{' { '}' } else { '\'' };
+                let mut key = String::new();
+                loop {
+                    match chars.next().ok_or_else(invalid_token)? {
+                        '\\' => key.push(chars.next().ok_or_else(invalid_token)?),
+                        d if d == end => break,
+                        d => key.push(d),
+                    }
+                }
+                if c == '\'' {
+                    result.push_str(&key);
+                } else {
+                    let value = data.get(&key).ok_or_else(|| InstallerError::UnknownData {
+                        key: key.clone(),
+                        token: token.to_string(),
+                    })?;
+                    result.push_str(value);
+                }
+            }
+            c => result.push(c),
+        }
+    }
+
+    Ok(result)
+}
+
+fn bracketed(value: &str, start: char, end: char) -> Option<&str> {
+    value
+        .strip_prefix(start)
+        .and_then(|value| value.strip_suffix(end))
+}
+
+fn parse_artifact(artifact: &str) -> Result<GradleSpecifier, InstallerError> {
+    artifact
+        .parse()
+        .map_err(|_| InstallerError::InvalidArtifact {
+            artifact: artifact.to_string(),
+        })
+}
+
+fn artifact_path(libraries: &Path, artifact: &str) -> Result<PathBuf, InstallerError> {
+    Ok(libraries.join(parse_artifact(artifact)?.path()))
+}
+
+fn path_string(path: &Path) -> String {
+    path.display().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A v1 spec profile, trimmed to one processor of each kind, the hashes aren't the real ones.
+    static INSTALL_PROFILE: &str =
+        include_str!("../tests/data/forge/installer_manifests/1.18.2-40.1.0.json");
+
+    fn plan(side: InstallSide) -> InstallPlan {
+        let profile: ForgeInstallerProfileV2 = serde_json::from_str(INSTALL_PROFILE).unwrap();
+        let directories = InstallDirectories::new("/root", "/tmp/installer.jar");
+        InstallPlan::new(&profile, side, &directories).unwrap()
+    }
+
+    fn args(processor: &ProcessorInvocation) -> Vec<&str> {
+        processor.args.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn resolves_client_processors() {
+        let plan = plan(InstallSide::Client);
+
+        assert_eq!(plan.minecraft_version, "1.18.2");
+        let jars: Vec<String> = plan
+            .processors
+            .iter()
+            .map(|processor| processor.jar.to_string())
+            .collect();
+        assert_eq!(
+            jars,
+            vec![
+                "net.minecraftforge:installertools:1.2.10",
+                "net.minecraftforge:installertools:1.2.10",
+                "net.minecraftforge:jarsplitter:1.1.4",
+                "net.minecraftforge:binarypatcher:1.1.1",
+            ]
+        );
+        // [artifact] arguments are library paths
+        assert_eq!(
+            args(&plan.processors[0]),
+            vec![
+                "--task",
+                "MCP_DATA",
+                "--input",
+                "/root/libraries/de/oceanlabs/mcp/mcp_config/1.18.2-20220404.173914/mcp_config-1.18.2-20220404.173914.zip",
+                "--output",
+                "/root/libraries/de/oceanlabs/mcp/mcp_config/1.18.2-20220404.173914/mcp_config-1.18.2-20220404.173914-mappings.txt",
+                "--key",
+                "mappings",
+            ]
+        );
+        assert_eq!(
+            args(&plan.processors[1]),
+            vec![
+                "--task",
+                "DOWNLOAD_MOJMAPS",
+                "--version",
+                "1.18.2",
+                "--side",
+                "client",
+                "--output",
+                "/root/libraries/net/minecraft/client/1.18.2-20220404.173914/client-1.18.2-20220404.173914-mappings.txt",
+            ]
+        );
+        assert_eq!(
+            args(&plan.processors[2])[..2],
+            ["--input", "/root/versions/1.18.2/1.18.2.jar"]
+        );
+        assert_eq!(
+            args(&plan.processors[3]),
+            vec![
+                "--clean",
+                "/root/libraries/net/minecraft/client/1.18.2-20220404.173914/client-1.18.2-20220404.173914-srg.jar",
+                "--output",
+                "/root/libraries/net/minecraftforge/forge/1.18.2-40.1.0/forge-1.18.2-40.1.0-client.jar",
+                "--apply",
+                "/root/forge-installer/data/client.lzma",
+            ]
+        );
+        // files of the installer jar are extracted
+        assert_eq!(
+            plan.extract,
+            BTreeMap::from([(
+                "/data/client.lzma".to_string(),
+                PathBuf::from("/root/forge-installer/data/client.lzma")
+            )])
+        );
+        assert_eq!(
+            plan.processors[3].outputs,
+            BTreeMap::from([(
+                "/root/libraries/net/minecraftforge/forge/1.18.2-40.1.0/forge-1.18.2-40.1.0-client.jar"
+                    .to_string(),
+                "a1d35ac51bd3bd68d7bd1b7ef9b5cca1d2a4c26f".to_string()
+            )])
+        );
+        assert_eq!(
+            plan.processors[3].classpath[0],
+            PathBuf::from(
+                "/root/libraries/net/minecraftforge/binarypatcher/1.1.1/binarypatcher-1.1.1.jar"
+            )
+        );
+    }
+
+    #[test]
+    fn resolves_server_processors() {
+        let plan = plan(InstallSide::Server);
+
+        assert_eq!(plan.processors.len(), 5);
+        // the server jar comes from serverJarPath
+        assert_eq!(
+            args(&plan.processors[0]),
+            vec![
+                "--task",
+                "BUNDLER_EXTRACT",
+                "--input",
+                "/root/libraries/net/minecraft/server/1.18.2/server-1.18.2.jar",
+                "--output",
+                "/root/libraries/net/minecraft/server/1.18.2-20220404.173914/server-1.18.2-20220404.173914-unpacked.jar",
+                "--jar-only",
+            ]
+        );
+        assert_eq!(args(&plan.processors[2])[5], "server");
+        assert_eq!(
+            args(&plan.processors[3])[..2],
+            [
+                "--input",
+                "/root/libraries/net/minecraft/server/1.18.2-20220404.173914/server-1.18.2-20220404.173914-unpacked.jar"
+            ]
+        );
+        assert_eq!(
+            args(&plan.processors[4])[5],
+            "/root/forge-installer/data/server.lzma"
+        );
+    }
+
+    #[test]
+    fn replaces_tokens() {
+        let data = HashMap::from([("KEY".to_string(), "value".to_string())]);
+
+        assert_eq!(
+            replace_tokens("{KEY}/'{KEY}'/\\{", &data).unwrap(),
+            "value/{KEY}/{"
+        );
+        assert!(matches!(
+            replace_tokens("{MISSING}", &data),
+            Err(InstallerError::UnknownData { .. })
+        ));
+        assert!(matches!(
+            replace_tokens("'unclosed", &data),
+            Err(InstallerError::InvalidToken { .. })
+        ));
+    }
+}
@@ -0,0 +1,178 @@
+use crate::models::{
+    fabric::{FabricInstallerDataV1, FabricJarInfo},
+    misc::GradleSpecifier,
+    polymc::{DependencyEntry, PolyMCLibrary, PolyMCSharedPackageData, PolyMCVersionFile},
+};
+
+use super::{PolyMCGenerator, FABRIC_INTERMEDIARY_UID, FABRIC_LOADER_UID, MINECRAFT_UID};
+
+static FABRIC_MAVEN_URL: &str = "https://maven.fabricmc.net";
+
+/// Returns the string value of a field of a Fabric meta entry.
+fn meta_field<'a>(entry: &'a serde_json::Value, field: &str) -> std::io::Result<&'a str> {
+    entry
+        .get(field)
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| std::io::Error::other(format!("Fabric meta entry has no {} field", field)))
+}
+
+impl PolyMCGenerator {
+    /// Generates the Fabric intermediary and loader packages from the cached Fabric meta.
+    pub(super) fn generate_fabric(&mut self) -> std::io::Result<()> {
+        self.generate_fabric_intermediary()?;
+        self.generate_fabric_loader()
+    }
+
+    /// Reads the cached jar information of a Fabric artifact.
+    fn read_fabric_jar_info(&self, maven: &str) -> std::io::Result<Option<FabricJarInfo>> {
+        self.read_cache(format!("fabric/jars/{}.json", maven.replace(':', ".")))
+    }
+
+    fn generate_fabric_intermediary(&mut self) -> std::io::Result<()> {
+        let index: Vec<serde_json::Value> =
+            match self.read_cache("fabric/meta-v2/intermediary.json")? {
+                Some(index) => index,
+                None => {
+                    info!("No cached Fabric intermediary index found, skipping intermediary...");
+                    return Ok(());
+                }
+            };
+
+        info!("Generating Fabric intermediary versions...");
+        let mut versions = Vec::new();
+        for entry in &index {
+            let maven = meta_field(entry, "maven")?;
+            let version = meta_field(entry, "version")?;
+            let jar_info = self.read_fabric_jar_info(maven)?;
+
+            let mut pmc_file = PolyMCVersionFile::new(
+                "Intermediary Mappings".to_string(),
+                version.to_string(),
+                FABRIC_INTERMEDIARY_UID.to_string(),
+            );
+            pmc_file.requires = Some(vec![DependencyEntry {
+                uid: MINECRAFT_UID.to_string(),
+                equal: Some(version.to_string()),
+                suggests: None,
+            }]);
+            // the mappings always match the exact Minecraft version, so they are swapped along with it
+            pmc_file.volatile = Some(true);
+            pmc_file.order = Some(11);
+            pmc_file.release_time = jar_info.and_then(|jar_info| jar_info.release_time);
+            pmc_file.version_file_type = Some("release".to_string());
+            pmc_file.libraries = Some(vec![PolyMCLibrary::new(
+                maven.parse().map_err(std::io::Error::other)?,
+                Some(FABRIC_MAVEN_URL.to_string()),
+            )]);
+            versions.push(self.write_version_file(&pmc_file)?);
+        }
+
+        let mut package = PolyMCSharedPackageData::new(
+            FABRIC_INTERMEDIARY_UID.to_string(),
+            "Intermediary Mappings".to_string(),
+        );
+        package.description = Some(
+            "Intermediary mappings allow using Fabric Loader with mods for Minecraft in a more compatible manner."
+                .to_string(),
+        );
+        package.project_url = Some("https://fabricmc.net".to_string());
+        package.authors = Some(vec!["Fabric Developers".to_string()]);
+        self.write_package(&package, versions)?;
+        self.mark_complete(&[FABRIC_INTERMEDIARY_UID]);
+        Ok(())
+    }
+
+    fn generate_fabric_loader(&mut self) -> std::io::Result<()> {
+        let index: Vec<serde_json::Value> = match self.read_cache("fabric/meta-v2/loader.json")? {
+            Some(index) => index,
+            None => {
+                info!("No cached Fabric loader index found, skipping loader...");
+                return Ok(());
+            }
+        };
+
+        info!("Generating Fabric loader versions...");
+        let mut versions = Vec::new();
+        let mut recommended = None;
+        let mut complete = true;
+        for entry in &index {
+            let maven = meta_field(entry, "maven")?;
+            let version = meta_field(entry, "version")?;
+            let stable = entry.get("stable").and_then(|stable| stable.as_bool()) == Some(true);
+            let installer_data: Option<FabricInstallerDataV1> =
+                self.read_cache(format!("fabric/loader-installer-json/{}.json", version))?;
+            let installer_data = match installer_data {
+                Some(installer_data) => installer_data,
+                None => {
+                    warn!(
+                        "Skipping Fabric loader {}, its installer JSON isn't cached",
+                        version
+                    );
+                    complete = false;
+                    continue;
+                }
+            };
+            let jar_info = self.read_fabric_jar_info(maven)?;
+
+            let mut pmc_file = PolyMCVersionFile::new(
+                "Fabric Loader".to_string(),
+                version.to_string(),
+                FABRIC_LOADER_UID.to_string(),
+            );
+            pmc_file.requires = Some(vec![DependencyEntry {
+                uid: FABRIC_INTERMEDIARY_UID.to_string(),
+                equal: None,
+                suggests: None,
+            }]);
+            pmc_file.order = Some(10);
+            pmc_file.release_time = jar_info.and_then(|jar_info| jar_info.release_time);
+            pmc_file.version_file_type =
+                Some(if stable { "release" } else { "snapshot" }.to_string());
+            // newer installer JSONs split the main class by side
+            pmc_file.main_class = match &installer_data.main_class {
+                serde_json::Value::String(main_class) => Some(main_class.clone()),
+                main_class => main_class
+                    .get("client")
+                    .and_then(|main_class| main_class.as_str())
+                    .map(|main_class| main_class.to_string()),
+            };
+            pmc_file.add_tweakers = installer_data
+                .launch_wrapper
+                .as_ref()
+                .and_then(|launch_wrapper| launch_wrapper.tweakers.client.clone());
+
+            let mut libraries = Vec::new();
+            libraries.extend(installer_data.libraries.common.iter().flatten().cloned());
+            libraries.extend(installer_data.libraries.client.iter().flatten().cloned());
+            let loader_name: GradleSpecifier = maven.parse().map_err(std::io::Error::other)?;
+            libraries.push(PolyMCLibrary::new(
+                loader_name,
+                Some(FABRIC_MAVEN_URL.to_string()),
+            ));
+            pmc_file.libraries = Some(libraries);
+
+            // the meta lists the newest loader first
+            if stable && recommended.is_none() {
+                recommended = Some(version.to_string());
+            }
+            versions.push(self.write_version_file(&pmc_file)?);
+        }
+
+        let mut package = PolyMCSharedPackageData::new(
+            FABRIC_LOADER_UID.to_string(),
+            "Fabric Loader".to_string(),
+        );
+        package.description = Some(
+            "Fabric Loader is a tool to load Fabric-compatible mods in game environments."
+                .to_string(),
+        );
+        package.project_url = Some("https://fabricmc.net".to_string());
+        package.authors = Some(vec!["Fabric Developers".to_string()]);
+        package.recommended = recommended.map(|recommended| vec![recommended]);
+        self.write_package(&package, versions)?;
+        if complete {
+            self.mark_complete(&[FABRIC_LOADER_UID]);
+        }
+        Ok(())
+    }
+}
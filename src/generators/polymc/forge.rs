@@ -0,0 +1,798 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use crate::{
+    forge_installer::{replace_tokens, runs_on, InstallSide},
+    models::{
+        forge::{
+            DerivedForgeIndex, ForgeInstallerProfile, ForgeInstallerProfileV2, ForgeLegacyInfo,
+            ForgeLegacyInfoList, ForgeServerIndex, ForgeServerIndexEntry, ForgeServerVersion,
+            ForgeVersion, InstallerInfo, FML_LIB_MAPPING, FORGE_LEGACY_INFO,
+        },
+        misc::GradleSpecifier,
+        mojang::{
+            MojangArtifact, MojangArtifactBase, MojangLibrary, MojangLibraryDownloads,
+            MojangVersionFile,
+        },
+        polymc::{DependencyEntry, PolyMCLibrary, PolyMCSharedPackageData, PolyMCVersionFile},
+    },
+    version::MinecraftVersion,
+};
+
+use super::{PolyMCGenerator, FORGE_UID, MINECRAFT_UID};
+
+static FORGE_MAVEN_URL: &str = "https://maven.minecraftforge.net/";
+static FORGE_WRAPPER_MAIN_CLASS: &str = "io.github.zekerzhayard.forgewrapper.installer.Main";
+/// The arguments every modern version needs, PolyMC doesn't inherit them from the Minecraft component.
+static MODERN_MINECRAFT_ARGUMENTS: &str = "--username ${auth_player_name} --version ${version_name} --gameDir ${game_directory} --assetsDir ${assets_root} --assetIndex ${assets_index_name} --uuid ${auth_uuid} --accessToken ${auth_access_token} --userType ${user_type} --versionType ${version_type}";
+
+lazy_static! {
+    static ref TWEAK_CLASS_REGEX: regex::Regex =
+        regex::Regex::new(r"--tweakClass ([a-zA-Z0-9\.]+)").unwrap();
+}
+
+impl PolyMCGenerator {
+    /// Generates the `net.minecraftforge` package from the cached Forge index, installers and profiles.
+    pub(super) fn generate_forge(&mut self) -> std::io::Result<()> {
+        let index: DerivedForgeIndex = match self.read_cache("forge/derived_index.json")? {
+            Some(index) => index,
+            None => {
+                info!("No cached Forge index found, skipping Forge...");
+                return Ok(());
+            }
+        };
+        // the static list is a fallback for builds the updater hasn't processed
+        let legacy_info: ForgeLegacyInfoList = serde_json::from_str(FORGE_LEGACY_INFO)?;
+        let mut legacy_info = legacy_info.number.unwrap_or_default();
+        if let Some(cached_legacy_info) =
+            self.read_cache::<ForgeLegacyInfoList, _>("forge/legacy_info.json")?
+        {
+            legacy_info.extend(cached_legacy_info.number.unwrap_or_default());
+        }
+
+        info!("Generating Forge versions...");
+        let mut entries = index
+            .versions
+            .unwrap_or_default()
+            .into_values()
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.cmp_version(b));
+        let mut versions = Vec::new();
+        let mut recommended = Vec::new();
+        let mut complete = true;
+        for entry in entries {
+            let is_recommended = entry.recommended == Some(true);
+            let version: ForgeVersion = entry.into();
+            if !version.is_supported() {
+                info!(
+                    "Skipping Forge {}, it isn't supported",
+                    version.long_version
+                );
+                continue;
+            }
+
+            let pmc_file = if version.uses_installer() {
+                self.forge_installer_version_file(&version)?
+            } else {
+                legacy_version_file(&version, legacy_info.get(&version.build.to_string()))?
+            };
+            if let Some(pmc_file) = pmc_file {
+                if is_recommended {
+                    recommended.push(pmc_file.version.clone());
+                }
+                versions.push(self.write_version_file(&pmc_file)?);
+            } else {
+                complete = false;
+            }
+        }
+
+        let mut package = PolyMCSharedPackageData::new(FORGE_UID.to_string(), "Forge".to_string());
+        package.description = Some(
+            "Minecraft Forge is a free, open-source modding API all of your favourite mods use!"
+                .to_string(),
+        );
+        package.project_url = Some("https://www.minecraftforge.net/forum/".to_string());
+        package.authors = Some(vec![
+            "LexManos".to_string(),
+            "Eloraam".to_string(),
+            "Spacetoad".to_string(),
+        ]);
+        package.recommended = Some(recommended);
+        self.write_package(&package, versions)?;
+        if complete {
+            self.mark_complete(&[FORGE_UID]);
+        }
+        Ok(())
+    }
+
+    /// Creates the version file of a Forge version which is installed with an installer.
+    ///
+    /// Returns `None` if the installer wasn't processed by the updater.
+    fn forge_installer_version_file(
+        &self,
+        version: &ForgeVersion,
+    ) -> std::io::Result<Option<PolyMCVersionFile>> {
+        let profile: Option<serde_json::Value> = self.read_cache(format!(
+            "forge/installer_manifests/{}.json",
+            version.long_version
+        ))?;
+        let profile = match profile {
+            Some(profile) => profile,
+            None => {
+                warn!(
+                    "Skipping Forge {}, its installer profile isn't cached",
+                    version.long_version
+                );
+                return Ok(None);
+            }
+        };
+        if let Ok(profile) = serde_json::from_value::<ForgeInstallerProfile>(profile.clone()) {
+            return self.profile_version_file(version, &profile).map(Some);
+        }
+
+        // newer installers only list the libraries of the processors and ship a separate version.json
+        let installer_version: Option<MojangVersionFile> = self.read_cache(format!(
+            "forge/version_manifests/{}.json",
+            version.long_version
+        ))?;
+        let installer_info: Option<InstallerInfo> = self.read_cache(format!(
+            "forge/installer_info/{}.json",
+            version.long_version
+        ))?;
+        match (installer_version, installer_info) {
+            (Some(installer_version), Some(installer_info)) => {
+                let profile_libraries: Vec<MojangLibrary> = profile
+                    .get("libraries")
+                    .cloned()
+                    .map(serde_json::from_value)
+                    .transpose()?
+                    .unwrap_or_default();
+                build_system_version_file(
+                    version,
+                    &installer_version,
+                    &profile_libraries,
+                    &installer_info,
+                )
+                .map(Some)
+            }
+            _ => {
+                warn!(
+                    "Skipping Forge {}, its installer wasn't processed",
+                    version.long_version
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Creates the version file of a Forge version whose installer profile contains the full version info.
+    fn profile_version_file(
+        &self,
+        version: &ForgeVersion,
+        profile: &ForgeInstallerProfile,
+    ) -> std::io::Result<PolyMCVersionFile> {
+        let mut pmc_file = forge_version_file(version);
+        let version_info = &profile.version_info;
+        pmc_file.main_class = version_info.main_class.clone();
+        pmc_file.release_time = version_info.time;
+        if let Some(minecraft_arguments) = &version_info.minecraft_arguments {
+            let tweakers = TWEAK_CLASS_REGEX
+                .captures_iter(minecraft_arguments)
+                .map(|captures| captures[1].to_string())
+                .collect::<Vec<String>>();
+            if !tweakers.is_empty() {
+                pmc_file.add_tweakers = Some(tweakers);
+            }
+        }
+
+        let minecraft_version: Option<MojangVersionFile> =
+            self.read_cache(format!("mojang/versions/{}.json", version.mc_version_sane))?;
+        let minecraft_libraries = minecraft_version
+            .and_then(|minecraft_version| minecraft_version.libraries)
+            .unwrap_or_default();
+
+        let mut libraries = Vec::new();
+        for forge_library in version_info.libraries.iter().flatten() {
+            let name = &forge_library.library.name;
+            if name.is_lwjgl() || name.is_log4j() {
+                continue;
+            }
+            // the game already ships this library in the same or a newer version
+            let provided_by_minecraft = minecraft_libraries.iter().any(|library| {
+                library.name.group == name.group
+                    && library.name.artifact == name.artifact
+                    && library.name.maven_version() >= name.maven_version()
+            });
+            if provided_by_minecraft {
+                continue;
+            }
+
+            let mut library = forge_library.library.clone();
+            if library.name.group == "net.minecraftforge" {
+                if library.name.artifact == "minecraftforge" {
+                    library.name.artifact = "forge".to_string();
+                    library.name.classifier = Some("universal".to_string());
+                    library.name.version =
+                        format!("{}-{}", version.mc_version, library.name.version);
+                } else if library.name.artifact == "forge" {
+                    library.name.classifier = Some("universal".to_string());
+                }
+            }
+            libraries.push(PolyMCLibrary {
+                library,
+                url: forge_library_url(forge_library.url.as_deref()),
+                mmc_hint: None,
+            });
+        }
+        pmc_file.libraries = Some(libraries);
+
+        Ok(pmc_file)
+    }
+
+    /// Generates `forge-server/`, which describes how the server of every Forge version with an installer is
+    /// installed and started.
+    pub(super) fn generate_forge_server(&mut self) -> std::io::Result<()> {
+        let index: DerivedForgeIndex = match self.read_cache("forge/derived_index.json")? {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        info!("Generating Forge server versions...");
+        let mut entries = index
+            .versions
+            .unwrap_or_default()
+            .into_values()
+            .collect::<Vec<_>>();
+        // newest first
+        entries.sort_by(|a, b| b.cmp_version(a));
+        let mut versions = Vec::new();
+        let mut complete = true;
+        for entry in entries {
+            let version: ForgeVersion = entry.into();
+            if !version.is_supported() || !version.uses_installer() {
+                continue;
+            }
+            if let Some(server_version) = self.forge_server_version(&version)? {
+                let path = Path::new("forge-server").join(format!("{}.json", version.long_version));
+                let (sha256, _) = self.write_json(&path, &server_version)?;
+                versions.push(ForgeServerIndexEntry {
+                    version: server_version.version,
+                    minecraft_version: server_version.minecraft_version,
+                    sha256,
+                });
+            } else {
+                complete = false;
+            }
+        }
+
+        info!("Writing {} Forge server versions...", versions.len());
+        self.write_json(
+            Path::new("forge-server/index.json"),
+            &ForgeServerIndex { versions },
+        )?;
+        if complete {
+            self.mark_complete(&["forge-server"]);
+        }
+        Ok(())
+    }
+
+    /// Creates the server metadata of a Forge version from its cached installer profile.
+    ///
+    /// Returns `None` if the installer profile isn't cached.
+    fn forge_server_version(
+        &self,
+        version: &ForgeVersion,
+    ) -> std::io::Result<Option<ForgeServerVersion>> {
+        let profile: Option<serde_json::Value> = self.read_cache(format!(
+            "forge/installer_manifests/{}.json",
+            version.long_version
+        ))?;
+        let profile = match profile {
+            Some(profile) => profile,
+            None => return Ok(None),
+        };
+        let minecraft_version: Option<MojangVersionFile> =
+            self.read_cache(format!("mojang/versions/{}.json", version.mc_version_sane))?;
+        let minecraft_server = minecraft_version
+            .and_then(|minecraft_version| minecraft_version.downloads)
+            .and_then(|mut downloads| downloads.remove("server"));
+        let default_server_path = format!("minecraft_server.{}.jar", version.mc_version_sane);
+
+        if let Ok(profile) = serde_json::from_value::<ForgeInstallerProfile>(profile.clone()) {
+            // the installer copies the universal jar into the server directory and only downloads the libraries
+            // the server requires
+            let mut libraries = Vec::new();
+            for forge_library in profile.version_info.libraries.iter().flatten() {
+                if forge_library.server_req != Some(true) {
+                    continue;
+                }
+                libraries.push(PolyMCLibrary {
+                    library: forge_library.library.clone(),
+                    url: forge_library_url(forge_library.url.as_deref()),
+                    mmc_hint: None,
+                });
+            }
+            let mut universal = profile.install.path.clone();
+            universal.classifier = Some("universal".to_string());
+
+            return Ok(Some(ForgeServerVersion {
+                version: version.long_version.clone(),
+                minecraft_version: version.mc_version_sane.clone(),
+                minecraft_server,
+                minecraft_server_path: default_server_path,
+                installer: None,
+                libraries,
+                forge_jar: Some(MojangArtifactBase {
+                    sha1: None,
+                    size: None,
+                    url: format!("{}{}", FORGE_MAVEN_URL, universal.path()),
+                }),
+                processors: Vec::new(),
+                data: BTreeMap::new(),
+                run_arguments: vec![
+                    "-jar".to_string(),
+                    profile.install.file_path.clone(),
+                    "nogui".to_string(),
+                ],
+            }));
+        }
+
+        let profile: ForgeInstallerProfileV2 = serde_json::from_value(profile).map_err(|e| {
+            std::io::Error::other(format!(
+                "Failed to parse the installer profile of Forge {}: {}",
+                version.long_version, e
+            ))
+        })?;
+        let installer_version: Option<MojangVersionFile> = self.read_cache(format!(
+            "forge/version_manifests/{}.json",
+            version.long_version
+        ))?;
+        let installer_info: Option<InstallerInfo> = self.read_cache(format!(
+            "forge/installer_info/{}.json",
+            version.long_version
+        ))?;
+
+        // the installer downloads the libraries of the processors and the ones of the server
+        let mut libraries: Vec<PolyMCLibrary> = Vec::new();
+        let all_libraries = profile.libraries.iter().flatten().chain(
+            installer_version
+                .iter()
+                .flat_map(|installer_version| installer_version.libraries.iter().flatten()),
+        );
+        for library in all_libraries {
+            if !libraries
+                .iter()
+                .any(|listed| listed.library.name == library.name)
+            {
+                libraries.push(library.clone().into());
+            }
+        }
+
+        let processors = profile
+            .processors
+            .iter()
+            .flatten()
+            .filter(|processor| runs_on(processor, InstallSide::Server))
+            .cloned()
+            .collect();
+        let data = profile
+            .data
+            .iter()
+            .flatten()
+            .filter_map(|(key, spec)| Some((key.clone(), spec.server.clone()?)))
+            .collect();
+        // the path is relative to the server directory, like the libraries
+        let path_data = HashMap::from([
+            ("SIDE".to_string(), InstallSide::Server.to_string()),
+            ("LIBRARY_DIR".to_string(), "libraries".to_string()),
+            (
+                "MINECRAFT_VERSION".to_string(),
+                version.mc_version_sane.clone(),
+            ),
+        ]);
+        let minecraft_server_path = match &profile.server_jar_path {
+            Some(server_jar_path) => replace_tokens(server_jar_path, &path_data).map_err(|e| {
+                std::io::Error::other(format!(
+                    "Invalid server jar path of Forge {}: {}",
+                    version.long_version, e
+                ))
+            })?,
+            None => default_server_path,
+        };
+        // since 1.17 the installer writes argument files instead of a server jar
+        let run_arguments =
+            if MinecraftVersion::new(&version.mc_version_sane) >= MinecraftVersion::new("1.17") {
+                vec![
+                    "@user_jvm_args.txt".to_string(),
+                    format!(
+                        "@libraries/net/minecraftforge/forge/{}/unix_args.txt",
+                        version.long_version
+                    ),
+                    "nogui".to_string(),
+                ]
+            } else {
+                vec![
+                    "-jar".to_string(),
+                    format!("forge-{}.jar", version.long_version),
+                    "nogui".to_string(),
+                ]
+            };
+
+        Ok(Some(ForgeServerVersion {
+            version: version.long_version.clone(),
+            minecraft_version: version.mc_version_sane.clone(),
+            minecraft_server,
+            minecraft_server_path,
+            installer: installer_info.map(|installer_info| MojangArtifactBase {
+                sha1: installer_info.sha1_hash,
+                size: installer_info.size.map(|size| size as i64),
+                url: version.url().unwrap_or_default(),
+            }),
+            libraries,
+            forge_jar: None,
+            processors,
+            data,
+            run_arguments,
+        }))
+    }
+}
+
+/// Replaces the old Forge Maven URL, which doesn't serve files anymore.
+fn forge_library_url(url: Option<&str>) -> Option<String> {
+    match url {
+        Some("http://files.minecraftforge.net/maven/") => Some(FORGE_MAVEN_URL.to_string()),
+        url => url.map(|url| url.to_string()),
+    }
+}
+
+/// Creates a version file with the fields every Forge version shares.
+fn forge_version_file(version: &ForgeVersion) -> PolyMCVersionFile {
+    let mut pmc_file = PolyMCVersionFile::new(
+        "Forge".to_string(),
+        version.raw_version.clone(),
+        FORGE_UID.to_string(),
+    );
+    pmc_file.requires = Some(vec![DependencyEntry {
+        uid: MINECRAFT_UID.to_string(),
+        equal: Some(version.mc_version_sane.clone()),
+        suggests: None,
+    }]);
+    pmc_file.order = Some(5);
+    pmc_file.version_file_type = Some("release".to_string());
+    pmc_file
+}
+
+/// Creates downloads which only consist of a single artifact.
+fn artifact_downloads(
+    url: String,
+    sha1: Option<String>,
+    size: Option<i64>,
+) -> MojangLibraryDownloads {
+    MojangLibraryDownloads {
+        artifact: Some(MojangArtifact {
+            artifact_base: MojangArtifactBase { sha1, size, url },
+            path: None,
+        }),
+        classifiers: None,
+    }
+}
+
+/// Creates the version file of a Forge version which predates the installer and is applied as a jar mod.
+///
+/// Returns `None` if there is no legacy info for the build.
+fn legacy_version_file(
+    version: &ForgeVersion,
+    legacy_info: Option<&ForgeLegacyInfo>,
+) -> std::io::Result<Option<PolyMCVersionFile>> {
+    let (url, legacy_info) = match (version.url(), legacy_info) {
+        (Some(url), Some(legacy_info)) => (url, legacy_info),
+        _ => {
+            warn!(
+                "Skipping Forge {}, there is no legacy info for build {}",
+                version.long_version, version.build
+            );
+            return Ok(None);
+        }
+    };
+
+    let mut pmc_file = forge_version_file(version);
+    pmc_file.release_time = legacy_info.release_time;
+    if FML_LIB_MAPPING.contains_key(&version.mc_version) {
+        pmc_file.add_traits = Some(vec!["legacyFML".to_string()]);
+    }
+
+    let classifier = if url.contains("universal") {
+        "universal"
+    } else {
+        "client"
+    };
+    let mut name: GradleSpecifier = format!(
+        "net.minecraftforge:forge:{}:{}",
+        version.long_version, classifier
+    )
+    .parse()
+    .map_err(std::io::Error::other)?;
+    // the oldest builds were distributed as zip files
+    name.extension = url
+        .rsplit('.')
+        .next()
+        .map(|extension| extension.to_string());
+    let mut jar_mod = PolyMCLibrary::new(name, None);
+    jar_mod.library.downloads = Some(artifact_downloads(
+        url,
+        legacy_info.sha1.clone(),
+        legacy_info.size.map(i64::from),
+    ));
+    pmc_file.jar_mods = Some(vec![jar_mod]);
+
+    Ok(Some(pmc_file))
+}
+
+/// Creates the library of ForgeWrapper, which runs the installer processors at launch.
+fn forge_wrapper_library() -> PolyMCLibrary {
+    let mut library = PolyMCLibrary::new(
+        GradleSpecifier {
+            group: "io.github.zekerzhayard".to_string(),
+            artifact: "ForgeWrapper".to_string(),
+            version: "1.5.5".to_string(),
+            extension: Some("jar".to_string()),
+            classifier: None,
+        },
+        None,
+    );
+    library.library.downloads = Some(artifact_downloads(
+        "https://github.com/ZekerZhayard/ForgeWrapper/releases/download/1.5.5/ForgeWrapper-1.5.5.jar"
+            .to_string(),
+        Some("566dfd60aacffaa02884614835f1151d36f1f985".to_string()),
+        Some(34331),
+    ));
+    library
+}
+
+/// Creates the version file of a Forge version built by the build system, which is installed through ForgeWrapper.
+fn build_system_version_file(
+    version: &ForgeVersion,
+    installer_version: &MojangVersionFile,
+    profile_libraries: &[MojangLibrary],
+    installer_info: &InstallerInfo,
+) -> std::io::Result<PolyMCVersionFile> {
+    let mut pmc_file = forge_version_file(version);
+    pmc_file.main_class = Some(FORGE_WRAPPER_MAIN_CLASS.to_string());
+    pmc_file.release_time = installer_version.release_time;
+
+    // ForgeWrapper needs the installer and the libraries of its processors
+    let installer_name: GradleSpecifier = format!(
+        "net.minecraftforge:forge:{}:installer",
+        version.long_version
+    )
+    .parse()
+    .map_err(std::io::Error::other)?;
+    let mut installer = PolyMCLibrary::new(installer_name, None);
+    installer.library.downloads = Some(artifact_downloads(
+        version.url().unwrap_or_default(),
+        installer_info.sha1_hash.clone(),
+        installer_info.size.map(|size| size as i64),
+    ));
+    let mut maven_files = vec![installer];
+    for library in profile_libraries {
+        if library.name.is_log4j() {
+            continue;
+        }
+
+        let mut library = PolyMCLibrary::from(library.clone());
+        let name = &library.library.name;
+        if name.group == "net.minecraftforge"
+            && name.artifact == "forge"
+            && name.classifier.as_deref() == Some("universal")
+        {
+            let url = format!("{}{}", FORGE_MAVEN_URL, name.path());
+            if let Some(artifact) = library
+                .library
+                .downloads
+                .as_mut()
+                .and_then(|downloads| downloads.artifact.as_mut())
+            {
+                artifact.artifact_base.url = url;
+            }
+        }
+        maven_files.push(library);
+    }
+    pmc_file.maven_files = Some(maven_files);
+
+    let mut libraries = vec![forge_wrapper_library()];
+    for library in installer_version.libraries.iter().flatten() {
+        if library.name.is_log4j() {
+            continue;
+        }
+
+        let mut library = PolyMCLibrary::from(library.clone());
+        if library.library.name.group == "net.minecraftforge"
+            && library.library.name.artifact == "forge"
+        {
+            // PolyMC loads the launcher jar, which is published next to the universal jar
+            library.library.name.classifier = Some("launcher".to_string());
+            let path = library.library.name.path();
+            if let Some(artifact) = library
+                .library
+                .downloads
+                .as_mut()
+                .and_then(|downloads| downloads.artifact.as_mut())
+            {
+                artifact.artifact_base.url = format!("{}{}", FORGE_MAVEN_URL, path);
+                artifact.path = Some(path);
+            }
+        }
+        libraries.push(library);
+    }
+    pmc_file.libraries = Some(libraries);
+
+    let mut minecraft_arguments = vec![MODERN_MINECRAFT_ARGUMENTS.to_string()];
+    minecraft_arguments.extend(
+        installer_version
+            .arguments
+            .iter()
+            .flat_map(|arguments| arguments.game.iter().flatten())
+            .filter_map(|argument| argument.as_str().map(|argument| argument.to_string())),
+    );
+    pmc_file.minecraft_arguments = Some(minecraft_arguments.join(" "));
+
+    Ok(pmc_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::forge::ForgeEntry;
+
+    static V2_PROFILE: &str =
+        include_str!("../../../tests/data/forge/installer_manifests/1.18.2-40.1.0.json");
+
+    fn forge_version(mc_version: &str, version: &str, branch: Option<&str>) -> ForgeVersion {
+        let entry: ForgeEntry = serde_json::from_value(json!({
+            "longversion": format!("{}-{}", mc_version, version),
+            "mcversion": mc_version,
+            "version": version,
+            "build": version.rsplit('.').next().unwrap().parse::<i32>().unwrap(),
+            "branch": branch
+        }))
+        .unwrap();
+        entry.into()
+    }
+
+    /// Creates the server version of a Forge version from a cache which only has its installer profile.
+    fn server_version(
+        name: &str,
+        version: &ForgeVersion,
+        profile: &serde_json::Value,
+    ) -> ForgeServerVersion {
+        let cache = std::env::temp_dir().join(format!(
+            "morphosis-meta-forge-server-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&cache);
+        let profile_path = cache.join(format!(
+            "forge/installer_manifests/{}.json",
+            version.long_version
+        ));
+        std::fs::create_dir_all(profile_path.parent().unwrap()).unwrap();
+        std::fs::write(&profile_path, profile.to_string()).unwrap();
+
+        let server_version = PolyMCGenerator::new(&cache, cache.join("output"))
+            .forge_server_version(version)
+            .unwrap()
+            .unwrap();
+        std::fs::remove_dir_all(&cache).unwrap();
+        server_version
+    }
+
+    #[test]
+    fn runs_the_server_processors_and_argument_files_since_1_17() {
+        let version = forge_version("1.18.2", "40.1.0", None);
+        let profile: serde_json::Value = serde_json::from_str(V2_PROFILE).unwrap();
+        let server_version = server_version("v2", &version, &profile);
+
+        assert_eq!(
+            server_version.minecraft_server_path,
+            "libraries/net/minecraft/server/1.18.2/server-1.18.2.jar"
+        );
+        // processors of the client side are left out
+        let jars: Vec<_> = server_version
+            .processors
+            .iter()
+            .map(|processor| processor.jar.as_deref().unwrap())
+            .collect();
+        assert_eq!(
+            jars,
+            vec![
+                "net.minecraftforge:installertools:1.2.10",
+                "net.minecraftforge:installertools:1.2.10",
+                "net.minecraftforge:installertools:1.2.10",
+                "net.minecraftforge:jarsplitter:1.1.4",
+                "net.minecraftforge:binarypatcher:1.1.1",
+            ]
+        );
+        assert_eq!(server_version.data["BINPATCH"], "/data/server.lzma");
+        assert_eq!(
+            server_version.run_arguments,
+            vec![
+                "@user_jvm_args.txt",
+                "@libraries/net/minecraftforge/forge/1.18.2-40.1.0/unix_args.txt",
+                "nogui",
+            ]
+        );
+        assert!(server_version.forge_jar.is_none());
+    }
+
+    #[test]
+    fn starts_the_forge_jar_before_1_17() {
+        let version = forge_version("1.16.5", "36.2.39", None);
+        let mut profile: serde_json::Value = serde_json::from_str(V2_PROFILE).unwrap();
+        profile["minecraft"] = json!("1.16.5");
+        profile["version"] = json!("1.16.5-forge-36.2.39");
+        profile
+            .as_object_mut()
+            .unwrap()
+            .remove("serverJarPath")
+            .unwrap();
+        let server_version = server_version("v2-jar", &version, &profile);
+
+        assert_eq!(
+            server_version.minecraft_server_path,
+            "minecraft_server.1.16.5.jar"
+        );
+        assert_eq!(
+            server_version.run_arguments,
+            vec!["-jar", "forge-1.16.5-36.2.39.jar", "nogui"]
+        );
+        assert_eq!(server_version.processors.len(), 5);
+    }
+
+    #[test]
+    fn downloads_the_server_libraries_of_legacy_installers() {
+        let version = forge_version("1.7.10", "10.13.4.1614", Some("1.7.10"));
+        let profile: serde_json::Value = serde_json::from_str(include_str!(
+            "../../../tests/data/forge/installer_manifests/1.7.10-10.13.4.1614-1.7.10.json"
+        ))
+        .unwrap();
+        let server_version = server_version("legacy", &version, &profile);
+
+        let libraries: Vec<_> = server_version
+            .libraries
+            .iter()
+            .map(|library| (library.library.name.to_string(), library.url.as_deref()))
+            .collect();
+        assert_eq!(
+            libraries,
+            vec![
+                ("net.minecraft:launchwrapper:1.12".to_string(), None),
+                ("org.ow2.asm:asm-all:5.0.3".to_string(), None),
+                (
+                    "com.typesafe.akka:akka-actor_2.11:2.3.3".to_string(),
+                    Some(FORGE_MAVEN_URL)
+                ),
+                ("lzma:lzma:0.0.1".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            server_version.forge_jar.unwrap().url,
+            format!(
+                "{}net/minecraftforge/forge/1.7.10-10.13.4.1614-1.7.10/forge-1.7.10-10.13.4.1614-1.7.10-universal.jar",
+                FORGE_MAVEN_URL
+            )
+        );
+        assert_eq!(
+            server_version.run_arguments,
+            vec![
+                "-jar",
+                "forge-1.7.10-10.13.4.1614-1.7.10-universal.jar",
+                "nogui"
+            ]
+        );
+        assert!(server_version.processors.is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::TimeZone;
+
+use crate::models::{
+    liteloader::LiteloaderIndex,
+    misc::GradleSpecifier,
+    polymc::{DependencyEntry, PolyMCLibrary, PolyMCSharedPackageData, PolyMCVersionFile},
+};
+
+use super::{PolyMCGenerator, LITELOADER_UID, MINECRAFT_UID};
+
+impl PolyMCGenerator {
+    /// Generates the `com.mumfrey.liteloader` package from the cached Liteloader index.
+    pub(super) fn generate_liteloader(&mut self) -> std::io::Result<()> {
+        let index: LiteloaderIndex = match self.read_cache("liteloader/versions.json")? {
+            Some(index) => index,
+            None => {
+                info!("No cached Liteloader index found, skipping Liteloader...");
+                return Ok(());
+            }
+        };
+
+        info!("Generating Liteloader versions...");
+        let mut versions = Vec::new();
+        let mut recommended = Vec::new();
+        let mut mc_versions = index
+            .versions
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<Vec<_>>();
+        mc_versions.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (mc_version, entry) in mc_versions {
+            let artifacts = match &entry.artifacts {
+                Some(artifacts) => &artifacts.liteloader,
+                None => continue,
+            };
+
+            for (key, artifact) in artifacts {
+                // "latest" duplicates the newest artifact of the Minecraft version
+                if key == "latest" {
+                    recommended.push(artifact.version.clone());
+                    continue;
+                }
+
+                let mut pmc_file = PolyMCVersionFile::new(
+                    "LiteLoader".to_string(),
+                    artifact.version.clone(),
+                    LITELOADER_UID.to_string(),
+                );
+                pmc_file.requires = Some(vec![DependencyEntry {
+                    uid: MINECRAFT_UID.to_string(),
+                    equal: Some(mc_version.clone()),
+                    suggests: None,
+                }]);
+                pmc_file.order = Some(10);
+                pmc_file.version_file_type = Some("release".to_string());
+                pmc_file.main_class = Some("net.minecraft.launchwrapper.Launch".to_string());
+                pmc_file.add_tweakers = Some(vec![artifact.tweak_class.clone()]);
+                pmc_file.release_time = artifact
+                    .timestamp
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|timestamp| chrono::Utc.timestamp_opt(timestamp, 0).single());
+
+                let mut libraries = artifact.libraries.clone();
+                for library in libraries.iter_mut() {
+                    // Liteloader's repository doesn't host this one
+                    if library.library.name.to_string() == "org.ow2.asm:asm-all:5.0.3" {
+                        library.url = Some("https://repo.maven.apache.org/maven2/".to_string());
+                    }
+                }
+                let liteloader_name: GradleSpecifier =
+                    format!("com.mumfrey:liteloader:{}", artifact.version)
+                        .parse()
+                        .map_err(std::io::Error::other)?;
+                libraries.push(PolyMCLibrary::new(
+                    liteloader_name,
+                    Some(entry.repo.url.clone()),
+                ));
+                pmc_file.libraries = Some(libraries);
+
+                versions.push(self.write_version_file(&pmc_file)?);
+            }
+        }
+
+        let mut package =
+            PolyMCSharedPackageData::new(LITELOADER_UID.to_string(), "LiteLoader".to_string());
+        package.description = Some(index.meta.description);
+        package.project_url = Some(index.meta.url);
+        package.authors = Some(vec![index.meta.authors]);
+        recommended.sort();
+        package.recommended = Some(recommended);
+        self.write_package(&package, versions)?;
+        self.mark_complete(&[LITELOADER_UID]);
+        Ok(())
+    }
+}
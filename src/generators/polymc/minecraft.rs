@@ -0,0 +1,658 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    models::{
+        mojang::{
+            MojangArtifact, MojangArtifactBase, MojangIndex, MojangLibraryDownloads,
+            MojangVersionFile,
+        },
+        polymc::{
+            DependencyEntry, LegacyOverrideIndex, PolyMCLibrary, PolyMCSharedPackageData,
+            PolyMCVersionFile, LWJGL2_UID, LWJGL3_UID,
+        },
+    },
+    version::MavenVersion,
+};
+
+use super::{PolyMCGenerator, MINECRAFT_UID};
+
+/// A Log4j version without Log4Shell and the later lookup vulnerabilities, which vulnerable versions are replaced with.
+struct Log4jPatch {
+    version: &'static str,
+    maven_url: &'static str,
+    /// Artifacts with their SHA-1 hash and size.
+    artifacts: &'static [(&'static str, &'static str, i64)],
+}
+
+/// 2.0-beta9 with the lookups removed, for the versions which can't run newer Log4j versions.
+static LOG4J_BETA9_PATCH: Log4jPatch = Log4jPatch {
+    version: "2.0-beta9-fixed",
+    maven_url: "https://files.polymc.org/maven/",
+    artifacts: &[
+        (
+            "log4j-api",
+            "b61eaf2e64d8b0277e188262a8b771bbfa1502b3",
+            107347,
+        ),
+        (
+            "log4j-core",
+            "677991ea2d7426f76309a73739cecf609679492c",
+            677588,
+        ),
+    ],
+};
+
+static LOG4J_PATCH: Log4jPatch = Log4jPatch {
+    version: "2.17.1",
+    maven_url: "https://repo1.maven.org/maven2/",
+    artifacts: &[
+        (
+            "log4j-api",
+            "d771af8e336e372fb5399c99edabe0919aeaf5b2",
+            301872,
+        ),
+        (
+            "log4j-core",
+            "779f60f3844dadc3ef597976fcb1e5127b1f343d",
+            1790452,
+        ),
+        (
+            "log4j-slf4j18-impl",
+            "ca499d751f4ddd8afb016ef698c30be0da1d09f7",
+            21268,
+        ),
+    ],
+};
+
+/// Disables lookups in Log4j 2.10 to 2.14.1, for vulnerable libraries which can't be replaced.
+static LOG4J_NO_LOOKUPS_ARGUMENT: &str = "-Dlog4j2.formatMsgNoLookups=true";
+
+/// The first Log4j version which knows [`LOG4J_NO_LOOKUPS_ARGUMENT`].
+static LOG4J_NO_LOOKUPS_VERSION: &str = "2.10";
+
+/// The LWJGL libraries of a Minecraft version, which are moved to a component of their own.
+struct LwjglVariant {
+    uid: &'static str,
+    version: String,
+    /// SHA-256 hash of the libraries, versions with the same hash share the component.
+    sha256: String,
+    /// The Minecraft version the variant was taken from.
+    minecraft_version: String,
+    file: PolyMCVersionFile,
+}
+
+impl PolyMCGenerator {
+    /// Generates the `net.minecraft` package from the cached Mojang versions.
+    pub(super) fn generate_minecraft(&mut self) -> std::io::Result<()> {
+        let index: MojangIndex = match self.read_cache("mojang/version_manifest_v2.json")? {
+            Some(index) => index,
+            None => {
+                info!("No cached Mojang index found, skipping Minecraft...");
+                return Ok(());
+            }
+        };
+
+        let legacy_overrides = LegacyOverrideIndex::load(self.legacy_overrides.as_deref())?;
+        let unknown_versions = legacy_overrides.unknown_versions(&index);
+        if !unknown_versions.is_empty() {
+            warn!(
+                "Legacy overrides of unknown Minecraft versions: {}",
+                unknown_versions.join(", ")
+            );
+        }
+
+        info!("Generating Minecraft versions...");
+        let mut versions = Vec::new();
+        let mut lwjgl_variants = BTreeMap::new();
+        let mut complete = true;
+        for entry in &index.versions {
+            let version_file: Option<MojangVersionFile> =
+                self.read_cache(format!("mojang/versions/{}.json", entry.id))?;
+            let version_file = match version_file {
+                Some(version_file) => version_file,
+                None => {
+                    warn!(
+                        "Skipping Minecraft {}, its version file isn't cached",
+                        entry.id
+                    );
+                    complete = false;
+                    continue;
+                }
+            };
+
+            let mut pmc_file = minecraft_version_file(&version_file, &entry.id)?;
+            let patches = patch_log4j(&mut pmc_file);
+            self.report.patched.extend(
+                patches
+                    .into_iter()
+                    .map(|patch| format!("{}/{}: {}", MINECRAFT_UID, entry.id, patch)),
+            );
+            let lwjgl_variant = split_lwjgl(&mut pmc_file)?;
+            // legacy versions are launched without their libraries, only LWJGL is kept
+            match legacy_overrides.versions.get(&entry.id) {
+                Some(legacy_override) => pmc_file.apply_legacy_override(legacy_override),
+                None if ["old_alpha", "old_beta"].contains(&entry.version_type.as_str())
+                    && legacy_overrides.covers(&entry.id) =>
+                {
+                    warn!(
+                        "Minecraft {} is a legacy version, but has no legacy override",
+                        entry.id
+                    )
+                }
+                None => {}
+            }
+            if let Some(variant) = lwjgl_variant {
+                add_lwjgl_variant(&mut lwjgl_variants, variant, &mut pmc_file);
+            }
+            versions.push(self.write_version_file(&pmc_file)?);
+        }
+
+        let mut package =
+            PolyMCSharedPackageData::new(MINECRAFT_UID.to_string(), "Minecraft".to_string());
+        package.recommended = index
+            .latest
+            .get("release")
+            .map(|release| vec![release.clone()]);
+        self.write_package(&package, versions)?;
+        self.write_lwjgl_packages(lwjgl_variants.into_values())?;
+
+        // the LWJGL versions are split from the Minecraft versions, so they are only complete along with them
+        if complete {
+            self.mark_complete(&[MINECRAFT_UID, LWJGL2_UID, LWJGL3_UID]);
+        }
+        Ok(())
+    }
+
+    /// Writes the `org.lwjgl` and `org.lwjgl3` packages.
+    fn write_lwjgl_packages<I>(&mut self, variants: I) -> std::io::Result<()>
+    where
+        I: IntoIterator<Item = LwjglVariant>,
+    {
+        let mut lwjgl2_versions = Vec::new();
+        let mut lwjgl3_versions = Vec::new();
+        for variant in variants {
+            let entry = self.write_version_file(&variant.file)?;
+            if variant.uid == LWJGL2_UID {
+                lwjgl2_versions.push(entry);
+            } else {
+                lwjgl3_versions.push(entry);
+            }
+        }
+
+        for (uid, name, versions) in [
+            (LWJGL2_UID, "LWJGL 2", lwjgl2_versions),
+            (LWJGL3_UID, "LWJGL 3", lwjgl3_versions),
+        ] {
+            if !versions.is_empty() {
+                let package = PolyMCSharedPackageData::new(uid.to_string(), name.to_string());
+                self.write_package(&package, versions)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces the Log4j libraries vulnerable to Log4Shell and returns a description of every change.
+///
+/// If a vulnerable library has no replacement, lookups are disabled with a JVM argument instead, which older Log4j
+/// versions ignore, so those stay vulnerable with a warning. PolyMC version files have no logging configuration, so
+/// the fixed configurations Mojang lists under `logging` can't be carried over.
+fn patch_log4j(pmc_file: &mut PolyMCVersionFile) -> Vec<String> {
+    let mut patches = Vec::new();
+    let mut unpatched = false;
+    let fixed_version = MavenVersion::new(LOG4J_PATCH.version);
+    for library in pmc_file.libraries.iter_mut().flatten() {
+        let name = &library.library.name;
+        if !name.is_log4j() || name.version == LOG4J_BETA9_PATCH.version {
+            continue;
+        }
+        let patch = if name.version == "2.0-beta9" {
+            &LOG4J_BETA9_PATCH
+        } else if name.maven_version() < fixed_version {
+            &LOG4J_PATCH
+        } else {
+            continue;
+        };
+
+        let replacement = patch
+            .artifacts
+            .iter()
+            .find(|(artifact, _, _)| *artifact == name.artifact);
+        let (_, sha1, size) = match replacement {
+            Some(replacement) => replacement,
+            None if name.maven_version() < MavenVersion::new(LOG4J_NO_LOOKUPS_VERSION) => {
+                warn!(
+                    "No replacement for {} in Minecraft {}, it stays vulnerable",
+                    name, pmc_file.version
+                );
+                continue;
+            }
+            None => {
+                warn!(
+                    "No replacement for {} in Minecraft {}, it stays vulnerable apart from lookups, which are disabled",
+                    name, pmc_file.version
+                );
+                unpatched = true;
+                continue;
+            }
+        };
+
+        let old_name = name.to_string();
+        let mut new_name = name.clone();
+        new_name.version = patch.version.to_string();
+        library.library.downloads = Some(MojangLibraryDownloads {
+            artifact: Some(MojangArtifact {
+                artifact_base: MojangArtifactBase {
+                    sha1: Some(sha1.to_string()),
+                    size: Some(*size),
+                    url: format!("{}{}", patch.maven_url, new_name.path()),
+                },
+                path: Some(new_name.path()),
+            }),
+            classifiers: None,
+        });
+        library.library.name = new_name;
+        patches.push(format!("{} -> {}", old_name, patch.version));
+    }
+
+    if unpatched {
+        pmc_file
+            .add_jvm_arguments
+            .get_or_insert_with(Vec::new)
+            .push(LOG4J_NO_LOOKUPS_ARGUMENT.to_string());
+        patches.push(format!("added {}", LOG4J_NO_LOOKUPS_ARGUMENT));
+    }
+
+    patches
+}
+
+/// Moves the LWJGL libraries of a Minecraft version to a version of the LWJGL 2 or 3 package, which the Minecraft
+/// version then requires.
+fn split_lwjgl(pmc_file: &mut PolyMCVersionFile) -> std::io::Result<Option<LwjglVariant>> {
+    let (lwjgl_libraries, libraries): (Vec<PolyMCLibrary>, Vec<PolyMCLibrary>) = pmc_file
+        .libraries
+        .take()
+        .unwrap_or_default()
+        .into_iter()
+        .partition(|library| library.library.name.is_lwjgl());
+    pmc_file.libraries = Some(libraries);
+    if lwjgl_libraries.is_empty() {
+        return Ok(None);
+    }
+
+    // LWJGL 3 moved to the org.lwjgl group, LWJGL 2 uses org.lwjgl.lwjgl
+    let (uid, name, group) = if lwjgl_libraries
+        .iter()
+        .any(|library| library.library.name.group == "org.lwjgl")
+    {
+        (LWJGL3_UID, "LWJGL 3", "org.lwjgl")
+    } else {
+        (LWJGL2_UID, "LWJGL 2", "org.lwjgl.lwjgl")
+    };
+    let version = lwjgl_libraries
+        .iter()
+        .find(|library| {
+            library.library.name.group == group && library.library.name.artifact == "lwjgl"
+        })
+        .map(|library| library.library.name.version.clone())
+        .ok_or_else(|| {
+            std::io::Error::other(format!(
+                "Minecraft {} has LWJGL libraries, but no {}:lwjgl",
+                pmc_file.version, group
+            ))
+        })?;
+    // going through a Value sorts the keys of natives and classifiers, identical sets have to hash the same
+    let contents = serde_json::to_vec(&serde_json::to_value(&lwjgl_libraries)?)?;
+    let sha256 = data_encoding::HEXLOWER
+        .encode(ring::digest::digest(&ring::digest::SHA256, &contents).as_ref());
+
+    let mut file = PolyMCVersionFile::new(name.to_string(), version.clone(), uid.to_string());
+    file.libraries = Some(lwjgl_libraries);
+    file.release_time = pmc_file.release_time;
+    file.version_file_type = Some("release".to_string());
+    file.order = Some(-1);
+    file.volatile = Some(true);
+    // only one of them can be on the class path
+    let other_uid = if uid == LWJGL2_UID {
+        LWJGL3_UID
+    } else {
+        LWJGL2_UID
+    };
+    file.conflicts = Some(vec![DependencyEntry {
+        uid: other_uid.to_string(),
+        equal: None,
+        suggests: None,
+    }]);
+
+    pmc_file
+        .requires
+        .get_or_insert_with(Vec::new)
+        .push(DependencyEntry {
+            uid: uid.to_string(),
+            equal: None,
+            suggests: Some(version.clone()),
+        });
+
+    Ok(Some(LwjglVariant {
+        uid,
+        version,
+        sha256,
+        minecraft_version: pmc_file.version.clone(),
+        file,
+    }))
+}
+
+/// Adds the LWJGL libraries of a Minecraft version to the variants, keyed by `(uid, version)`.
+///
+/// Versions are added newest first, so the newest libraries keep the LWJGL version. Other libraries with the same
+/// LWJGL version get the version suffixed with the start of their hash, which the Minecraft version suggests instead.
+fn add_lwjgl_variant(
+    variants: &mut BTreeMap<(&'static str, String), LwjglVariant>,
+    mut variant: LwjglVariant,
+    pmc_file: &mut PolyMCVersionFile,
+) {
+    if let Some(existing) = variants.get(&(variant.uid, variant.version.clone())) {
+        if existing.sha256 != variant.sha256 {
+            let version = format!("{}-{}", variant.version, &variant.sha256[..8]);
+            info!(
+                "Minecraft {} has other libraries for {} {} than Minecraft {}, adding them as {}",
+                variant.minecraft_version,
+                variant.uid,
+                variant.version,
+                existing.minecraft_version,
+                version
+            );
+            for requirement in pmc_file.requires.iter_mut().flatten() {
+                if requirement.uid == variant.uid {
+                    requirement.suggests = Some(version.clone());
+                }
+            }
+            variant.file.version.clone_from(&version);
+            variant.version = version;
+        }
+    }
+
+    variants
+        .entry((variant.uid, variant.version.clone()))
+        .or_insert(variant);
+}
+
+/// Converts a Mojang version file to a version file of the `net.minecraft` package.
+fn minecraft_version_file(
+    file: &MojangVersionFile,
+    id: &str,
+) -> std::io::Result<PolyMCVersionFile> {
+    let has_client = file
+        .downloads
+        .as_ref()
+        .map(|downloads| downloads.contains_key("client"))
+        .unwrap_or(false);
+    if !has_client {
+        return Err(std::io::Error::other(format!(
+            "Minecraft {} has no client download",
+            id
+        )));
+    }
+
+    let mut pmc_file = PolyMCVersionFile::from_mojang_file(
+        file,
+        "Minecraft".to_string(),
+        MINECRAFT_UID.to_string(),
+        id.to_string(),
+    )
+    .map_err(std::io::Error::other)?;
+    pmc_file.order = Some(-2);
+
+    if let Some(arguments) = &file.arguments {
+        // PolyMC only understands the legacy argument string, so only the unconditional arguments are kept
+        if pmc_file.minecraft_arguments.is_none() {
+            let game_arguments = arguments
+                .game
+                .iter()
+                .flatten()
+                .filter_map(|argument| argument.as_str())
+                .collect::<Vec<&str>>();
+            if !game_arguments.is_empty() {
+                pmc_file.minecraft_arguments = Some(game_arguments.join(" "));
+            }
+        }
+
+        let starts_on_first_thread = arguments
+            .jvm
+            .iter()
+            .flatten()
+            .any(|argument| argument.to_string().contains("-XstartOnFirstThread"));
+        if starts_on_first_thread {
+            pmc_file
+                .add_traits
+                .get_or_insert_with(Vec::new)
+                .push("FirstThreadOnMacOS".to_string());
+        }
+    }
+
+    Ok(pmc_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn minecraft_file(version: &str, lwjgl_url: &str) -> PolyMCVersionFile {
+        serde_json::from_value(json!({
+            "formatVersion": 1,
+            "name": "Minecraft",
+            "uid": MINECRAFT_UID,
+            "version": version,
+            "libraries": [
+                { "name": "org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209", "url": lwjgl_url },
+                { "name": "com.paulscode:soundsystem:20120107", "url": "https://libraries.minecraft.net/" }
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn suggested_lwjgl(pmc_file: &PolyMCVersionFile) -> Option<&str> {
+        pmc_file
+            .requires
+            .iter()
+            .flatten()
+            .find(|requirement| requirement.uid == LWJGL2_UID)
+            .and_then(|requirement| requirement.suggests.as_deref())
+    }
+
+    fn log4j_file(libraries: &[&str]) -> PolyMCVersionFile {
+        let libraries: Vec<_> = libraries
+            .iter()
+            .map(|name| json!({ "name": name, "url": "https://libraries.minecraft.net/" }))
+            .collect();
+        serde_json::from_value(json!({
+            "formatVersion": 1,
+            "name": "Minecraft",
+            "uid": MINECRAFT_UID,
+            "version": "1.18",
+            "libraries": libraries
+        }))
+        .unwrap()
+    }
+
+    /// The SHA-1 hash, size and URL of an artifact download.
+    type Download = (String, i64, String);
+
+    /// Returns the name and artifact download of every library.
+    fn downloads(pmc_file: &PolyMCVersionFile) -> Vec<(String, Option<Download>)> {
+        pmc_file
+            .libraries
+            .iter()
+            .flatten()
+            .map(|library| {
+                let artifact = library
+                    .library
+                    .downloads
+                    .as_ref()
+                    .and_then(|downloads| downloads.artifact.as_ref())
+                    .map(|artifact| {
+                        let base = &artifact.artifact_base;
+                        (
+                            base.sha1.clone().unwrap(),
+                            base.size.unwrap(),
+                            base.url.clone(),
+                        )
+                    });
+                (library.library.name.to_string(), artifact)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replaces_vulnerable_log4j_versions() {
+        let mut pmc_file = log4j_file(&[
+            "org.apache.logging.log4j:log4j-api:2.14.1",
+            "org.apache.logging.log4j:log4j-core:2.14.1",
+            "org.apache.logging.log4j:log4j-slf4j18-impl:2.14.1",
+            "com.mojang:brigadier:1.0.18",
+        ]);
+
+        let patches = patch_log4j(&mut pmc_file);
+        assert_eq!(
+            patches,
+            vec![
+                "org.apache.logging.log4j:log4j-api:2.14.1 -> 2.17.1",
+                "org.apache.logging.log4j:log4j-core:2.14.1 -> 2.17.1",
+                "org.apache.logging.log4j:log4j-slf4j18-impl:2.14.1 -> 2.17.1",
+            ]
+        );
+        let downloads = downloads(&pmc_file);
+        assert_eq!(
+            downloads[0],
+            (
+                "org.apache.logging.log4j:log4j-api:2.17.1".to_string(),
+                Some((
+                    "d771af8e336e372fb5399c99edabe0919aeaf5b2".to_string(),
+                    301872,
+                    "https://repo1.maven.org/maven2/org/apache/logging/log4j/log4j-api/2.17.1/log4j-api-2.17.1.jar"
+                        .to_string()
+                ))
+            )
+        );
+        assert_eq!(
+            downloads[1],
+            (
+                "org.apache.logging.log4j:log4j-core:2.17.1".to_string(),
+                Some((
+                    "779f60f3844dadc3ef597976fcb1e5127b1f343d".to_string(),
+                    1790452,
+                    "https://repo1.maven.org/maven2/org/apache/logging/log4j/log4j-core/2.17.1/log4j-core-2.17.1.jar"
+                        .to_string()
+                ))
+            )
+        );
+        assert_eq!(
+            downloads[3],
+            ("com.mojang:brigadier:1.0.18".to_string(), None)
+        );
+        assert!(pmc_file.add_jvm_arguments.is_none());
+    }
+
+    #[test]
+    fn replaces_log4j_beta9_with_the_fixed_build() {
+        let mut pmc_file = log4j_file(&[
+            "org.apache.logging.log4j:log4j-api:2.0-beta9",
+            "org.apache.logging.log4j:log4j-core:2.0-beta9-fixed",
+        ]);
+
+        let patches = patch_log4j(&mut pmc_file);
+        assert_eq!(
+            patches,
+            vec!["org.apache.logging.log4j:log4j-api:2.0-beta9 -> 2.0-beta9-fixed"]
+        );
+        let downloads = downloads(&pmc_file);
+        assert_eq!(
+            downloads[0],
+            (
+                "org.apache.logging.log4j:log4j-api:2.0-beta9-fixed".to_string(),
+                Some((
+                    "b61eaf2e64d8b0277e188262a8b771bbfa1502b3".to_string(),
+                    107347,
+                    "https://files.polymc.org/maven/org/apache/logging/log4j/log4j-api/2.0-beta9-fixed/log4j-api-2.0-beta9-fixed.jar"
+                        .to_string()
+                ))
+            )
+        );
+        // the fixed build is left alone
+        assert_eq!(
+            downloads[1],
+            (
+                "org.apache.logging.log4j:log4j-core:2.0-beta9-fixed".to_string(),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_log4j_versions_which_are_not_vulnerable() {
+        let mut pmc_file = log4j_file(&[
+            "org.apache.logging.log4j:log4j-api:2.17.1",
+            "org.apache.logging.log4j:log4j-core:2.19.0",
+        ]);
+
+        assert!(patch_log4j(&mut pmc_file).is_empty());
+        assert!(downloads(&pmc_file)
+            .iter()
+            .all(|(_, artifact)| artifact.is_none()));
+    }
+
+    #[test]
+    fn disables_lookups_of_log4j_artifacts_without_a_replacement() {
+        let mut pmc_file = log4j_file(&[
+            "org.apache.logging.log4j:log4j-1.2-api:2.14.1",
+            "org.apache.logging.log4j:log4j-jul:2.15.0",
+        ]);
+
+        assert_eq!(
+            patch_log4j(&mut pmc_file),
+            vec!["added -Dlog4j2.formatMsgNoLookups=true"]
+        );
+        assert_eq!(
+            pmc_file.add_jvm_arguments,
+            Some(vec![LOG4J_NO_LOOKUPS_ARGUMENT.to_string()])
+        );
+
+        // versions before 2.10 ignore the argument
+        let mut pmc_file = log4j_file(&["org.apache.logging.log4j:log4j-1.2-api:2.8.1"]);
+        assert!(patch_log4j(&mut pmc_file).is_empty());
+        assert!(pmc_file.add_jvm_arguments.is_none());
+    }
+
+    #[test]
+    fn keeps_lwjgl_variants_with_the_same_version_apart() {
+        let mut variants = BTreeMap::new();
+        let mut files = [
+            minecraft_file("1.12.2", "https://libraries.minecraft.net/"),
+            minecraft_file("1.12.1", "https://libraries.minecraft.net/"),
+            minecraft_file("1.12", "https://files.example.org/maven/"),
+        ];
+        for file in &mut files {
+            let variant = split_lwjgl(file).unwrap().unwrap();
+            add_lwjgl_variant(&mut variants, variant, file);
+        }
+
+        let versions: Vec<_> = variants
+            .values()
+            .map(|variant| (variant.version.as_str(), variant.minecraft_version.as_str()))
+            .collect();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0], ("2.9.4-nightly-20150209", "1.12.2"));
+        let suffixed = versions[1].0;
+        assert!(suffixed.starts_with("2.9.4-nightly-20150209-"));
+        assert_eq!(versions[1].1, "1.12");
+        assert_eq!(variants.values().nth(1).unwrap().file.version, suffixed);
+
+        assert_eq!(suggested_lwjgl(&files[0]), Some("2.9.4-nightly-20150209"));
+        assert_eq!(suggested_lwjgl(&files[1]), Some("2.9.4-nightly-20150209"));
+        assert_eq!(suggested_lwjgl(&files[2]), Some(suffixed));
+        assert_eq!(files[2].libraries.as_ref().unwrap().len(), 1);
+    }
+}
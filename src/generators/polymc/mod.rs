@@ -0,0 +1,456 @@
+// without any upstream, only the empty package index is generated
+#![cfg_attr(
+    not(any(
+        feature = "mojang",
+        feature = "forge",
+        feature = "fabric",
+        feature = "liteloader"
+    )),
+    allow(dead_code)
+)]
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::models::{
+    patch::LibraryPatchIndex,
+    polymc::{
+        PolyMCPackageIndex, PolyMCPackageIndexEntry, PolyMCSharedPackageData, PolyMCVersionFile,
+        PolyMCVersionIndex, PolyMCVersionIndexEntry, VersionedJsonObject,
+        CURRENT_POLYMC_FORMAT_VERSION,
+    },
+    report::UpdateReport,
+};
+
+pub use crate::models::polymc::{
+    FABRIC_INTERMEDIARY_UID, FABRIC_LOADER_UID, FORGE_UID, LITELOADER_UID, LWJGL2_UID, LWJGL3_UID,
+    MINECRAFT_UID,
+};
+
+#[cfg(feature = "fabric")]
+mod fabric;
+#[cfg(feature = "forge")]
+mod forge;
+#[cfg(feature = "liteloader")]
+mod liteloader;
+#[cfg(feature = "mojang")]
+mod minecraft;
+
+/// How a generated file differs from the one already in the output directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileChange {
+    Unchanged,
+    Added,
+    Updated,
+}
+
+/// Generates PolyMC metadata from the upstream cache written by the updaters.
+pub struct PolyMCGenerator {
+    cache_directory: PathBuf,
+    output_directory: PathBuf,
+    dry_run: bool,
+    legacy_overrides: Option<PathBuf>,
+    library_patches: Option<PathBuf>,
+    forge_server: bool,
+    patch_index: LibraryPatchIndex,
+    report: UpdateReport,
+    generated_files: HashSet<PathBuf>,
+    /// Directories of the packages whose upstream index was loaded and whose versions were all generated.
+    complete_packages: HashSet<&'static str>,
+    packages: Vec<PolyMCPackageIndexEntry>,
+}
+
+impl PolyMCGenerator {
+    pub fn new<C, O>(cache_directory: C, output_directory: O) -> Self
+    where
+        C: AsRef<Path>,
+        O: AsRef<Path>,
+    {
+        Self {
+            cache_directory: cache_directory.as_ref().to_path_buf(),
+            output_directory: output_directory.as_ref().to_path_buf(),
+            dry_run: false,
+            legacy_overrides: None,
+            library_patches: None,
+            forge_server: false,
+            patch_index: LibraryPatchIndex::default(),
+            report: UpdateReport::new("polymc"),
+            generated_files: HashSet::new(),
+            complete_packages: HashSet::new(),
+            packages: Vec::new(),
+        }
+    }
+
+    /// Only compares the generated files with the output directory, without writing or deleting anything.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Reads overrides of legacy Minecraft versions from a file, which take precedence over the bundled ones.
+    pub fn with_legacy_overrides<P>(mut self, legacy_overrides: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.legacy_overrides = Some(legacy_overrides.as_ref().to_path_buf());
+        self
+    }
+
+    /// Reads patches from a file, which are applied to the libraries of every generated version file.
+    pub fn with_library_patches<P>(mut self, library_patches: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.library_patches = Some(library_patches.as_ref().to_path_buf());
+        self
+    }
+
+    /// Also generates `forge-server/`, which describes how Forge servers are installed and started.
+    pub fn with_forge_server(mut self, forge_server: bool) -> Self {
+        self.forge_server = forge_server;
+        self
+    }
+
+    /// Generates the packages of every upstream found in the cache and returns a report of what changed.
+    ///
+    /// Versions are reported as `{uid}/{version}`, files relative to the output directory.
+    /// Files which weren't generated during this run are removed from the directories of packages which were
+    /// generated completely, packages with a missing index or skipped versions keep their previous files.
+    pub fn generate(&mut self) -> std::io::Result<UpdateReport> {
+        self.report = UpdateReport::new("polymc");
+        self.generated_files.clear();
+        self.complete_packages.clear();
+        self.packages.clear();
+        self.patch_index = LibraryPatchIndex::load(self.library_patches.as_deref())?;
+        if !self.dry_run {
+            std::fs::create_dir_all(&self.output_directory)?;
+        }
+
+        #[cfg(feature = "mojang")]
+        self.generate_minecraft()?;
+        #[cfg(feature = "forge")]
+        self.generate_forge()?;
+        #[cfg(feature = "forge")]
+        if self.forge_server {
+            self.generate_forge_server()?;
+        }
+        #[cfg(feature = "fabric")]
+        self.generate_fabric()?;
+        #[cfg(feature = "liteloader")]
+        self.generate_liteloader()?;
+
+        info!("Writing package index...");
+        self.packages.sort_by(|a, b| a.uid.cmp(&b.uid));
+        let package_index = PolyMCPackageIndex {
+            versioned_json_object: VersionedJsonObject {
+                format_version: *CURRENT_POLYMC_FORMAT_VERSION,
+            },
+            packages: std::mem::take(&mut self.packages),
+            #[cfg(feature = "extra-fields")]
+            extra: Default::default(),
+        };
+        self.write_json(Path::new("index.json"), &package_index)?;
+        self.remove_stale_files()?;
+
+        self.report.added.sort();
+        self.report.updated.sort();
+        self.report.removed.sort();
+        self.report.patched.sort();
+        Ok(std::mem::take(&mut self.report))
+    }
+
+    /// Reads a JSON file from the cache directory, returning `None` if it doesn't exist.
+    fn read_cache<T, P>(&self, path: P) -> std::io::Result<Option<T>>
+    where
+        T: DeserializeOwned,
+        P: AsRef<Path>,
+    {
+        let path = self.cache_directory.join(path);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let file = std::fs::File::open(&path)?;
+        serde_json::from_reader(file).map(Some).map_err(|e| {
+            std::io::Error::other(format!("Failed to parse {}: {}", path.display(), e))
+        })
+    }
+
+    /// Serializes a value and writes it to the output directory, unless the file is unchanged.
+    ///
+    /// Returns the SHA-256 hash of the serialized value.
+    fn write_json<T>(&mut self, path: &Path, value: &T) -> std::io::Result<(String, FileChange)>
+    where
+        T: Serialize,
+    {
+        // going through a Value sorts all keys, so the output doesn't depend on HashMap order
+        let contents = serde_json::to_vec_pretty(&serde_json::to_value(value)?)?;
+        let sha256 = data_encoding::HEXLOWER
+            .encode(ring::digest::digest(&ring::digest::SHA256, &contents).as_ref());
+
+        if !self.generated_files.insert(path.to_path_buf()) {
+            warn!(
+                "{} was generated twice, keeping the last one",
+                path.display()
+            );
+        }
+        let full_path = self.output_directory.join(path);
+        let change = match std::fs::read(&full_path) {
+            Ok(previous) if previous == contents => FileChange::Unchanged,
+            Ok(_) => FileChange::Updated,
+            Err(_) => FileChange::Added,
+        };
+        if change != FileChange::Unchanged {
+            if !self.dry_run {
+                if let Some(parent) = full_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&full_path, &contents)?;
+            }
+            self.report.record_file(&full_path, &self.output_directory);
+        }
+
+        Ok((sha256, change))
+    }
+
+    /// Applies the library patches to a version file, writes it to `{uid}/{version}.json` and returns its index entry.
+    fn write_version_file(
+        &mut self,
+        file: &PolyMCVersionFile,
+    ) -> std::io::Result<PolyMCVersionIndexEntry> {
+        let patched_file;
+        let file = if self.patch_index.is_empty() {
+            file
+        } else {
+            let mut file = file.clone();
+            let patches = self.patch_index.apply(&mut file);
+            self.report.patched.extend(
+                patches
+                    .into_iter()
+                    .map(|patch| format!("{}/{}: {}", file.uid, file.version, patch)),
+            );
+            patched_file = file;
+            &patched_file
+        };
+        let path = Path::new(&file.uid).join(format!("{}.json", file.version));
+        let (sha256, change) = self.write_json(&path, file)?;
+        let id = format!("{}/{}", file.uid, file.version);
+        match change {
+            FileChange::Added => self.report.added.push(id),
+            FileChange::Updated => self.report.updated.push(id),
+            FileChange::Unchanged => {}
+        }
+
+        Ok(PolyMCVersionIndexEntry::from_version_file(file, sha256))
+    }
+
+    /// Writes the version index and the package data of a package and adds it to the package index.
+    fn write_package(
+        &mut self,
+        package: &PolyMCSharedPackageData,
+        mut versions: Vec<PolyMCVersionIndexEntry>,
+    ) -> std::io::Result<()> {
+        info!(
+            "Writing package {} with {} versions...",
+            package.uid,
+            versions.len()
+        );
+        let recommended = package.recommended.clone().unwrap_or_default();
+        for version in versions.iter_mut() {
+            if recommended.contains(&version.version) {
+                version.recommended = Some(true);
+            }
+        }
+        // newest versions first, just like the upstream indexes
+        versions.sort_by(|a, b| {
+            b.release_time
+                .cmp(&a.release_time)
+                .then_with(|| b.version.cmp(&a.version))
+        });
+
+        let version_index = PolyMCVersionIndex {
+            versioned_json_object: VersionedJsonObject {
+                format_version: *CURRENT_POLYMC_FORMAT_VERSION,
+            },
+            name: package.name.clone(),
+            uid: package.uid.clone(),
+            versions,
+            #[cfg(feature = "extra-fields")]
+            extra: Default::default(),
+        };
+        let (sha256, _) =
+            self.write_json(&Path::new(&package.uid).join("index.json"), &version_index)?;
+        self.write_json(&Path::new(&package.uid).join("package.json"), package)?;
+        self.packages.push(PolyMCPackageIndexEntry {
+            name: package.name.clone(),
+            uid: package.uid.clone(),
+            sha256,
+        });
+
+        Ok(())
+    }
+
+    /// Marks the directories of packages as completely generated, so their stale files can be removed.
+    ///
+    /// Generators only call this if every version of the upstream index was generated, a version skipped because
+    /// of an incomplete cache could otherwise be deleted from the output.
+    fn mark_complete(&mut self, directories: &[&'static str]) {
+        self.complete_packages.extend(directories);
+    }
+
+    /// Removes the JSON files of the completely generated package directories which weren't generated during this
+    /// run.
+    ///
+    /// Other files and directories of the output directory are left alone.
+    fn remove_stale_files(&mut self) -> std::io::Result<()> {
+        let mut stale_files = Vec::new();
+        for directory in &self.complete_packages {
+            let directory = self.output_directory.join(directory);
+            if !directory.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(&directory)? {
+                stale_files.push(entry?.path());
+            }
+        }
+        stale_files.retain(|path| {
+            path.is_file()
+                && path.extension().map(|extension| extension == "json") == Some(true)
+                && path
+                    .strip_prefix(&self.output_directory)
+                    .map(|relative_path| !self.generated_files.contains(relative_path))
+                    .unwrap_or(false)
+        });
+        stale_files.sort();
+
+        for path in stale_files {
+            info!("Removing stale file {}...", path.display());
+            let relative_path = path.strip_prefix(&self.output_directory).unwrap();
+            let file_name = relative_path.file_name().unwrap().to_string_lossy();
+            if let (Some(uid), Some(version)) = (
+                relative_path
+                    .parent()
+                    .filter(|uid| *uid != Path::new("forge-server")),
+                file_name.strip_suffix(".json"),
+            ) {
+                if version != "index" && version != "package" {
+                    self.report
+                        .removed
+                        .push(format!("{}/{}", uid.to_string_lossy(), version));
+                }
+            }
+            if !self.dry_run {
+                std::fs::remove_file(&path)?;
+            }
+            self.report
+                .record_removed_file(&path, &self.output_directory);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "mojang"))]
+mod tests {
+    use super::*;
+
+    /// Generates into an output directory with a few existing files, from a cache with the given Mojang versions.
+    ///
+    /// Without versions, the cache has no Mojang index at all. Only 1.7.10 and 1.18.1 have a cached version file.
+    /// Returns whether `net.minecraft/1.0.json` was kept and the report.
+    fn generate_over_existing_files(name: &str, versions: Option<&[&str]>) -> (bool, UpdateReport) {
+        let root = std::env::temp_dir().join(format!(
+            "morphosis-meta-stale-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        let cache = root.join("cache");
+        let output = root.join("output");
+        for (path, contents) in [
+            ("net.minecraft/1.0.json", "{}"),
+            ("forge-server/1.18.2-40.1.0.json", "{}"),
+            ("notes.json", "{}"),
+            ("custom/version.json", "{}"),
+        ] {
+            let path = output.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        std::fs::create_dir_all(cache.join("mojang/versions")).unwrap();
+        if let Some(versions) = versions {
+            let mut index: serde_json::Value = serde_json::from_str(include_str!(
+                "../../../tests/data/mojang/version_manifest_v2.json"
+            ))
+            .unwrap();
+            index["versions"]
+                .as_array_mut()
+                .unwrap()
+                .retain(|entry| versions.contains(&entry["id"].as_str().unwrap()));
+            std::fs::write(
+                cache.join("mojang/version_manifest_v2.json"),
+                index.to_string(),
+            )
+            .unwrap();
+            for (version, contents) in [
+                (
+                    "1.7.10",
+                    include_str!("../../../tests/data/mojang/versions/1.7.10.json"),
+                ),
+                (
+                    "1.18.1",
+                    include_str!("../../../tests/data/mojang/versions/1.18.1.json"),
+                ),
+            ] {
+                std::fs::write(
+                    cache.join(format!("mojang/versions/{}.json", version)),
+                    contents,
+                )
+                .unwrap();
+            }
+        }
+
+        let report = PolyMCGenerator::new(&cache, &output).generate().unwrap();
+        let kept = [
+            output.join("forge-server/1.18.2-40.1.0.json").is_file(),
+            output.join("notes.json").is_file(),
+            output.join("custom/version.json").is_file(),
+        ];
+        let minecraft_kept = output.join("net.minecraft/1.0.json").is_file();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(kept, [true; 3]);
+        (minecraft_kept, report)
+    }
+
+    #[test]
+    fn keeps_files_of_packages_without_a_cached_index() {
+        let (kept, report) = generate_over_existing_files("absent", None);
+
+        assert!(kept);
+        assert!(report.removed.is_empty());
+    }
+
+    #[test]
+    fn keeps_files_of_packages_with_skipped_versions() {
+        // 22w03a has no cached version file
+        let (kept, report) =
+            generate_over_existing_files("partial", Some(&["22w03a", "1.18.1", "1.7.10"]));
+
+        assert!(kept);
+        assert!(report.removed.is_empty());
+        assert!(report.added.contains(&"net.minecraft/1.18.1".to_string()));
+    }
+
+    #[test]
+    fn only_removes_stale_files_of_complete_packages() {
+        let (kept, report) = generate_over_existing_files("complete", Some(&["1.18.1", "1.7.10"]));
+
+        assert!(!kept);
+        assert_eq!(report.removed, vec!["net.minecraft/1.0".to_string()]);
+    }
+}
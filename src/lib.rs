@@ -2,14 +2,32 @@
 extern crate custom_error;
 #[macro_use]
 extern crate lazy_static;
-#[macro_use]
+#[cfg_attr(any(feature = "client", feature = "polymc"), macro_use)]
 extern crate log;
 
+#[cfg(feature = "client")]
 pub mod clients;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod forge_installer;
+#[cfg(feature = "polymc")]
+pub mod generators;
 pub mod models;
-mod validators;
+pub mod profile;
+#[cfg(feature = "python")]
+mod python;
+pub mod resolver;
+pub mod validators;
+pub mod version;
 
-pub use clients::mojang::MojangUpdater;
-pub use clients::forge::ForgeUpdater;
+#[cfg(all(feature = "client", feature = "fabric"))]
 pub use clients::fabric::FabricUpdater;
-pub use clients::liteloader::LiteloaderUpdater;
\ No newline at end of file
+#[cfg(all(feature = "client", feature = "forge"))]
+pub use clients::forge::ForgeUpdater;
+#[cfg(all(feature = "client", feature = "liteloader"))]
+pub use clients::liteloader::LiteloaderUpdater;
+#[cfg(all(feature = "client", feature = "mojang"))]
+pub use clients::mojang::MojangUpdater;
+#[cfg(feature = "polymc")]
+pub use generators::polymc::PolyMCGenerator;
+pub use models::report::{RemovalPolicy, UpdateReport};
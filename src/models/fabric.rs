@@ -1,42 +1,65 @@
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "extra-fields")]
+use super::ExtraFields;
+
 use super::polymc::PolyMCLibrary;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct FabricInstallerArguments {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub client: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub common: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub server: Option<Vec<String>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct FabricInstallerLaunchWrapper {
     pub tweakers: FabricInstallerArguments,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct FabricInstallerLibraries {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub client: Option<Vec<PolyMCLibrary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub common: Option<Vec<PolyMCLibrary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub server: Option<Vec<PolyMCLibrary>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct FabricInstallerDataV1 {
     pub version: i32,
     pub libraries: FabricInstallerLibraries,
     #[serde(rename = "mainClass")]
     pub main_class: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub arguments: Option<FabricInstallerArguments>,
-    #[serde(rename = "launchwrapper")]
+    #[serde(rename = "launchwrapper", skip_serializing_if = "Option::is_none")]
     pub launch_wrapper: Option<FabricInstallerLaunchWrapper>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct FabricJarInfo {
     #[serde(rename = "releaseTime")]
     pub release_time: Option<chrono::DateTime<chrono::Utc>>,
     pub size: Option<u64>,
     pub sha256: Option<String>,
     pub sha1: Option<String>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
@@ -1,18 +1,33 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
 
 use chrono::DateTime;
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "extra-fields")]
+use super::ExtraFields;
+
+use crate::version::{MavenVersion, MinecraftVersion};
+
 use super::{
     misc::GradleSpecifier,
     mojang::{
         JavaVersion, MojangArguments, MojangArtifactBase, MojangAssets, MojangLibrary,
         MojangLogging,
     },
+    polymc::PolyMCLibrary,
 };
 
+/// Release times and hashes of legacy Forge builds, which the Forge API doesn't provide, see [`ForgeLegacyInfoList`].
+///
+/// Only a fallback for builds missing from the `forge/legacy_info.json` the updater builds from the universal jars.
+pub static FORGE_LEGACY_INFO: &str = include_str!("static_files/forge_legacyinfo.json");
+
 #[derive(Clone)]
-pub struct FMLLib(String, String, bool);
+pub struct FMLLib(pub String, pub String, pub bool);
 
 lazy_static! {
     pub static ref FML_LIB_MAPPING: HashMap<String, Vec<FMLLib>> = {
@@ -182,9 +197,11 @@ lazy_static! {
     };
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ForgeFile {
     pub classifier: String,
+    /// The MD5 hash of the file.
     pub hash: String,
     pub extension: String,
 }
@@ -207,6 +224,7 @@ impl ForgeFile {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ForgeEntry {
     #[serde(rename = "longversion")]
     pub long_version: String,
@@ -217,7 +235,82 @@ pub struct ForgeEntry {
     pub branch: Option<String>,
     pub latest: Option<bool>,
     pub recommended: Option<bool>,
-    pub files: Option<HashMap<String, ForgeFile>>,
+    /// Every file of the build, a classifier may have files with several extensions.
+    #[serde(default, deserialize_with = "forge_files::deserialize")]
+    pub files: Option<Vec<ForgeFile>>,
+    /// Set if the version was removed upstream, but kept in the local index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed: Option<bool>,
+    /// The changes of this build, taken from its changelog.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+}
+
+impl ForgeEntry {
+    /// Returns the Forge version, parsed for comparison.
+    pub fn maven_version(&self) -> MavenVersion {
+        MavenVersion::new(&self.version)
+    }
+
+    /// Returns the Minecraft version, parsed for comparison.
+    pub fn minecraft_version(&self) -> MinecraftVersion {
+        MinecraftVersion::new(&self.mc_version)
+    }
+
+    /// Compares two entries by Minecraft version, then by Forge version and build number.
+    pub fn cmp_version(&self, other: &Self) -> Ordering {
+        self.minecraft_version()
+            .cmp(&other.minecraft_version())
+            .then_with(|| self.maven_version().cmp(&other.maven_version()))
+            .then_with(|| self.build.cmp(&other.build))
+            .then_with(|| self.long_version.cmp(&other.long_version))
+    }
+}
+
+/// Extracts the changes of a build from a Forge changelog, which lists every build up to the one it was published
+/// with.
+///
+/// Legacy changelogs have a `Build {long version}:` header above the changes of a build, newer ones list the changes
+/// as ` - {version} {message}` with continuation lines indented.
+pub fn changelog_excerpt(changelog: &str, version: &str) -> Option<String> {
+    let legacy = changelog.lines().any(|line| {
+        let trimmed = line.trim();
+        trimmed.starts_with("Build ") && trimmed.ends_with(':')
+    });
+    let mut lines = Vec::new();
+    let mut in_excerpt = false;
+    for line in changelog.lines() {
+        let trimmed = line.trim();
+        if legacy {
+            if let Some(header) = trimmed
+                .strip_prefix("Build ")
+                .and_then(|header| header.strip_suffix(':'))
+            {
+                if in_excerpt {
+                    break;
+                }
+                in_excerpt = header.split('-').any(|part| part == version);
+            } else if in_excerpt && !trimmed.is_empty() {
+                lines.push(trimmed);
+            }
+        } else if let Some(entry) = trimmed.strip_prefix("- ") {
+            if in_excerpt {
+                break;
+            }
+            let (entry_version, message) = entry.split_once(' ').unwrap_or((entry, ""));
+            if entry_version == version {
+                in_excerpt = true;
+                lines.push(message.trim());
+            }
+        } else if in_excerpt && !trimmed.is_empty() {
+            if !line.starts_with(char::is_whitespace) {
+                break;
+            }
+            lines.push(trimmed);
+        }
+    }
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
 }
 
 pub struct ForgeVersion {
@@ -247,38 +340,29 @@ impl From<ForgeEntry> for ForgeVersion {
             long_version = format!("{}-{}", long_version, branch);
         }
 
-        let mut installer_file_name: Option<String> = None;
-        let mut installer_url: Option<String> = None;
-        let mut universal_file_name: Option<String> = None;
-        let mut universal_url: Option<String> = None;
-        let mut changelog_url: Option<String> = None;
-
-        if let Some(files) = entry.files {
-            for (classifier, file_entry) in files {
-                let extension = file_entry.extension.clone();
-                let file_name = file_entry.file_name(long_version.as_str());
-                let url = file_entry.url(long_version.as_str());
-
-                if classifier == "installer" && extension == "jar" {
-                    installer_file_name = Some(file_name);
-                    installer_url = Some(url);
-                    continue;
-                }
+        let files = entry.files.unwrap_or_default();
+        let find_file = |classifier: &str, extension: &str| {
+            files
+                .iter()
+                .find(|file| file.classifier == classifier && file.extension == extension)
+        };
+        let installer = find_file("installer", "jar");
+        // the oldest builds only have a client zip
+        let universal = [
+            ("universal", "jar"),
+            ("universal", "zip"),
+            ("client", "jar"),
+            ("client", "zip"),
+        ]
+        .into_iter()
+        .find_map(|(classifier, extension)| find_file(classifier, extension));
+        let changelog = find_file("changelog", "txt");
 
-                if (classifier == "universal" || classifier == "client")
-                    && (extension == "jar" || extension == "zip")
-                {
-                    universal_file_name = Some(file_name);
-                    universal_url = Some(url);
-                    continue;
-                }
-
-                if classifier == "changelog" && extension == "txt" {
-                    changelog_url = Some(url);
-                    continue;
-                }
-            }
-        }
+        let installer_file_name = installer.map(|file| file.file_name(&long_version));
+        let installer_url = installer.map(|file| file.url(&long_version));
+        let universal_file_name = universal.map(|file| file.file_name(&long_version));
+        let universal_url = universal.map(|file| file.url(&long_version));
+        let changelog_url = changelog.map(|file| file.url(&long_version));
 
         ForgeVersion {
             build,
@@ -348,20 +432,93 @@ impl ForgeVersion {
     }
 }
 
+custom_error! {
+    /// Errors that can occur when parsing the key of a Forge promotion.
+    pub PromotionKeyError
+        InvalidKey { key: String } = "Invalid promotion key '{key}', expected {{mc}}-latest or {{mc}}-recommended, optionally followed by -{{branch}}",
+}
+
+/// Whether a promotion marks the latest or the recommended build.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ForgePromotionKind {
+    Latest,
+    Recommended,
+}
+
+/// A key of `promotions_slim.json`: `{mc}-{kind}` or `{mc}-{kind}-{branch}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForgePromotionKey {
+    pub mc_version: String,
+    pub kind: ForgePromotionKind,
+    pub branch: Option<String>,
+}
+
+impl FromStr for ForgePromotionKey {
+    type Err = PromotionKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid_key = || PromotionKeyError::InvalidKey { key: s.to_string() };
+        let mut parts = s.splitn(3, '-');
+        let mc_version = parts
+            .next()
+            .filter(|mc_version| !mc_version.is_empty())
+            .ok_or_else(invalid_key)?;
+        let kind = match parts.next() {
+            Some("latest") => ForgePromotionKind::Latest,
+            Some("recommended") => ForgePromotionKind::Recommended,
+            _ => return Err(invalid_key()),
+        };
+        let branch = match parts.next() {
+            Some("") => return Err(invalid_key()),
+            branch => branch.map(|branch| branch.to_string()),
+        };
+
+        Ok(Self {
+            mc_version: mc_version.to_string(),
+            kind,
+            branch,
+        })
+    }
+}
+
+/// The promoted builds of a branch, as long versions.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ForgeBranchPromotions {
+    /// The promoted latest build, or the newest build of the branch if there is no promotion.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recommended: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ForgeMCVersionInfo {
+    /// The promoted latest build, or the newest build if there is no promotion.
     pub latest: Option<String>,
     pub recommended: Option<String>,
     pub versions: Option<Vec<String>>,
+    /// The promoted builds of every branch of the Minecraft version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branches: Option<BTreeMap<String, ForgeBranchPromotions>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DerivedForgeIndex {
     pub mc_versions: Option<HashMap<String, ForgeMCVersionInfo>>,
     pub versions: Option<HashMap<String, ForgeEntry>>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ForgeInstallerProfileInstallSection {
     pub profile_name: String,
@@ -378,6 +535,7 @@ pub struct ForgeInstallerProfileInstallSection {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ForgeLibrary {
     #[serde(flatten)]
     pub library: MojangLibrary,
@@ -387,11 +545,14 @@ pub struct ForgeLibrary {
     pub server_req: Option<bool>,
     #[serde(rename = "clientreq", skip_serializing_if = "Option::is_none")]
     pub client_req: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub checksums: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ForgeVersionFile {
     // flatten doesn't work here, because it can't handle overriding fields
@@ -418,11 +579,16 @@ pub struct ForgeVersionFile {
         with = "crate::validators::mojang_version_validation",
         default
     )]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(schema_with = "crate::validators::mojang_version_validation::schema")
+    )]
     pub minimum_launcher_version: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub release_time: Option<DateTime<chrono::Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub time: Option<DateTime<chrono::Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub inherits_from: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logging: Option<HashMap<String, MojangLogging>>,
@@ -432,34 +598,56 @@ pub struct ForgeVersionFile {
     pub java_version: Option<JavaVersion>,
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub version_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub jar: Option<String>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ForgeOptional {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub client: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub server: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub default: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub inject: Option<bool>,
-    #[serde(rename = "desc")]
+    #[serde(rename = "desc", skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub artifact: Option<GradleSpecifier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub maven: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ForgeInstallerProfile {
     pub install: ForgeInstallerProfileInstallSection,
     #[serde(rename = "versionInfo")]
     pub version_info: ForgeVersionFile,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub optionals: Option<Vec<ForgeOptional>>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ForgeLegacyInfo {
-    #[serde(rename = "releaseTime")]
+    #[serde(rename = "releaseTime", with = "legacy_timestamp", default)]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
     pub release_time: Option<DateTime<chrono::Utc>>,
     pub size: Option<i32>,
     pub sha256: Option<String>,
@@ -467,21 +655,106 @@ pub struct ForgeLegacyInfo {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ForgeLegacyInfoList {
     pub number: Option<HashMap<String, ForgeLegacyInfo>>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// Deserializes the files of a Forge build, which older indexes kept as a map of classifier to file.
+mod forge_files {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::ForgeFile;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ForgeFiles {
+        List(Vec<ForgeFile>),
+        Map(HashMap<String, ForgeFile>),
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Vec<ForgeFile>>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(
+            Option::<ForgeFiles>::deserialize(deserializer)?.map(|files| match files {
+                ForgeFiles::List(files) => files,
+                ForgeFiles::Map(files) => {
+                    let mut files = files.into_values().collect::<Vec<_>>();
+                    files.sort_by(|a, b| {
+                        (&a.classifier, &a.extension).cmp(&(&b.classifier, &b.extension))
+                    });
+                    files
+                }
+            }),
+        )
+    }
+}
+
+/// Serializes and deserializes the release time of legacy builds, which may lack a timezone.
+///
+/// Timestamps without a timezone are treated as UTC.
+pub mod legacy_timestamp {
+    use chrono::{DateTime, NaiveDateTime};
+    use serde::Deserialize;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<chrono::Utc>>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let v = Option::<String>::deserialize(deserializer)?;
+        if let Some(v) = v {
+            if let Ok(timestamp) = DateTime::parse_from_rfc3339(&v) {
+                return Ok(Some(timestamp.with_timezone(&chrono::Utc)));
+            }
+            let timestamp = NaiveDateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.f")
+                .map_err(serde::de::Error::custom)?;
+            return Ok(Some(timestamp.and_utc()));
+        }
+        Ok(None)
+    }
+
+    pub fn serialize<S>(
+        timestamp: &Option<DateTime<chrono::Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if let Some(timestamp) = timestamp {
+            serializer.serialize_str(&timestamp.to_rfc3339())
+        } else {
+            serializer.serialize_none()
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DataSpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub client: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub server: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ProcessorSpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub jar: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub classpath: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub args: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub outputs: Option<HashMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sides: Option<Vec<String>>,
@@ -489,50 +762,310 @@ pub struct ProcessorSpec {
 
 /// A Forge installer profile, which is only ever used in 1.12.2-14.23.5.2851
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ForgeInstallerProfileV1_5 {
+    #[serde(
+        rename = "_comment_",
+        alias = "_comment",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub _comment: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub spec: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub icon: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub json: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<GradleSpecifier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub logo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub minecraft: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub welcome: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub processors: Option<Vec<ProcessorSpec>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub libraries: Option<Vec<MojangLibrary>>,
     #[serde(rename = "mirrorList", skip_serializing_if = "Option::is_none")]
     pub mirror_list: Option<String>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ForgeInstallerProfileV2 {
+    #[serde(
+        rename = "_comment_",
+        alias = "_comment",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub _comment: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub spec: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub icon: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub json: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<GradleSpecifier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub logo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub minecraft: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub welcome: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<HashMap<String, DataSpec>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub processors: Option<Vec<ProcessorSpec>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub libraries: Option<Vec<MojangLibrary>>,
     #[serde(rename = "mirrorList", skip_serializing_if = "Option::is_none")]
     pub mirror_list: Option<String>,
     #[serde(rename = "serverJarPath", skip_serializing_if = "Option::is_none")]
     pub server_jar_path: Option<String>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct InstallerInfo {
     #[serde(rename = "sha1hash")]
     pub sha1_hash: Option<String>,
     #[serde(rename = "sha256hash")]
     pub sha256_hash: Option<String>,
     pub size: Option<u64>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// How the server of a Forge version is installed and started, generated from the cached installer.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ForgeServerVersion {
+    pub version: String,
+    pub minecraft_version: String,
+    /// The vanilla server jar.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minecraft_server: Option<MojangArtifactBase>,
+    /// Where the vanilla server jar is expected, relative to the server directory.
+    pub minecraft_server_path: String,
+    /// The installer, which contains the data files of the processors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installer: Option<MojangArtifactBase>,
+    /// Libraries which are downloaded to `libraries/`.
+    pub libraries: Vec<PolyMCLibrary>,
+    /// The Forge jar which is started with `-jar`, placed in the server directory, for versions without processors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forge_jar: Option<MojangArtifactBase>,
+    /// The processors which run on the server, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub processors: Vec<ProcessorSpec>,
+    /// The server values of the installer data.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub data: BTreeMap<String, String>,
+    /// The arguments `java` is started with in the server directory, Windows uses `win_args.txt` instead of
+    /// `unix_args.txt`.
+    pub run_arguments: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ForgeServerIndexEntry {
+    pub version: String,
+    pub minecraft_version: String,
+    pub sha256: String,
+}
+
+/// The generated server versions, newest first.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ForgeServerIndex {
+    pub versions: Vec<ForgeServerIndexEntry>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static LEGACY_CHANGELOG: &str = "Changelog:
+Build 1.7.10-10.13.4.1614-1.7.10:
+\tLexManos:
+\t\tFix the version check of the installer.
+
+Build 1.7.10-10.13.4.1613-1.7.10:
+\tcpw:
+\t\tUpdate the mappings.
+";
+
+    static CHANGELOG: &str = "1.18.x Changelog
+40.1
+====
+ - 40.1.0 1.18.2 Recommended Build.
+ - 40.0.54 Fix the order of mod list entries.
+           Build the sorted list only once.
+ - 40.0.53 Add a tag for fence gates.
+";
+
+    fn forge_entry(files: serde_json::Value) -> ForgeEntry {
+        serde_json::from_value(serde_json::json!({
+            "longversion": "1.12.2-14.23.5.2860",
+            "mcversion": "1.12.2",
+            "version": "14.23.5.2860",
+            "build": 2860,
+            "files": files
+        }))
+        .unwrap()
+    }
+
+    fn file_keys(entry: &ForgeEntry) -> Vec<(String, String)> {
+        entry
+            .files
+            .iter()
+            .flatten()
+            .map(|file| (file.classifier.clone(), file.extension.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_files_as_list_and_legacy_map() {
+        let list = forge_entry(serde_json::json!([
+            { "classifier": "installer", "extension": "jar", "hash": "a" },
+            { "classifier": "installer", "extension": "json", "hash": "b" }
+        ]));
+        // the legacy format keys files by name and has no order
+        let map = forge_entry(serde_json::json!({
+            "installer-json": { "classifier": "installer", "extension": "json", "hash": "b" },
+            "installer-jar": { "classifier": "installer", "extension": "jar", "hash": "a" }
+        }));
+
+        let expected = vec![
+            ("installer".to_string(), "jar".to_string()),
+            ("installer".to_string(), "json".to_string()),
+        ];
+        assert_eq!(file_keys(&list), expected);
+        assert_eq!(file_keys(&map), expected);
+        assert!(forge_entry(serde_json::Value::Null).files.is_none());
+    }
+
+    #[test]
+    fn selects_files_by_extension() {
+        let version: ForgeVersion = forge_entry(serde_json::json!([
+            { "classifier": "changelog", "extension": "txt", "hash": "a" },
+            { "classifier": "installer", "extension": "json", "hash": "b" },
+            { "classifier": "installer", "extension": "jar", "hash": "c" },
+            { "classifier": "universal", "extension": "zip", "hash": "d" },
+            { "classifier": "universal", "extension": "jar", "hash": "e" },
+            { "classifier": "client", "extension": "zip", "hash": "f" }
+        ]))
+        .into();
+
+        assert_eq!(
+            version.installer_file_name.as_deref(),
+            Some("forge-1.12.2-14.23.5.2860-installer.jar")
+        );
+        assert_eq!(
+            version.universal_file_name.as_deref(),
+            Some("forge-1.12.2-14.23.5.2860-universal.jar")
+        );
+        assert_eq!(
+            version.changelog_url.as_deref(),
+            Some("https://files.minecraftforge.net/maven/net/minecraftforge/forge/1.12.2-14.23.5.2860/forge-1.12.2-14.23.5.2860-changelog.txt")
+        );
+
+        // the oldest builds only have a client zip
+        let version: ForgeVersion = forge_entry(serde_json::json!([
+            { "classifier": "client", "extension": "zip", "hash": "f" }
+        ]))
+        .into();
+        assert!(version.installer_file_name.is_none());
+        assert_eq!(
+            version.universal_file_name.as_deref(),
+            Some("forge-1.12.2-14.23.5.2860-client.zip")
+        );
+    }
+
+    #[test]
+    fn extracts_legacy_changelog() {
+        assert_eq!(
+            changelog_excerpt(LEGACY_CHANGELOG, "10.13.4.1614").as_deref(),
+            Some("LexManos:\nFix the version check of the installer.")
+        );
+        assert_eq!(
+            changelog_excerpt(LEGACY_CHANGELOG, "10.13.4.1613").as_deref(),
+            Some("cpw:\nUpdate the mappings.")
+        );
+        assert_eq!(changelog_excerpt(LEGACY_CHANGELOG, "10.13.4.1612"), None);
+    }
+
+    #[test]
+    fn extracts_changelog() {
+        assert_eq!(
+            changelog_excerpt(CHANGELOG, "40.1.0").as_deref(),
+            Some("1.18.2 Recommended Build.")
+        );
+        // a continuation line starting with "Build " doesn't make the changelog a legacy one
+        assert_eq!(
+            changelog_excerpt(CHANGELOG, "40.0.54").as_deref(),
+            Some("Fix the order of mod list entries.\nBuild the sorted list only once.")
+        );
+        assert_eq!(changelog_excerpt(CHANGELOG, "40.0.52"), None);
+    }
+
+    #[test]
+    fn parses_promotion_keys() {
+        assert_eq!(
+            "1.7.10-latest-new".parse::<ForgePromotionKey>().unwrap(),
+            ForgePromotionKey {
+                mc_version: "1.7.10".to_string(),
+                kind: ForgePromotionKind::Latest,
+                branch: Some("new".to_string()),
+            }
+        );
+        assert_eq!(
+            "1.7.10_pre4-recommended"
+                .parse::<ForgePromotionKey>()
+                .unwrap(),
+            ForgePromotionKey {
+                mc_version: "1.7.10_pre4".to_string(),
+                kind: ForgePromotionKind::Recommended,
+                branch: None,
+            }
+        );
+        for invalid in [
+            "latest",
+            "1.7.10",
+            "1.7.10-newest",
+            "1.7.10-latest-",
+            "-latest",
+        ] {
+            assert!(
+                invalid.parse::<ForgePromotionKey>().is_err(),
+                "{:?} was accepted",
+                invalid
+            );
+        }
+    }
 }
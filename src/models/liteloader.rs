@@ -2,9 +2,13 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "extra-fields")]
+use super::ExtraFields;
+
 use super::polymc::PolyMCLibrary;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct LiteloaderRepo {
     pub stream: String,
     #[serde(rename = "type")]
@@ -14,6 +18,7 @@ pub struct LiteloaderRepo {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct LiteloaderArtifact {
     pub tweak_class: String,
@@ -32,6 +37,7 @@ pub struct LiteloaderArtifact {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct LiteloaderDev {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -43,21 +49,25 @@ pub struct LiteloaderDev {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct LiteloaderArtifacts {
     #[serde(rename = "com.mumfrey:liteloader")]
-    pub liteloader: HashMap<String, LiteloaderArtifact>
+    pub liteloader: HashMap<String, LiteloaderArtifact>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct LiteloaderSnapshot {
     #[serde(flatten)]
     pub artefact: LiteloaderArtifact,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_successful_build: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct LiteloaderSnapshots {
     pub libraries: Vec<PolyMCLibrary>,
@@ -66,6 +76,7 @@ pub struct LiteloaderSnapshots {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct LiteloaderEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dev: Option<LiteloaderDev>,
@@ -74,9 +85,13 @@ pub struct LiteloaderEntry {
     pub artifacts: Option<LiteloaderArtifacts>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub snapshots: Option<LiteloaderSnapshots>,
+    /// Set if the version was removed upstream, but kept in the local index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct LiteloaderMeta {
     pub description: String,
@@ -87,7 +102,12 @@ pub struct LiteloaderMeta {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct LiteloaderIndex {
     pub meta: LiteloaderMeta,
     pub versions: Option<HashMap<String, LiteloaderEntry>>,
-}
\ No newline at end of file
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
@@ -0,0 +1,181 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+custom_error! {
+    /// Errors that can occur when parsing a `maven-metadata.xml` file.
+    pub MavenError
+        InvalidMetadata { reason: String } = "Invalid Maven metadata: {reason}",
+}
+
+/// The list of versions inside the `versioning` section.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct MavenVersions {
+    #[serde(default)]
+    pub version: Vec<String>,
+}
+
+/// The `versioning` section of a `maven-metadata.xml` file.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MavenVersioning {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release: Option<String>,
+    #[serde(default)]
+    pub versions: MavenVersions,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "maven_timestamp",
+        default
+    )]
+    pub last_updated: Option<DateTime<chrono::Utc>>,
+}
+
+/// A `maven-metadata.xml` file as served by Maven repositories.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename = "metadata", rename_all = "camelCase")]
+pub struct MavenMetadata {
+    #[serde(rename = "@modelVersion", skip_serializing_if = "Option::is_none")]
+    pub model_version: Option<String>,
+    pub group_id: String,
+    pub artifact_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versioning: Option<MavenVersioning>,
+}
+
+impl MavenMetadata {
+    /// Returns all versions listed in the metadata, in upstream order.
+    pub fn versions(&self) -> &[String] {
+        self.versioning
+            .as_ref()
+            .map(|versioning| versioning.versions.version.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the latest version, falling back to the last listed version.
+    pub fn latest(&self) -> Option<&str> {
+        self.versioning
+            .as_ref()
+            .and_then(|versioning| versioning.latest.as_deref())
+            .or_else(|| self.versions().last().map(|v| v.as_str()))
+    }
+
+    /// Returns the latest release version, if the repository published one.
+    pub fn release(&self) -> Option<&str> {
+        self.versioning
+            .as_ref()
+            .and_then(|versioning| versioning.release.as_deref())
+    }
+
+    /// Serializes the metadata back to XML.
+    pub fn to_xml(&self) -> Result<String, MavenError> {
+        quick_xml::se::to_string(self).map_err(|e| MavenError::InvalidMetadata {
+            reason: e.to_string(),
+        })
+    }
+}
+
+impl FromStr for MavenMetadata {
+    type Err = MavenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        quick_xml::de::from_str(s).map_err(|e| MavenError::InvalidMetadata {
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// Serializes and deserializes the `lastUpdated` timestamp, which uses the `yyyyMMddHHmmss` format.
+pub mod maven_timestamp {
+    use super::*;
+
+    const FORMAT: &str = "%Y%m%d%H%M%S";
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<chrono::Utc>>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let v = Option::<String>::deserialize(deserializer)?;
+        if let Some(v) = v {
+            let timestamp = NaiveDateTime::parse_from_str(v.trim(), FORMAT)
+                .map_err(serde::de::Error::custom)?;
+            return Ok(Some(timestamp.and_utc()));
+        }
+        Ok(None)
+    }
+
+    pub fn serialize<S>(
+        timestamp: &Option<DateTime<chrono::Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if let Some(timestamp) = timestamp {
+            serializer.serialize_str(&timestamp.format(FORMAT).to_string())
+        } else {
+            serializer.serialize_none()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    static FORGE_METADATA: &str = include_str!("../../tests/data/maven/forge-maven-metadata.xml");
+
+    #[test]
+    fn parses_metadata() {
+        let metadata: MavenMetadata = FORGE_METADATA.parse().unwrap();
+        assert_eq!(metadata.model_version.as_deref(), Some("1.1.0"));
+        assert_eq!(metadata.group_id, "net.minecraftforge");
+        assert_eq!(metadata.artifact_id, "forge");
+        assert_eq!(metadata.versions().len(), 6);
+        assert_eq!(metadata.versions()[0], "1.7.10-10.13.4.1614-1.7.10");
+        assert_eq!(metadata.latest(), Some("1.18.2-40.1.0"));
+        assert_eq!(metadata.release(), Some("1.18.2-40.1.0"));
+        assert_eq!(
+            metadata.versioning.unwrap().last_updated,
+            Some(chrono::Utc.with_ymd_and_hms(2022, 4, 24, 21, 5, 5).unwrap())
+        );
+    }
+
+    #[test]
+    fn round_trips_metadata() {
+        let metadata: MavenMetadata = FORGE_METADATA.parse().unwrap();
+        let written: MavenMetadata = metadata.to_xml().unwrap().parse().unwrap();
+        assert_eq!(written.versions(), metadata.versions());
+        assert_eq!(
+            written.versioning.unwrap().last_updated,
+            metadata.versioning.unwrap().last_updated
+        );
+    }
+
+    #[test]
+    fn latest_falls_back_to_last_version() {
+        let metadata: MavenMetadata = "<metadata><groupId>g</groupId><artifactId>a</artifactId>\
+            <versioning><versions><version>1.0</version><version>1.1</version></versions></versioning>\
+            </metadata>"
+            .parse()
+            .unwrap();
+        assert_eq!(metadata.latest(), Some("1.1"));
+        assert_eq!(metadata.release(), None);
+        assert_eq!(metadata.versioning.unwrap().last_updated, None);
+    }
+
+    #[test]
+    fn rejects_invalid_timestamp() {
+        let metadata = "<metadata><groupId>g</groupId><artifactId>a</artifactId>\
+            <versioning><lastUpdated>2022-04-24</lastUpdated></versioning></metadata>"
+            .parse::<MavenMetadata>();
+        assert!(metadata.is_err());
+    }
+}
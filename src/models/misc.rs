@@ -1,7 +1,9 @@
-use std::{fmt::Display, str::FromStr};
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
+use crate::version::MavenVersion;
+
 custom_error! { pub ModelError
     InvalidGradleSpecifier { specifier: String } = "Invalid Gradle specifier '{specifier}'",
 }
@@ -57,9 +59,14 @@ impl GradleSpecifier {
         format!("{}/{}", self.base(), self.filename())
     }
 
+    /// Returns the version of the artifact, parsed for comparison.
+    pub fn maven_version(&self) -> MavenVersion {
+        MavenVersion::new(&self.version)
+    }
+
     /// Returns `true` if the specifier is a LWJGL artifact.
     pub fn is_lwjgl(&self) -> bool {
-        vec![
+        [
             "org.lwjgl",
             "org.lwjgl.lwjgl",
             "net.java.jinput",
@@ -70,7 +77,7 @@ impl GradleSpecifier {
 
     /// Returns `true` if the specifier is a Log4j artifact.
     pub fn is_log4j(&self) -> bool {
-        vec!["org.apache.logging.log4j"].contains(&self.group.as_str())
+        ["org.apache.logging.log4j"].contains(&self.group.as_str())
     }
 }
 
@@ -89,7 +96,7 @@ impl FromStr for GradleSpecifier {
             .collect::<Vec<&str>>();
 
         let group = components
-            .get(0)
+            .first()
             .ok_or(ModelError::InvalidGradleSpecifier {
                 specifier: s.to_string(),
             })?
@@ -112,19 +119,18 @@ impl FromStr for GradleSpecifier {
             extension = Some(at_split[1].to_string());
         }
 
-        let classifier: Option<String>;
-        if components.len() == 4 {
-            classifier = Some(
+        let classifier = if components.len() == 4 {
+            Some(
                 components
                     .get(3)
                     .ok_or(ModelError::InvalidGradleSpecifier {
                         specifier: s.to_string(),
                     })?
                     .to_string(),
-            );
+            )
         } else {
-            classifier = None;
-        }
+            None
+        };
 
         Ok(GradleSpecifier {
             group,
@@ -164,6 +170,28 @@ impl Display for GradleSpecifier {
     }
 }
 
+impl Eq for GradleSpecifier {}
+
+impl Ord for GradleSpecifier {
+    /// Orders specifiers by group and artifact, then by Maven version semantics.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.group
+            .cmp(&other.group)
+            .then_with(|| self.artifact.cmp(&other.artifact))
+            .then_with(|| self.maven_version().cmp(&other.maven_version()))
+            .then_with(|| self.classifier.cmp(&other.classifier))
+            .then_with(|| self.extension.cmp(&other.extension))
+            // equal Maven versions can still be spelled differently
+            .then_with(|| self.version.cmp(&other.version))
+    }
+}
+
+impl PartialOrd for GradleSpecifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Serialize for GradleSpecifier {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -173,6 +201,22 @@ impl Serialize for GradleSpecifier {
     }
 }
 
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for GradleSpecifier {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "GradleSpecifier".into()
+    }
+
+    /// A `group:artifact:version[:classifier][@extension]` string, with the custom `gradle-specifier` format.
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "string",
+            "format": "gradle-specifier",
+            "pattern": "^[^:@]+:[^:@]+:[^:@]+(:[^:@]+)?(@[^:@]+)?$",
+        })
+    }
+}
+
 impl<'de> Deserialize<'de> for GradleSpecifier {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -1,6 +1,13 @@
 pub mod fabric;
 pub mod forge;
+pub mod liteloader;
+pub mod maven;
 pub mod misc;
 pub mod mojang;
+pub mod patch;
 pub mod polymc;
-pub mod liteloader;
\ No newline at end of file
+pub mod report;
+
+/// Fields of a model which this crate doesn't know about, see the `extra-fields` feature.
+#[cfg(feature = "extra-fields")]
+pub type ExtraFields = serde_json::Map<String, serde_json::Value>;
@@ -1,12 +1,24 @@
-use std::{cell::RefCell, collections::HashMap, ops::DerefMut};
+use std::{
+    cell::RefCell, cmp::Ordering, collections::HashMap, fmt::Display, ops::DerefMut, str::FromStr,
+    sync::Mutex,
+};
 
 use chrono::DateTime;
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "extra-fields")]
+use super::ExtraFields;
+
+use crate::version::MinecraftVersion;
+
 use super::misc::GradleSpecifier;
 
 lazy_static! {
     pub static ref MAX_MOJANG_SUPPORTED_VERSION: i32 = 21;
+    pub static ref MAX_SUPPORTED_COMPLIANCE_LEVEL: i32 = 1;
+    /// Compiled OS version patterns of rules, `None` if the pattern is invalid.
+    static ref OS_VERSION_PATTERNS: Mutex<HashMap<String, Option<regex::Regex>>> =
+        Mutex::new(HashMap::new());
 }
 
 // TODO: Change the supported version if it changes!
@@ -18,6 +30,7 @@ custom_error! {
 
 /// A single entry of Mojang's version index.
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangIndexEntry {
     pub id: String,
     #[serde(rename = "releaseTime")]
@@ -30,16 +43,38 @@ pub struct MojangIndexEntry {
     pub sha1: Option<String>,
     #[serde(rename = "complianceLevel", skip_serializing_if = "Option::is_none")]
     pub compliance_level: Option<i32>,
+    /// Set if the version was removed upstream, but kept in the local index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed: Option<bool>,
+}
+
+impl MojangIndexEntry {
+    /// Returns the version ID, parsed for comparison.
+    pub fn minecraft_version(&self) -> MinecraftVersion {
+        MinecraftVersion::new(&self.id)
+    }
+
+    /// Compares two entries by release time, falling back to the version ID.
+    pub fn cmp_version(&self, other: &Self) -> Ordering {
+        self.release_time
+            .cmp(&other.release_time)
+            .then_with(|| self.minecraft_version().cmp(&other.minecraft_version()))
+    }
 }
 
 /// Mojang's index of all versions.
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangIndex<'a> {
     pub latest: HashMap<String, String>,
     pub versions: Vec<MojangIndexEntry>,
     // this has to be a RefCell for interior mutability
     #[serde(skip)]
     pub version_map: RefCell<HashMap<String, &'a MojangIndexEntry>>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 impl<'a> MojangIndex<'a> {
@@ -57,6 +92,7 @@ impl<'a> MojangIndex<'a> {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangArtifactBase {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sha1: Option<String>,
@@ -66,6 +102,7 @@ pub struct MojangArtifactBase {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangArtifact {
     #[serde(flatten)]
     pub artifact_base: MojangArtifactBase,
@@ -74,6 +111,7 @@ pub struct MojangArtifact {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangAssets {
     #[serde(flatten)]
     pub artifact: MojangArtifactBase,
@@ -83,6 +121,7 @@ pub struct MojangAssets {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangLibraryDownloads {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub artifact: Option<MojangArtifact>,
@@ -91,11 +130,20 @@ pub struct MojangLibraryDownloads {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangLibraryExtractRules {
     pub exclude: Vec<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+custom_error! {
+    /// Errors that can occur when parsing a platform.
+    pub PlatformError
+        InvalidPlatform { platform: String } = "Invalid platform '{platform}', expected {{os}}-{{arch}} like linux-aarch64"
+}
+
+/// An operating system, optionally limited to an architecture like PolyMC's `linux-arm64`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum OSName {
     #[serde(rename = "windows")]
     Windows,
@@ -103,15 +151,201 @@ pub enum OSName {
     Linux,
     #[serde(rename = "osx")]
     MacOS,
+    #[serde(rename = "windows-arm64")]
+    WindowsArm64,
+    #[serde(rename = "linux-arm64")]
+    LinuxArm64,
+    #[serde(rename = "linux-arm32")]
+    LinuxArm32,
+    #[serde(rename = "osx-arm64")]
+    MacOSArm64,
+}
+
+impl OSName {
+    /// Returns the operating system without the architecture.
+    pub fn base(self) -> Self {
+        match self {
+            Self::Windows | Self::WindowsArm64 => Self::Windows,
+            Self::Linux | Self::LinuxArm64 | Self::LinuxArm32 => Self::Linux,
+            Self::MacOS | Self::MacOSArm64 => Self::MacOS,
+        }
+    }
+
+    /// Returns the normalized architecture the name is limited to.
+    pub fn arch(self) -> Option<&'static str> {
+        match self {
+            Self::WindowsArm64 | Self::LinuxArm64 | Self::MacOSArm64 => Some("arm64"),
+            Self::LinuxArm32 => Some("arm32"),
+            Self::Windows | Self::Linux | Self::MacOS => None,
+        }
+    }
+
+    /// Returns the name as used in rules and as key of `natives`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Windows => "windows",
+            Self::Linux => "linux",
+            Self::MacOS => "osx",
+            Self::WindowsArm64 => "windows-arm64",
+            Self::LinuxArm64 => "linux-arm64",
+            Self::LinuxArm32 => "linux-arm32",
+            Self::MacOSArm64 => "osx-arm64",
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct OSRule {
-    pub name: OSName,
-    pub rules: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<OSName>,
+    /// A regular expression matched against the version of the operating system.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The architecture of the JVM, e.g. `x86` for 32-bit Java.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+impl OSRule {
+    pub fn matches(&self, platform: &Platform) -> bool {
+        let name_matches = self
+            .name
+            .map(|name| {
+                name.base() == platform.os
+                    && name
+                        .arch()
+                        .map(|arch| arch == platform.arch)
+                        .unwrap_or(true)
+            })
+            .unwrap_or(true);
+        let arch_matches = self
+            .arch
+            .as_ref()
+            .map(|arch| normalize_arch(arch) == platform.arch)
+            .unwrap_or(true);
+        // without a known version, version-specific rules don't apply
+        let version_matches = self
+            .version
+            .as_ref()
+            .map(|pattern| match &platform.version {
+                Some(version) => os_version_matches(pattern, version),
+                None => false,
+            })
+            .unwrap_or(true);
+
+        name_matches && arch_matches && version_matches
+    }
+}
+
+/// Matches an OS version against a pattern of a rule, compiling every pattern only once.
+///
+/// Invalid patterns never match.
+fn os_version_matches(pattern: &str, version: &str) -> bool {
+    let mut patterns = OS_VERSION_PATTERNS.lock().unwrap();
+    let regex = patterns
+        .entry(pattern.to_string())
+        .or_insert_with(|| regex::Regex::new(pattern).ok());
+    regex
+        .as_ref()
+        .map(|regex| regex.is_match(version))
+        .unwrap_or(false)
+}
+
+/// Normalizes the different names of an architecture, e.g. `aarch64` and `arm64` are both `arm64`.
+pub fn normalize_arch(arch: &str) -> String {
+    let arch = arch.to_lowercase();
+    match arch.as_str() {
+        "x86_64" | "amd64" | "x64" => "x86_64".to_string(),
+        "x86" | "i386" | "i486" | "i586" | "i686" => "x86".to_string(),
+        "aarch64" | "arm64" | "armv8" => "arm64".to_string(),
+        "arm" | "arm32" | "armv7" | "armv7l" | "armhf" | "armv6l" => "arm32".to_string(),
+        _ => arch,
+    }
+}
+
+/// The platform a launcher runs on, which rules and natives are evaluated for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    /// The operating system, without an architecture.
+    pub os: OSName,
+    /// The normalized architecture, see [`normalize_arch`].
+    pub arch: String,
+    /// The version of the operating system, if known.
+    pub version: Option<String>,
+}
+
+impl Platform {
+    pub fn new(os: OSName, arch: &str) -> Self {
+        Self {
+            os: os.base(),
+            arch: normalize_arch(os.arch().unwrap_or(arch)),
+            version: None,
+        }
+    }
+
+    /// Returns the platform this program was built for.
+    pub fn current() -> Self {
+        let os = match std::env::consts::OS {
+            "windows" => OSName::Windows,
+            "macos" => OSName::MacOS,
+            _ => OSName::Linux,
+        };
+        Self::new(os, std::env::consts::ARCH)
+    }
+
+    pub fn with_version<V>(mut self, version: V) -> Self
+    where
+        V: Into<String>,
+    {
+        self.version = Some(version.into());
+        self
+    }
+
+    pub fn is_x86(&self) -> bool {
+        self.arch == "x86" || self.arch == "x86_64"
+    }
+
+    /// Returns `32` or `64`, which replaces `${arch}` in classifiers of natives.
+    pub fn bitness(&self) -> &'static str {
+        match self.arch.as_str() {
+            "x86" | "arm32" => "32",
+            _ => "64",
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = PlatformError;
+
+    /// Parses `{os}-{arch}` like `linux-aarch64`, `windows-x86_64` or `osx-arm64`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid_platform = || PlatformError::InvalidPlatform {
+            platform: s.to_string(),
+        };
+        let (os, arch) = s.split_once('-').ok_or_else(invalid_platform)?;
+        let os = match os.to_lowercase().as_str() {
+            "windows" => OSName::Windows,
+            "linux" => OSName::Linux,
+            "osx" | "macos" => OSName::MacOS,
+            _ => return Err(invalid_platform()),
+        };
+        if arch.is_empty() {
+            return Err(invalid_platform());
+        }
+
+        Ok(Self::new(os, arch))
+    }
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.os.as_str(), self.arch)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum MojangAction {
     Allow,
@@ -119,21 +353,87 @@ pub enum MojangAction {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangRule {
     pub action: MojangAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub os: Option<OSRule>,
 }
 
+impl MojangRule {
+    pub fn matches(&self, platform: &Platform) -> bool {
+        self.os
+            .as_ref()
+            .map(|os| os.matches(platform))
+            .unwrap_or(true)
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangLibrary {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub extract: Option<MojangLibraryExtractRules>,
     pub name: GradleSpecifier,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub downloads: Option<MojangLibraryDownloads>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub natives: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub rules: Option<Vec<MojangRule>>,
 }
 
+impl MojangLibrary {
+    /// Evaluates the rules the way the launchers do: without rules the library is allowed, otherwise the last
+    /// matching rule decides and nothing is allowed if none matches.
+    ///
+    /// Libraries with a `natives-{os}-{arch}` classifier, like `natives-linux-arm64`, are only allowed on that
+    /// architecture, the ones with a `natives-{os}` classifier only on x86.
+    pub fn is_allowed(&self, platform: &Platform) -> bool {
+        let natives = self
+            .name
+            .classifier
+            .as_deref()
+            .and_then(|classifier| classifier.strip_prefix("natives-"));
+        if let Some(natives) = natives {
+            let allowed = match natives.split_once('-') {
+                Some((_, arch)) => normalize_arch(arch) == platform.arch,
+                None => platform.is_x86(),
+            };
+            if !allowed {
+                return false;
+            }
+        }
+
+        match &self.rules {
+            Some(rules) => rules
+                .iter()
+                .rev()
+                .find(|rule| rule.matches(platform))
+                .map(|rule| rule.action == MojangAction::Allow)
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+
+    /// Returns the classifier of the natives for a platform, preferring an architecture-specific entry like
+    /// `linux-arm64` over the one of the operating system, which only has x86 natives.
+    pub fn native_classifier(&self, platform: &Platform) -> Option<String> {
+        let natives = self.natives.as_ref()?;
+        natives
+            .get(&format!("{}-{}", platform.os.as_str(), platform.arch))
+            .or_else(|| {
+                platform
+                    .is_x86()
+                    .then(|| natives.get(platform.os.as_str()))
+                    .flatten()
+            })
+            .map(|classifier| classifier.replace("${arch}", platform.bitness()))
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangLoggingArtifact {
     #[serde(flatten)]
     pub artifact: MojangArtifactBase,
@@ -141,12 +441,14 @@ pub struct MojangLoggingArtifact {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum MojangLoggingType {
     #[serde(rename = "log4j2-xml")]
     Log4J2Xml,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangLogging {
     pub file: MojangLoggingArtifact,
     pub argument: String,
@@ -155,8 +457,10 @@ pub struct MojangLogging {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MojangArguments {
-    pub game: Option<Vec<String>>,
+    // game arguments can also be objects with rules, just like JVM arguments
+    pub game: Option<Vec<serde_json::Value>>,
     pub jvm: Option<Vec<serde_json::Value>>,
 }
 
@@ -169,6 +473,7 @@ fn default_java_version() -> u8 {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct JavaVersion {
     #[serde(default = "default_java_component")]
     pub component: String,
@@ -177,6 +482,7 @@ pub struct JavaVersion {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct MojangVersionFile {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -202,6 +508,10 @@ pub struct MojangVersionFile {
         with = "crate::validators::mojang_version_validation",
         default
     )]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(schema_with = "crate::validators::mojang_version_validation::schema")
+    )]
     pub minimum_launcher_version: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub release_time: Option<DateTime<chrono::Utc>>,
@@ -217,4 +527,124 @@ pub struct MojangVersionFile {
     pub java_version: Option<JavaVersion>,
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub version_type: Option<String>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os_rule(rule: serde_json::Value) -> OSRule {
+        serde_json::from_value(rule).unwrap()
+    }
+
+    fn platform(os: OSName, version: Option<&str>) -> Platform {
+        Platform {
+            version: version.map(str::to_string),
+            ..Platform::new(os, "x86_64")
+        }
+    }
+
+    #[test]
+    fn os_rules_match_versions() {
+        let rule = os_rule(serde_json::json!({ "name": "osx", "version": "^10\\.5\\.\\d$" }));
+
+        assert!(rule.matches(&platform(OSName::MacOS, Some("10.5.8"))));
+        // the cached pattern gives the same results
+        assert!(rule.matches(&platform(OSName::MacOS, Some("10.5.2"))));
+        assert!(!rule.matches(&platform(OSName::MacOS, Some("10.6.1"))));
+        assert!(!rule.matches(&platform(OSName::MacOS, None)));
+        assert!(!rule.matches(&platform(OSName::Linux, Some("10.5.8"))));
+    }
+
+    #[test]
+    fn invalid_os_version_patterns_never_match() {
+        let rule = os_rule(serde_json::json!({ "version": "[" }));
+
+        assert!(!rule.matches(&platform(OSName::Windows, Some("["))));
+        assert!(os_rule(serde_json::json!({ "name": "windows" }))
+            .matches(&platform(OSName::Windows, None)));
+    }
+
+    fn library(library: serde_json::Value) -> MojangLibrary {
+        serde_json::from_value(library).unwrap()
+    }
+
+    fn parse_platform(platform: &str) -> Platform {
+        platform.parse().unwrap()
+    }
+
+    #[test]
+    fn arch_rules_match_the_normalized_architecture() {
+        // only 32-bit Java, like the natives of old LWJGL versions
+        let x86_only = library(serde_json::json!({
+            "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.0",
+            "rules": [{ "action": "allow", "os": { "arch": "x86" } }]
+        }));
+        assert!(x86_only.is_allowed(&parse_platform("windows-i686")));
+        assert!(!x86_only.is_allowed(&parse_platform("windows-x86_64")));
+
+        let disallowed_on_arm = library(serde_json::json!({
+            "name": "org.lwjgl:lwjgl:3.2.2",
+            "rules": [
+                { "action": "allow" },
+                { "action": "disallow", "os": { "arch": "arm64" } }
+            ]
+        }));
+        assert!(disallowed_on_arm.is_allowed(&parse_platform("linux-x86_64")));
+        assert!(!disallowed_on_arm.is_allowed(&parse_platform("linux-aarch64")));
+        assert!(!disallowed_on_arm.is_allowed(&parse_platform("osx-arm64")));
+
+        // the last matching rule wins, so an allow after a disallow allows again
+        let allowed_on_arm = library(serde_json::json!({
+            "name": "org.lwjgl:lwjgl:3.3.1",
+            "rules": [
+                { "action": "disallow", "os": { "name": "linux" } },
+                { "action": "allow", "os": { "name": "linux", "arch": "aarch64" } }
+            ]
+        }));
+        assert!(allowed_on_arm.is_allowed(&parse_platform("linux-arm64")));
+        assert!(!allowed_on_arm.is_allowed(&parse_platform("linux-x86_64")));
+        assert!(!allowed_on_arm.is_allowed(&parse_platform("windows-x86_64")));
+    }
+
+    #[test]
+    fn native_classifiers_depend_on_the_platform() {
+        let lwjgl = library(serde_json::json!({
+            "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209",
+            "natives": {
+                "linux": "natives-linux",
+                "linux-arm64": "natives-linux-arm64",
+                "osx": "natives-osx",
+                "windows": "natives-windows-${arch}"
+            }
+        }));
+
+        assert_eq!(
+            lwjgl.native_classifier(&parse_platform("linux-aarch64")),
+            Some("natives-linux-arm64".to_string())
+        );
+        assert_eq!(
+            lwjgl.native_classifier(&parse_platform("linux-x86_64")),
+            Some("natives-linux".to_string())
+        );
+        // the natives of the operating system are x86 only
+        assert_eq!(lwjgl.native_classifier(&parse_platform("osx-arm64")), None);
+        assert_eq!(
+            lwjgl.native_classifier(&parse_platform("windows-x86")),
+            Some("natives-windows-32".to_string())
+        );
+        assert_eq!(
+            lwjgl.native_classifier(&parse_platform("windows-amd64")),
+            Some("natives-windows-64".to_string())
+        );
+        assert_eq!(
+            library(serde_json::json!({ "name": "com.mojang:brigadier:1.0.18" }))
+                .native_classifier(&parse_platform("linux-x86_64")),
+            None
+        );
+    }
 }
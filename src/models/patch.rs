@@ -0,0 +1,362 @@
+//! Declarative patches of the libraries of generated version files, e.g. to replace a broken download or a
+//! vulnerable library in every version of a package.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    misc::GradleSpecifier,
+    mojang::MojangRule,
+    polymc::{PolyMCLibrary, PolyMCVersionFile},
+};
+use crate::version::MavenVersionRange;
+
+/// Which libraries a patch applies to, every given part of the name has to match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct LibraryMatch {
+    pub group: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<MavenVersionRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classifier: Option<String>,
+}
+
+impl LibraryMatch {
+    pub fn matches(&self, name: &GradleSpecifier) -> bool {
+        self.group == name.group
+            && self
+                .artifact
+                .as_ref()
+                .map(|artifact| artifact == &name.artifact)
+                .unwrap_or(true)
+            && self
+                .version
+                .as_ref()
+                .map(|version| version.contains(&name.maven_version()))
+                .unwrap_or(true)
+            && self
+                .classifier
+                .as_ref()
+                .map(|classifier| name.classifier.as_ref() == Some(classifier))
+                .unwrap_or(true)
+    }
+}
+
+/// A patch of the libraries and Maven files matched by `match`.
+///
+/// A matched library is removed, or replaced by `replace` and then given `rules`, an empty list removes its
+/// rules. `add` is inserted after the first matched library, unless a library with the same name is already listed,
+/// so nothing is added to a list without a matched library.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct LibraryPatch {
+    #[serde(rename = "match")]
+    pub library_match: LibraryMatch,
+    /// Only patches versions of these packages, every package if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uids: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub remove: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace: Option<PolyMCLibrary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rules: Option<Vec<MojangRule>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub add: Option<Vec<PolyMCLibrary>>,
+}
+
+impl LibraryPatch {
+    fn applies_to(&self, uid: &str) -> bool {
+        self.uids
+            .as_ref()
+            .map(|uids| uids.iter().any(|patched_uid| patched_uid == uid))
+            .unwrap_or(true)
+    }
+
+    /// Patches a list of libraries and returns a description of every change.
+    fn apply(&self, libraries: &mut Vec<PolyMCLibrary>) -> Vec<String> {
+        let mut changes = Vec::new();
+        let mut added = false;
+        // additions may already be listed after the matched library
+        let listed_names: Vec<GradleSpecifier> = libraries
+            .iter()
+            .map(|library| library.library.name.clone())
+            .collect();
+        let mut patched_libraries = Vec::with_capacity(libraries.len());
+        for library in libraries.drain(..) {
+            if !self.library_match.matches(&library.library.name) {
+                patched_libraries.push(library);
+                continue;
+            }
+
+            let old_name = library.library.name.to_string();
+            if self.remove {
+                changes.push(format!("removed {}", old_name));
+            } else {
+                let mut library = match &self.replace {
+                    Some(replacement) => {
+                        changes.push(format!("{} -> {}", old_name, replacement.library.name));
+                        replacement.clone()
+                    }
+                    None => library,
+                };
+                if let Some(rules) = &self.rules {
+                    library.library.rules = (!rules.is_empty()).then(|| rules.clone());
+                    changes.push(format!("changed rules of {}", library.library.name));
+                }
+                patched_libraries.push(library);
+            }
+
+            if !added {
+                added = true;
+                for addition in self.add.iter().flatten() {
+                    let listed = listed_names.contains(&addition.library.name)
+                        || patched_libraries
+                            .iter()
+                            .any(|library| library.library.name == addition.library.name);
+                    if !listed {
+                        changes.push(format!("added {}", addition.library.name));
+                        patched_libraries.push(addition.clone());
+                    }
+                }
+            }
+        }
+        *libraries = patched_libraries;
+
+        changes
+    }
+}
+
+/// Patches applied to every generated version file, in order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct LibraryPatchIndex {
+    pub patches: Vec<LibraryPatch>,
+}
+
+impl LibraryPatchIndex {
+    /// Reads the patches of a file, there are none if no file is given.
+    pub fn load(patches_file: Option<&Path>) -> std::io::Result<Self> {
+        let path = match patches_file {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+        let invalid_file = |reason: String| {
+            std::io::Error::other(format!("Failed to parse {}: {}", path.display(), reason))
+        };
+        let file = std::fs::File::open(path)?;
+        let patch_index: Self = serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| invalid_file(e.to_string()))?;
+
+        for (i, patch) in patch_index.patches.iter().enumerate() {
+            if patch.remove && (patch.replace.is_some() || patch.rules.is_some()) {
+                return Err(invalid_file(format!(
+                    "patches[{}] removes libraries, so it can't replace them or change their rules",
+                    i
+                )));
+            }
+            if !patch.remove
+                && patch.replace.is_none()
+                && patch.rules.is_none()
+                && patch.add.is_none()
+            {
+                return Err(invalid_file(format!(
+                    "patches[{}] doesn't change anything",
+                    i
+                )));
+            }
+        }
+
+        Ok(patch_index)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+
+    /// Patches the libraries and Maven files of a version file and returns a description of every change.
+    pub fn apply(&self, file: &mut PolyMCVersionFile) -> Vec<String> {
+        let mut changes = Vec::new();
+        for patch in self
+            .patches
+            .iter()
+            .filter(|patch| patch.applies_to(&file.uid))
+        {
+            for libraries in [&mut file.libraries, &mut file.maven_files]
+                .into_iter()
+                .flatten()
+            {
+                changes.extend(patch.apply(libraries));
+            }
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn version_file(uid: &str) -> PolyMCVersionFile {
+        serde_json::from_value(json!({
+            "formatVersion": 1,
+            "name": uid,
+            "uid": uid,
+            "version": "1.0",
+            "libraries": [
+                { "name": "org.lwjgl.lwjgl:lwjgl:2.9.1" },
+                {
+                    "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.1",
+                    "rules": [{ "action": "allow", "os": { "name": "osx" } }]
+                },
+                { "name": "net.java.jinput:jinput:2.0.5" }
+            ],
+            "mavenFiles": [{ "name": "org.lwjgl.lwjgl:lwjgl:2.9.4" }]
+        }))
+        .unwrap()
+    }
+
+    fn patches(patches: serde_json::Value) -> LibraryPatchIndex {
+        serde_json::from_value(json!({ "patches": patches })).unwrap()
+    }
+
+    fn names(libraries: &Option<Vec<PolyMCLibrary>>) -> Vec<String> {
+        libraries
+            .iter()
+            .flatten()
+            .map(|library| library.library.name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn removes_matched_libraries() {
+        let mut file = version_file("net.minecraft");
+        let changes = patches(json!([{
+            "match": { "group": "org.lwjgl.lwjgl", "version": "[2.9,2.9.4)" },
+            "remove": true
+        }]))
+        .apply(&mut file);
+
+        assert_eq!(
+            changes,
+            vec![
+                "removed org.lwjgl.lwjgl:lwjgl:2.9.1",
+                "removed org.lwjgl.lwjgl:lwjgl-platform:2.9.1",
+            ]
+        );
+        assert_eq!(names(&file.libraries), vec!["net.java.jinput:jinput:2.0.5"]);
+        // 2.9.4 is out of the range
+        assert_eq!(
+            names(&file.maven_files),
+            vec!["org.lwjgl.lwjgl:lwjgl:2.9.4"]
+        );
+    }
+
+    #[test]
+    fn replaces_libraries_and_their_rules() {
+        let mut file = version_file("net.minecraft");
+        let changes = patches(json!([{
+            "match": { "group": "org.lwjgl.lwjgl", "artifact": "lwjgl-platform" },
+            "replace": {
+                "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                "url": "https://files.example.org/maven/"
+            },
+            "rules": []
+        }]))
+        .apply(&mut file);
+
+        assert_eq!(
+            changes,
+            vec![
+                "org.lwjgl.lwjgl:lwjgl-platform:2.9.1 -> org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                "changed rules of org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+            ]
+        );
+        let replaced = &file.libraries.as_ref().unwrap()[1];
+        assert_eq!(
+            replaced.library.name.to_string(),
+            "org.lwjgl.lwjgl:lwjgl-platform:2.9.4"
+        );
+        assert!(replaced.library.rules.is_none());
+    }
+
+    #[test]
+    fn only_patches_listed_uids() {
+        let patch_index = patches(json!([{
+            "match": { "group": "net.java.jinput" },
+            "uids": ["org.lwjgl"],
+            "remove": true
+        }]));
+
+        let mut minecraft = version_file("net.minecraft");
+        assert!(patch_index.apply(&mut minecraft).is_empty());
+        let mut lwjgl = version_file("org.lwjgl");
+        assert_eq!(
+            patch_index.apply(&mut lwjgl),
+            vec!["removed net.java.jinput:jinput:2.0.5"]
+        );
+    }
+
+    #[test]
+    fn adds_libraries_after_the_first_match() {
+        let mut file = version_file("net.minecraft");
+        let changes = patches(json!([{
+            "match": { "group": "org.lwjgl.lwjgl" },
+            "add": [
+                { "name": "net.java.jutils:jutils:1.0.0" },
+                { "name": "net.java.jinput:jinput:2.0.5" }
+            ]
+        }]))
+        .apply(&mut file);
+
+        // jinput is already listed in the libraries, after the matched library
+        assert_eq!(
+            changes,
+            vec![
+                "added net.java.jutils:jutils:1.0.0",
+                "added net.java.jutils:jutils:1.0.0",
+                "added net.java.jinput:jinput:2.0.5",
+            ]
+        );
+        assert_eq!(
+            names(&file.libraries),
+            vec![
+                "org.lwjgl.lwjgl:lwjgl:2.9.1",
+                "net.java.jutils:jutils:1.0.0",
+                "org.lwjgl.lwjgl:lwjgl-platform:2.9.1",
+                "net.java.jinput:jinput:2.0.5",
+            ]
+        );
+        assert_eq!(
+            names(&file.maven_files),
+            vec![
+                "org.lwjgl.lwjgl:lwjgl:2.9.4",
+                "net.java.jutils:jutils:1.0.0",
+                "net.java.jinput:jinput:2.0.5",
+            ]
+        );
+    }
+
+    #[test]
+    fn adds_nothing_without_a_match() {
+        let mut file = version_file("net.minecraft");
+        let changes = patches(json!([{
+            "match": { "group": "com.mojang", "artifact": "text2speech" },
+            "add": [{ "name": "net.java.jutils:jutils:1.0.0" }]
+        }]))
+        .apply(&mut file);
+
+        assert!(changes.is_empty());
+        assert_eq!(
+            names(&file.libraries),
+            names(&version_file("net.minecraft").libraries)
+        );
+    }
+}
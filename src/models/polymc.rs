@@ -1,13 +1,16 @@
-use std::str::FromStr;
+use std::{collections::HashMap, path::Path, str::FromStr};
 
 use chrono::DateTime;
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "extra-fields")]
+use super::ExtraFields;
+
 use super::{
     misc::GradleSpecifier,
     mojang::{
-        MojangArtifact, MojangArtifactBase, MojangAssets, MojangError, MojangLibrary,
-        MojangLibraryDownloads, MojangVersionFile,
+        MojangArtifact, MojangArtifactBase, MojangAssets, MojangError, MojangIndex, MojangLibrary,
+        MojangLibraryDownloads, MojangVersionFile, MAX_SUPPORTED_COMPLIANCE_LEVEL,
     },
 };
 
@@ -15,14 +18,43 @@ lazy_static! {
     pub static ref CURRENT_POLYMC_FORMAT_VERSION: u8 = 1;
 }
 
+pub const MINECRAFT_UID: &str = "net.minecraft";
+pub const FORGE_UID: &str = "net.minecraftforge";
+pub const FABRIC_LOADER_UID: &str = "net.fabricmc.fabric-loader";
+pub const FABRIC_INTERMEDIARY_UID: &str = "net.fabricmc.intermediary";
+pub const LITELOADER_UID: &str = "com.mumfrey.liteloader";
+pub const LWJGL2_UID: &str = "org.lwjgl";
+pub const LWJGL3_UID: &str = "org.lwjgl3";
+
+/// Overrides of Minecraft versions older than the launcher's version format, see [`LegacyOverrideIndex`].
+pub static LEGACY_OVERRIDES: &str = include_str!("static_files/legacy_overrides.json");
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PolyMCLibrary {
     #[serde(flatten)]
     pub library: MojangLibrary,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
     #[serde(rename = "MMC-hint", skip_serializing_if = "Option::is_none")]
-    mmc_hint: Option<String>,
+    pub mmc_hint: Option<String>,
+}
+
+impl PolyMCLibrary {
+    /// Creates a library which is only described by its name and, optionally, a Maven repository URL.
+    pub fn new(name: GradleSpecifier, url: Option<String>) -> Self {
+        Self {
+            library: MojangLibrary {
+                extract: None,
+                name,
+                downloads: None,
+                natives: None,
+                rules: None,
+            },
+            url,
+            mmc_hint: None,
+        }
+    }
 }
 
 impl From<MojangLibrary> for PolyMCLibrary {
@@ -40,16 +72,22 @@ fn default_format_version() -> u8 {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct VersionedJsonObject {
     #[serde(
         rename = "formatVersion",
         default = "default_format_version",
         with = "crate::validators::polymc_version_validation"
     )]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(schema_with = "crate::validators::polymc_version_validation::schema")
+    )]
     pub format_version: u8,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DependencyEntry {
     pub uid: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -59,6 +97,7 @@ pub struct DependencyEntry {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PolyMCVersionFile {
     #[serde(flatten)]
     pub versioned_json_object: VersionedJsonObject,
@@ -95,8 +134,14 @@ pub struct PolyMCVersionFile {
     pub add_traits: Option<Vec<String>>,
     #[serde(rename = "+tweakers", skip_serializing_if = "Option::is_none")]
     pub add_tweakers: Option<Vec<String>>,
+    #[serde(rename = "+jvmArgs", skip_serializing_if = "Option::is_none")]
+    pub add_jvm_arguments: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub order: Option<i32>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 impl PolyMCVersionFile {
@@ -123,7 +168,10 @@ impl PolyMCVersionFile {
             version_file_type: None,
             add_traits: None,
             add_tweakers: None,
+            add_jvm_arguments: None,
             order: None,
+            #[cfg(feature = "extra-fields")]
+            extra: Default::default(),
         }
     }
 
@@ -184,24 +232,20 @@ impl PolyMCVersionFile {
         pmc_file.minecraft_arguments = file.minecraft_arguments.clone();
         pmc_file.release_time = file.release_time;
         pmc_file.version_file_type = file.version_type.clone();
-        let max_supported_compliance_level = 1;
         if let Some(compliance_level) = file.compliance_level {
-            if compliance_level == max_supported_compliance_level {
-                if pmc_file.add_traits.is_none() {
-                    pmc_file.add_traits = Some(Vec::new());
-                }
-
-                pmc_file
-                    .add_traits
-                    .as_mut()
-                    .unwrap()
-                    .push("XR:Initial".to_string());
-            } else {
+            if compliance_level > *MAX_SUPPORTED_COMPLIANCE_LEVEL {
                 return Err(MojangError::UnknownComplicanceLevel {
                     compliance_level,
-                    max_supported: max_supported_compliance_level,
+                    max_supported: *MAX_SUPPORTED_COMPLIANCE_LEVEL,
                 });
             }
+            // level 0 has no requirements, level 1 requires the launcher to support Xbox Live accounts
+            if compliance_level == 1 {
+                pmc_file
+                    .add_traits
+                    .get_or_insert_with(Vec::new)
+                    .push("XR:Initial".to_string());
+            }
         }
 
         Ok(pmc_file)
@@ -228,6 +272,7 @@ impl PolyMCVersionFile {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PolyMCSharedPackageData {
     #[serde(flatten)]
     pub versioned_json_object: VersionedJsonObject,
@@ -241,19 +286,15 @@ pub struct PolyMCSharedPackageData {
     pub description: Option<String>,
     #[serde(rename = "projectUrl", skip_serializing_if = "Option::is_none")]
     pub project_url: Option<String>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 impl PolyMCSharedPackageData {
-    /// Writes the package data to `polymc/{uid}/package.json`
-    pub fn write(&self) -> std::io::Result<()> {
-        let self_serialized = serde_json::to_string(&self)?;
-
-        std::fs::write(format!("polymc/{}/package.json", self.uid), self_serialized)
-    }
-
-    /// Creates a new PolyMCSharedPackageData and writes it to `polymc/{uid}/package.json`
-    pub fn write_new(uid: String, name: String) -> std::io::Result<()> {
-        let pmc_shared_package_data = Self {
+    pub fn new(uid: String, name: String) -> Self {
+        Self {
             versioned_json_object: VersionedJsonObject {
                 format_version: *CURRENT_POLYMC_FORMAT_VERSION,
             },
@@ -263,22 +304,48 @@ impl PolyMCSharedPackageData {
             authors: None,
             description: None,
             project_url: None,
-        };
-        pmc_shared_package_data.write()
+            #[cfg(feature = "extra-fields")]
+            extra: Default::default(),
+        }
     }
 
-    /// Reads the package data from `polymc/{uid}/package.json`
-    pub fn read(uid: String) -> std::io::Result<Self> {
-        let file_content = std::fs::read_to_string(format!("polymc/{}/package.json", uid))?;
+    /// Writes the package data to `{output_directory}/{uid}/package.json`
+    pub fn write<P>(&self, output_directory: P) -> std::io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let self_serialized = serde_json::to_string(&self)?;
+        let package_directory = output_directory.as_ref().join(&self.uid);
+        std::fs::create_dir_all(&package_directory)?;
+
+        std::fs::write(package_directory.join("package.json"), self_serialized)
+    }
+
+    /// Creates a new PolyMCSharedPackageData and writes it to `{output_directory}/{uid}/package.json`
+    pub fn write_new<P>(output_directory: P, uid: String, name: String) -> std::io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        Self::new(uid, name).write(output_directory)
+    }
+
+    /// Reads the package data from `{output_directory}/{uid}/package.json`
+    pub fn read<P>(output_directory: P, uid: String) -> std::io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let file_content =
+            std::fs::read_to_string(output_directory.as_ref().join(&uid).join("package.json"))?;
         let pmc_shared_package_data: Self = serde_json::from_str(&file_content)?;
         Ok(pmc_shared_package_data)
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PolyMCVersionIndexEntry {
     pub version: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub version_type: Option<String>,
     #[serde(rename = "releaseTime", skip_serializing_if = "Option::is_none")]
     pub release_time: Option<DateTime<chrono::Utc>>,
@@ -293,16 +360,38 @@ pub struct PolyMCVersionIndexEntry {
     pub sha256: String,
 }
 
+impl PolyMCVersionIndexEntry {
+    /// Creates the index entry of a version file, whose serialized form has the given SHA-256 hash.
+    pub fn from_version_file(file: &PolyMCVersionFile, sha256: String) -> Self {
+        Self {
+            version: file.version.clone(),
+            version_type: file.version_file_type.clone(),
+            release_time: file.release_time,
+            requires: file.requires.clone(),
+            conflicts: file.conflicts.clone(),
+            recommended: None,
+            volatile: file.volatile,
+            sha256,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PolyMCVersionIndex {
     #[serde(flatten)]
     pub versioned_json_object: VersionedJsonObject,
     pub name: String,
     pub uid: String,
     pub versions: Vec<PolyMCVersionIndexEntry>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PolyMCPackageIndexEntry {
     pub name: String,
     pub uid: String,
@@ -310,13 +399,19 @@ pub struct PolyMCPackageIndexEntry {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PolyMCPackageIndex {
     #[serde(flatten)]
     pub versioned_json_object: VersionedJsonObject,
     pub packages: Vec<PolyMCPackageIndexEntry>,
+    /// Fields which aren't part of the model, kept so they are written back unchanged.
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct LegacyOverrideEntry {
     #[serde(rename = "releaseTime", skip_serializing_if = "Option::is_none")]
     pub release_time: Option<DateTime<chrono::Utc>>,
@@ -327,3 +422,133 @@ pub struct LegacyOverrideEntry {
     #[serde(rename = "+traits", skip_serializing_if = "Option::is_none")]
     pub add_traits: Option<Vec<String>>,
 }
+
+/// Overrides of legacy Minecraft versions, keyed by Minecraft version.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct LegacyOverrideIndex {
+    /// Prefixes of the versions the overrides are complete for, e.g. `a1.` for every alpha 1 version.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub covers: Vec<String>,
+    pub versions: HashMap<String, LegacyOverrideEntry>,
+}
+
+impl LegacyOverrideIndex {
+    /// Returns the overrides bundled with this crate.
+    pub fn bundled() -> Self {
+        serde_json::from_str(LEGACY_OVERRIDES).expect("the bundled legacy overrides are valid")
+    }
+
+    /// Returns the bundled overrides, extended with the ones of a file if given.
+    pub fn load(overrides_file: Option<&std::path::Path>) -> std::io::Result<Self> {
+        let mut overrides = Self::bundled();
+        if let Some(path) = overrides_file {
+            let file = std::fs::File::open(path)?;
+            let file_overrides =
+                serde_json::from_reader(std::io::BufReader::new(file)).map_err(|e| {
+                    std::io::Error::other(format!("Failed to parse {}: {}", path.display(), e))
+                })?;
+            overrides.extend(file_overrides);
+        }
+
+        Ok(overrides)
+    }
+
+    /// Adds the overrides of another index, replacing the ones of the same versions.
+    pub fn extend(&mut self, other: Self) {
+        for prefix in other.covers {
+            if !self.covers.contains(&prefix) {
+                self.covers.push(prefix);
+            }
+        }
+        self.versions.extend(other.versions);
+    }
+
+    /// Returns whether every version starting like this one should have an override.
+    pub fn covers(&self, version: &str) -> bool {
+        self.covers
+            .iter()
+            .any(|prefix| version.starts_with(prefix.as_str()))
+    }
+
+    /// Returns the legacy versions of a Mojang index which are covered, but have no override, sorted.
+    pub fn missing_versions(&self, index: &MojangIndex) -> Vec<String> {
+        let mut missing_versions = index
+            .versions
+            .iter()
+            .filter(|entry| ["old_alpha", "old_beta"].contains(&entry.version_type.as_str()))
+            .filter(|entry| self.covers(&entry.id) && !self.versions.contains_key(&entry.id))
+            .map(|entry| entry.id.clone())
+            .collect::<Vec<String>>();
+        missing_versions.sort();
+        missing_versions
+    }
+
+    /// Returns the versions of the overrides which aren't in a Mojang index, sorted.
+    pub fn unknown_versions(&self, index: &MojangIndex) -> Vec<String> {
+        let mut unknown_versions = self
+            .versions
+            .keys()
+            .filter(|version| !index.versions.iter().any(|entry| &entry.id == *version))
+            .cloned()
+            .collect::<Vec<String>>();
+        unknown_versions.sort();
+        unknown_versions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_entry(id: &str, version_type: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "type": version_type,
+            "url": format!("https://piston-meta.mojang.com/v1/packages/{}.json", id),
+            "time": "2013-08-22T00:00:00+00:00",
+            "releaseTime": "2010-01-01T00:00:00+00:00"
+        })
+    }
+
+    #[test]
+    fn legacy_overrides_cover_alpha_and_beta() {
+        let overrides = LegacyOverrideIndex::bundled();
+        let index: MojangIndex = serde_json::from_value(serde_json::json!({
+            "latest": { "release": "1.0", "snapshot": "1.0" },
+            "versions": [
+                index_entry("b1.8.1", "old_beta"),
+                index_entry("b1.9-pre1", "old_beta"),
+                index_entry("a1.0.4", "old_alpha"),
+                index_entry("inf-20100618", "old_alpha"),
+                index_entry("c0.0.11a", "old_alpha"),
+                index_entry("rd-132211", "old_alpha"),
+            ]
+        }))
+        .unwrap();
+
+        assert!(overrides.covers("a1.2.6"));
+        assert!(!overrides.covers("rd-132211"));
+        // only the beta without an override, not the uncovered versions
+        assert_eq!(overrides.missing_versions(&index), vec!["b1.9-pre1"]);
+    }
+
+    #[test]
+    fn legacy_overrides_extend_coverage() {
+        let mut overrides = LegacyOverrideIndex::bundled();
+        overrides.extend(
+            serde_json::from_value(serde_json::json!({
+                "covers": ["c0.", "a1."],
+                "versions": {
+                    "c0.0.11a": { "mainClass": "com.mojang.minecraft.Minecraft" }
+                }
+            }))
+            .unwrap(),
+        );
+
+        assert_eq!(overrides.covers, vec!["a1.", "b1.", "c0."]);
+        assert!(overrides.covers("c0.30_01c"));
+        assert!(overrides.versions.contains_key("c0.0.11a"));
+        assert!(overrides.versions.contains_key("b1.8.1"));
+    }
+}
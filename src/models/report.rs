@@ -0,0 +1,288 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+custom_error! {
+    /// Errors that can occur when parsing report settings.
+    pub ReportError
+        UnknownRemovalPolicy { policy: String } = "Unknown removal policy '{policy}', expected keep, archive or delete",
+}
+
+/// What an updater does with versions which were removed upstream.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RemovalPolicy {
+    /// Keeps the version and its files, but marks it as removed in the generated index.
+    #[default]
+    Keep,
+    /// Moves the files of the version into the `archive` directory and drops it from the index.
+    Archive,
+    /// Deletes the files of the version and drops it from the index.
+    Delete,
+}
+
+impl RemovalPolicy {
+    /// Returns `true` if removed versions stay in the generated index.
+    pub fn keeps_entries(&self) -> bool {
+        *self == RemovalPolicy::Keep
+    }
+
+    /// Archives or deletes the cached files of a removed version and records that in the report.
+    ///
+    /// The paths are relative to the cache directory, missing files are ignored.
+    pub fn apply_to_files<P>(
+        &self,
+        cache_directory: P,
+        files: &[PathBuf],
+        report: &mut UpdateReport,
+    ) -> std::io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let cache_directory = cache_directory.as_ref();
+        for file in files {
+            let path = cache_directory.join(file);
+            if !path.is_file() {
+                continue;
+            }
+
+            match self {
+                RemovalPolicy::Keep => {}
+                RemovalPolicy::Archive => {
+                    let archive_path = cache_directory.join("archive").join(file);
+                    if let Some(parent) = archive_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::rename(&path, &archive_path)?;
+                    report.record_file(&archive_path, cache_directory);
+                    report.record_removed_file(&path, cache_directory);
+                }
+                RemovalPolicy::Delete => {
+                    std::fs::remove_file(&path)?;
+                    report.record_removed_file(&path, cache_directory);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for RemovalPolicy {
+    type Err = ReportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(RemovalPolicy::Keep),
+            "archive" => Ok(RemovalPolicy::Archive),
+            "delete" => Ok(RemovalPolicy::Delete),
+            _ => Err(ReportError::UnknownRemovalPolicy {
+                policy: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// A machine-readable summary of what a single update run changed.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateReport {
+    /// Name of the upstream the report belongs to, e.g. `mojang`.
+    pub component: String,
+    /// IDs of versions which didn't exist before this run.
+    pub added: Vec<String>,
+    /// IDs of versions which existed before, but changed upstream.
+    pub updated: Vec<String>,
+    /// IDs of versions which no longer exist upstream.
+    pub removed: Vec<String>,
+    /// Files written during this run, relative to the cache directory.
+    pub files_written: Vec<String>,
+    /// Files deleted during this run, relative to the cache directory.
+    #[serde(default)]
+    pub files_removed: Vec<String>,
+    /// Number of bytes received from upstream during this run.
+    pub bytes_downloaded: u64,
+    /// Fixes applied to the generated versions, e.g. replaced vulnerable libraries.
+    #[serde(default)]
+    pub patched: Vec<String>,
+}
+
+impl UpdateReport {
+    pub fn new(component: &str) -> Self {
+        Self {
+            component: component.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if any version was added, updated or removed.
+    pub fn has_changes(&self) -> bool {
+        !self.added.is_empty() || !self.updated.is_empty() || !self.removed.is_empty()
+    }
+
+    /// Records a file that has been written, storing its path relative to `base`.
+    pub fn record_file<P, B>(&mut self, path: P, base: B)
+    where
+        P: AsRef<Path>,
+        B: AsRef<Path>,
+    {
+        let relative_path = relative_path(path.as_ref(), base.as_ref());
+        if !self.files_written.contains(&relative_path) {
+            self.files_written.push(relative_path);
+        }
+    }
+
+    /// Records a file that has been deleted or archived, storing its path relative to `base`.
+    pub fn record_removed_file<P, B>(&mut self, path: P, base: B)
+    where
+        P: AsRef<Path>,
+        B: AsRef<Path>,
+    {
+        let relative_path = relative_path(path.as_ref(), base.as_ref());
+        if !self.files_removed.contains(&relative_path) {
+            self.files_removed.push(relative_path);
+        }
+    }
+
+    /// Records a number of bytes received from upstream.
+    pub fn record_download(&mut self, bytes: u64) {
+        self.bytes_downloaded += bytes;
+    }
+
+    /// Compares the previous and the current state of an index and records added, updated and removed IDs.
+    pub fn record_changes<T>(&mut self, previous: &HashMap<String, T>, current: &HashMap<String, T>)
+    where
+        T: PartialEq,
+    {
+        let mut added = Vec::new();
+        let mut updated = Vec::new();
+        for (id, value) in current {
+            match previous.get(id) {
+                None => added.push(id.clone()),
+                Some(previous_value) if previous_value != value => updated.push(id.clone()),
+                _ => {}
+            }
+        }
+        let mut removed = previous
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .cloned()
+            .collect::<Vec<String>>();
+
+        added.sort();
+        updated.sort();
+        removed.sort();
+        self.added.extend(added);
+        self.updated.extend(updated);
+        self.removed.extend(removed);
+    }
+}
+
+/// Returns the given path relative to `base`, always using forward slashes.
+fn relative_path(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(entries: &[(&str, u32)]) -> HashMap<String, u32> {
+        entries
+            .iter()
+            .map(|(id, value)| (id.to_string(), *value))
+            .collect()
+    }
+
+    /// Writes the files of a removed version into a fresh cache directory and applies the policy to them.
+    fn apply_policy(policy: RemovalPolicy) -> (PathBuf, UpdateReport) {
+        let cache_directory = std::env::temp_dir().join(format!(
+            "morphosis-meta-removal-{:?}-{}",
+            policy,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&cache_directory);
+        std::fs::create_dir_all(cache_directory.join("mojang/versions")).unwrap();
+        std::fs::write(cache_directory.join("mojang/versions/1.19.json"), "{}").unwrap();
+
+        let mut report = UpdateReport::new("mojang");
+        let files = [
+            PathBuf::from("mojang/versions/1.19.json"),
+            PathBuf::from("mojang/versions/1.19-missing.json"),
+        ];
+        policy
+            .apply_to_files(&cache_directory, &files, &mut report)
+            .unwrap();
+
+        (cache_directory, report)
+    }
+
+    #[test]
+    fn keeps_files_of_removed_versions() {
+        let (cache_directory, report) = apply_policy(RemovalPolicy::Keep);
+
+        assert!(cache_directory.join("mojang/versions/1.19.json").is_file());
+        assert!(!cache_directory.join("archive").exists());
+        assert!(report.files_written.is_empty());
+        assert!(report.files_removed.is_empty());
+        std::fs::remove_dir_all(&cache_directory).unwrap();
+    }
+
+    #[test]
+    fn archives_files_of_removed_versions() {
+        let (cache_directory, report) = apply_policy(RemovalPolicy::Archive);
+
+        assert!(!cache_directory.join("mojang/versions/1.19.json").exists());
+        assert_eq!(
+            std::fs::read_to_string(cache_directory.join("archive/mojang/versions/1.19.json"))
+                .unwrap(),
+            "{}"
+        );
+        assert!(!cache_directory
+            .join("archive/mojang/versions/1.19-missing.json")
+            .exists());
+        assert_eq!(
+            report.files_written,
+            vec!["archive/mojang/versions/1.19.json"]
+        );
+        assert_eq!(report.files_removed, vec!["mojang/versions/1.19.json"]);
+        std::fs::remove_dir_all(&cache_directory).unwrap();
+    }
+
+    #[test]
+    fn deletes_files_of_removed_versions() {
+        let (cache_directory, report) = apply_policy(RemovalPolicy::Delete);
+
+        assert!(!cache_directory.join("mojang/versions/1.19.json").exists());
+        assert!(cache_directory.join("mojang/versions").is_dir());
+        assert!(!cache_directory.join("archive").exists());
+        assert!(report.files_written.is_empty());
+        assert_eq!(report.files_removed, vec!["mojang/versions/1.19.json"]);
+        std::fs::remove_dir_all(&cache_directory).unwrap();
+    }
+
+    #[test]
+    fn records_added_updated_and_removed_versions() {
+        let previous = index(&[("1.18", 1), ("1.19", 1), ("1.7.10", 1), ("1.12.2", 1)]);
+        let current = index(&[("1.18", 1), ("1.19", 2), ("1.20", 1), ("1.19.4", 1)]);
+
+        let mut report = UpdateReport::new("mojang");
+        report.record_changes(&previous, &current);
+
+        assert_eq!(report.added, vec!["1.19.4", "1.20"]);
+        assert_eq!(report.updated, vec!["1.19"]);
+        assert_eq!(report.removed, vec!["1.12.2", "1.7.10"]);
+        assert!(report.has_changes());
+
+        let mut unchanged = UpdateReport::new("mojang");
+        unchanged.record_changes(&current, &current);
+        assert!(!unchanged.has_changes());
+    }
+}
@@ -0,0 +1,320 @@
+//! Merges the version files of a component stack into the single profile an instance is launched with, the way
+//! PolyMC does it.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::{
+    models::{
+        mojang::{MojangAssets, MojangLibrary, Platform},
+        polymc::{PolyMCLibrary, PolyMCVersionFile, MINECRAFT_UID},
+    },
+    resolver::ResolvedComponent,
+};
+
+/// The effective launch profile of a component stack.
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchProfile {
+    /// The components the profile was built from, as `{uid}/{version}`.
+    pub components: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minecraft_version: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub version_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub main_class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub applet_class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minecraft_arguments: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_index: Option<MojangAssets>,
+    pub traits: Vec<String>,
+    pub tweakers: Vec<String>,
+    pub jvm_arguments: Vec<String>,
+    pub libraries: Vec<PolyMCLibrary>,
+    pub native_libraries: Vec<PolyMCLibrary>,
+    pub maven_files: Vec<PolyMCLibrary>,
+    pub jar_mods: Vec<PolyMCLibrary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub main_jar: Option<PolyMCLibrary>,
+}
+
+impl LaunchProfile {
+    /// Applies version files in the order of the stack, which has to be sorted by `order`.
+    pub fn from_files<'a, I>(files: I) -> Self
+    where
+        I: IntoIterator<Item = &'a PolyMCVersionFile>,
+    {
+        let mut profile = Self::default();
+        for file in files {
+            profile.apply(file);
+        }
+
+        profile
+    }
+
+    /// Applies the components of a stack returned by the resolver.
+    pub fn from_stack(stack: &[ResolvedComponent]) -> Self {
+        Self::from_files(stack.iter().map(|component| &component.file))
+    }
+
+    /// Applies a version file on top of the profile.
+    ///
+    /// Values set by the file replace the ones of earlier files, lists of traits, tweakers, JVM arguments and jar mods
+    /// are appended to. Libraries replace an earlier library with the same group, artifact, classifier and platforms of
+    /// natives if their version is newer, otherwise they are appended. Rules aren't evaluated, every library is kept,
+    /// see [`LaunchProfile::for_platform`].
+    pub fn apply(&mut self, file: &PolyMCVersionFile) {
+        self.components
+            .push(format!("{}/{}", file.uid, file.version));
+        if file.uid == MINECRAFT_UID {
+            self.minecraft_version = Some(file.version.clone());
+        }
+        replace_if_set(&mut self.version_type, &file.version_file_type);
+        replace_if_set(&mut self.main_class, &file.main_class);
+        replace_if_set(&mut self.applet_class, &file.applet_class);
+        replace_if_set(&mut self.minecraft_arguments, &file.minecraft_arguments);
+        replace_if_set(&mut self.asset_index, &file.asset_index);
+        replace_if_set(&mut self.main_jar, &file.main_jar);
+
+        for added_trait in file.add_traits.iter().flatten() {
+            if !self.traits.contains(added_trait) {
+                self.traits.push(added_trait.clone());
+            }
+        }
+        for tweaker in file.add_tweakers.iter().flatten() {
+            if !self.tweakers.contains(tweaker) {
+                self.tweakers.push(tweaker.clone());
+            }
+        }
+        for argument in file.add_jvm_arguments.iter().flatten() {
+            if !self.jvm_arguments.contains(argument) {
+                self.jvm_arguments.push(argument.clone());
+            }
+        }
+        self.jar_mods
+            .extend(file.jar_mods.iter().flatten().cloned());
+        self.maven_files
+            .extend(file.maven_files.iter().flatten().cloned());
+
+        for library in file.libraries.iter().flatten() {
+            // natives are tracked separately, they share their name with the library they belong to
+            let libraries = if library.library.natives.is_some() {
+                &mut self.native_libraries
+            } else {
+                &mut self.libraries
+            };
+            apply_library(libraries, library);
+        }
+    }
+
+    /// Returns the profile with only the libraries the rules allow on a platform, and only the natives which have a
+    /// classifier for it, see [`MojangLibrary::native_classifier`](crate::models::mojang::MojangLibrary::native_classifier).
+    pub fn for_platform(&self, platform: &Platform) -> Self {
+        let allowed = |library: &&PolyMCLibrary| library.library.is_allowed(platform);
+        let mut profile = self.clone();
+        profile.libraries = self.libraries.iter().filter(allowed).cloned().collect();
+        profile.native_libraries = self
+            .native_libraries
+            .iter()
+            .filter(allowed)
+            .filter(|library| library.library.native_classifier(platform).is_some())
+            .cloned()
+            .collect();
+        profile.maven_files = self.maven_files.iter().filter(allowed).cloned().collect();
+
+        profile
+    }
+}
+
+fn replace_if_set<T>(value: &mut Option<T>, new_value: &Option<T>)
+where
+    T: Clone,
+{
+    if new_value.is_some() {
+        value.clone_from(new_value);
+    }
+}
+
+fn same_library(a: &MojangLibrary, b: &MojangLibrary) -> bool {
+    // natives for other platforms, like community builds for ARM, are kept next to the original ones
+    let platforms = |library: &MojangLibrary| {
+        library
+            .natives
+            .as_ref()
+            .map(|natives| natives.keys().cloned().collect::<BTreeSet<String>>())
+    };
+    a.name.group == b.name.group
+        && a.name.artifact == b.name.artifact
+        && a.name.classifier == b.name.classifier
+        && platforms(a) == platforms(b)
+}
+
+fn apply_library(libraries: &mut Vec<PolyMCLibrary>, library: &PolyMCLibrary) {
+    let existing = libraries
+        .iter_mut()
+        .find(|existing| same_library(&existing.library, &library.library));
+    match existing {
+        Some(existing) => {
+            if library.library.name.maven_version() > existing.library.name.maven_version() {
+                *existing = library.clone();
+            }
+        }
+        None => libraries.push(library.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::mojang::OSName;
+
+    fn version_file(value: serde_json::Value) -> PolyMCVersionFile {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Minecraft with LWJGL 3.2.2, a macOS-only library and natives, followed by an LWJGL update and a mod loader.
+    fn profile() -> LaunchProfile {
+        let minecraft = version_file(json!({
+            "formatVersion": 1,
+            "name": "Minecraft",
+            "uid": "net.minecraft",
+            "version": "1.18.2",
+            "mainClass": "net.minecraft.client.main.Main",
+            "+traits": ["XR:Initial"],
+            "+jvmArgs": ["-Xss1M"],
+            "libraries": [
+                { "name": "org.lwjgl:lwjgl:3.2.2" },
+                { "name": "org.lwjgl:lwjgl:3.2.2:natives-linux" },
+                {
+                    "name": "ca.weblite:java-objc-bridge:1.0.0",
+                    "rules": [{ "action": "allow", "os": { "name": "osx" } }]
+                },
+                {
+                    "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                    "natives": { "linux": "natives-linux", "windows": "natives-windows" }
+                }
+            ]
+        }));
+        let lwjgl = version_file(json!({
+            "formatVersion": 1,
+            "name": "LWJGL 3",
+            "uid": "org.lwjgl3",
+            "version": "3.3.1",
+            "+tweakers": ["org.example.Tweaker"],
+            "libraries": [
+                { "name": "org.lwjgl:lwjgl:3.3.1" },
+                {
+                    "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                    "natives": { "linux-arm64": "natives-linux-arm64" }
+                }
+            ]
+        }));
+        let loader = version_file(json!({
+            "formatVersion": 1,
+            "name": "Loader",
+            "uid": "org.example.loader",
+            "version": "1.0",
+            "mainClass": "org.example.Launch",
+            "+traits": ["XR:Initial", "FirstThreadOnMacOS"],
+            "+tweakers": ["org.example.Tweaker", "org.example.OtherTweaker"],
+            "+jvmArgs": ["-Xss1M", "-Dloader=true"],
+            "libraries": [{ "name": "org.lwjgl:lwjgl:3.2.0" }]
+        }));
+
+        LaunchProfile::from_files(&[minecraft, lwjgl, loader])
+    }
+
+    fn names(libraries: &[PolyMCLibrary]) -> Vec<String> {
+        libraries
+            .iter()
+            .map(|library| library.library.name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn newer_libraries_replace_older_ones() {
+        let profile = profile();
+
+        assert_eq!(
+            profile.components,
+            vec![
+                "net.minecraft/1.18.2",
+                "org.lwjgl3/3.3.1",
+                "org.example.loader/1.0"
+            ]
+        );
+        assert_eq!(profile.minecraft_version.as_deref(), Some("1.18.2"));
+        assert_eq!(profile.main_class.as_deref(), Some("org.example.Launch"));
+        // 3.3.1 replaces 3.2.2 in place, 3.2.0 is older and ignored, the classifier makes the natives a different
+        // library
+        assert_eq!(
+            names(&profile.libraries),
+            vec![
+                "org.lwjgl:lwjgl:3.3.1",
+                "org.lwjgl:lwjgl:3.2.2:natives-linux",
+                "ca.weblite:java-objc-bridge:1.0.0",
+            ]
+        );
+        // natives for other platforms are kept next to the original ones
+        assert_eq!(
+            names(&profile.native_libraries),
+            vec![
+                "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+            ]
+        );
+    }
+
+    #[test]
+    fn deduplicates_traits_tweakers_and_jvm_arguments() {
+        let profile = profile();
+
+        assert_eq!(profile.traits, vec!["XR:Initial", "FirstThreadOnMacOS"]);
+        assert_eq!(
+            profile.tweakers,
+            vec!["org.example.Tweaker", "org.example.OtherTweaker"]
+        );
+        assert_eq!(profile.jvm_arguments, vec!["-Xss1M", "-Dloader=true"]);
+    }
+
+    #[test]
+    fn filters_libraries_and_natives_for_platform() {
+        let profile = profile();
+
+        let linux = profile.for_platform(&Platform::new(OSName::Linux, "x86_64"));
+        assert_eq!(
+            names(&linux.libraries),
+            vec![
+                "org.lwjgl:lwjgl:3.3.1",
+                "org.lwjgl:lwjgl:3.2.2:natives-linux"
+            ]
+        );
+        assert_eq!(linux.native_libraries.len(), 1);
+        assert!(linux.native_libraries[0]
+            .library
+            .natives
+            .as_ref()
+            .unwrap()
+            .contains_key("linux"));
+
+        let linux_arm64 = profile.for_platform(&Platform::new(OSName::Linux, "aarch64"));
+        assert_eq!(names(&linux_arm64.libraries), vec!["org.lwjgl:lwjgl:3.3.1"]);
+        assert_eq!(linux_arm64.native_libraries.len(), 1);
+        assert!(linux_arm64.native_libraries[0]
+            .library
+            .natives
+            .as_ref()
+            .unwrap()
+            .contains_key("linux-arm64"));
+
+        let macos = profile.for_platform(&Platform::new(OSName::MacOS, "x86_64"));
+        assert!(names(&macos.libraries).contains(&"ca.weblite:java-objc-bridge:1.0.0".to_string()));
+        assert!(macos.native_libraries.is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+//! Python bindings for the models and the updaters, enabled by the `python` feature.
+//!
+//! Every model is exposed as a class with `parse`, `validate`, `to_json` and `to_dict`. The updaters are exposed
+//! as classes whose `update` method blocks until the update is done and returns the report as a dict.
+
+// triggered by the code generated by #[pymethods]
+#![allow(clippy::useless_conversion)]
+
+use std::path::PathBuf;
+
+use pyo3::{
+    exceptions::{PyOSError, PyRuntimeError, PyValueError},
+    prelude::*,
+};
+use tokio::runtime::Runtime;
+
+use crate::{
+    models::{
+        fabric::{FabricInstallerDataV1, FabricJarInfo},
+        forge::{
+            DerivedForgeIndex, ForgeInstallerProfile, ForgeInstallerProfileV1_5,
+            ForgeInstallerProfileV2, ForgeLegacyInfoList, InstallerInfo,
+        },
+        liteloader::LiteloaderIndex,
+        mojang::{MojangIndex, MojangVersionFile},
+        polymc::{
+            PolyMCPackageIndex, PolyMCSharedPackageData, PolyMCVersionFile, PolyMCVersionIndex,
+        },
+        report::UpdateReport,
+    },
+    FabricUpdater, ForgeUpdater, LiteloaderUpdater, MojangUpdater, RemovalPolicy,
+};
+
+/// Converts a serializable value to Python objects by going through the `json` module.
+fn to_python<T>(py: Python<'_>, value: &T) -> PyResult<PyObject>
+where
+    T: serde::Serialize,
+{
+    let json = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(py
+        .import_bound("json")?
+        .call_method1("loads", (json,))?
+        .unbind())
+}
+
+/// Converts Python objects to JSON by going through the `json` module.
+fn from_python(py: Python<'_>, value: &Bound<'_, PyAny>) -> PyResult<String> {
+    py.import_bound("json")?
+        .call_method1("dumps", (value,))?
+        .extract()
+}
+
+/// Defines a Python class wrapping a model, with optional additional methods.
+macro_rules! model_class {
+    ($class:ident, $name:literal, $model:ty $(, { $($methods:tt)* })?) => {
+        #[pyclass(name = $name, module = "morphosis_meta")]
+        #[derive(Clone)]
+        pub struct $class {
+            inner: $model,
+        }
+
+        #[pymethods]
+        impl $class {
+            /// Parses a JSON document, raising a `ValueError` if it doesn't match the model.
+            #[staticmethod]
+            fn parse(json: &str) -> PyResult<Self> {
+                serde_json::from_str(json)
+                    .map(|inner| Self { inner })
+                    .map_err(|e| PyValueError::new_err(e.to_string()))
+            }
+
+            /// Parses a dict, as returned by `json.load`, raising a `ValueError` if it doesn't match the model.
+            #[staticmethod]
+            fn from_dict(py: Python<'_>, value: &Bound<'_, PyAny>) -> PyResult<Self> {
+                Self::parse(&from_python(py, value)?)
+            }
+
+            /// Returns `None` if a JSON document matches the model, or a description of the first mismatch.
+            #[staticmethod]
+            fn validate(json: &str) -> Option<String> {
+                serde_json::from_str::<$model>(json)
+                    .err()
+                    .map(|e| e.to_string())
+            }
+
+            /// Serializes the model to JSON.
+            #[pyo3(signature = (pretty = false))]
+            fn to_json(&self, pretty: bool) -> PyResult<String> {
+                if pretty {
+                    serde_json::to_string_pretty(&self.inner)
+                } else {
+                    serde_json::to_string(&self.inner)
+                }
+                .map_err(|e| PyValueError::new_err(e.to_string()))
+            }
+
+            /// Serializes the model to a dict.
+            fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+                to_python(py, &self.inner)
+            }
+
+            fn __repr__(&self) -> String {
+                format!("<{}>", $name)
+            }
+
+            $($($methods)*)?
+        }
+    };
+}
+
+model_class!(PyMojangIndex, "MojangIndex", MojangIndex<'static>);
+model_class!(
+    PyMojangVersionFile,
+    "MojangVersionFile",
+    MojangVersionFile,
+    {
+        /// Converts the version file to a PolyMC version file of the given component.
+        fn to_polymc(
+            &self,
+            name: String,
+            uid: String,
+            version: String,
+        ) -> PyResult<PyPolyMCVersionFile> {
+            PolyMCVersionFile::from_mojang_file(&self.inner, name, uid, version)
+                .map(|inner| PyPolyMCVersionFile { inner })
+                .map_err(|e| PyValueError::new_err(e.to_string()))
+        }
+    }
+);
+model_class!(PyDerivedForgeIndex, "DerivedForgeIndex", DerivedForgeIndex);
+model_class!(PyInstallerInfo, "InstallerInfo", InstallerInfo);
+model_class!(
+    PyForgeInstallerProfile,
+    "ForgeInstallerProfile",
+    ForgeInstallerProfile
+);
+model_class!(
+    PyForgeInstallerProfileV1_5,
+    "ForgeInstallerProfileV1_5",
+    ForgeInstallerProfileV1_5
+);
+model_class!(
+    PyForgeInstallerProfileV2,
+    "ForgeInstallerProfileV2",
+    ForgeInstallerProfileV2
+);
+model_class!(
+    PyForgeLegacyInfoList,
+    "ForgeLegacyInfoList",
+    ForgeLegacyInfoList
+);
+model_class!(
+    PyFabricInstallerDataV1,
+    "FabricInstallerDataV1",
+    FabricInstallerDataV1
+);
+model_class!(PyFabricJarInfo, "FabricJarInfo", FabricJarInfo);
+model_class!(PyLiteloaderIndex, "LiteloaderIndex", LiteloaderIndex);
+model_class!(PyPolyMCVersionFile, "PolyMCVersionFile", PolyMCVersionFile);
+model_class!(
+    PyPolyMCSharedPackageData,
+    "PolyMCSharedPackageData",
+    PolyMCSharedPackageData
+);
+model_class!(
+    PyPolyMCVersionIndex,
+    "PolyMCVersionIndex",
+    PolyMCVersionIndex
+);
+model_class!(
+    PyPolyMCPackageIndex,
+    "PolyMCPackageIndex",
+    PolyMCPackageIndex
+);
+
+/// Defines a Python class wrapping an updater, together with the runtime its updates run on.
+macro_rules! updater_class {
+    ($class:ident, $name:literal, $updater:ty) => {
+        #[pyclass(name = $name, module = "morphosis_meta")]
+        pub struct $class {
+            runtime: Runtime,
+            updater: $updater,
+        }
+
+        #[pymethods]
+        impl $class {
+            #[new]
+            #[pyo3(signature = (cache_directory, removal_policy = "keep", offline = false))]
+            fn new(
+                cache_directory: PathBuf,
+                removal_policy: &str,
+                offline: bool,
+            ) -> PyResult<Self> {
+                let removal_policy: RemovalPolicy =
+                    removal_policy
+                        .parse()
+                        .map_err(|e: crate::models::report::ReportError| {
+                            PyValueError::new_err(e.to_string())
+                        })?;
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+
+                Ok(Self {
+                    runtime,
+                    updater: <$updater>::new(cache_directory)
+                        .with_removal_policy(removal_policy)
+                        .with_offline_mode(offline),
+                })
+            }
+
+            /// Updates the cache, blocking until it's done, and returns the update report as a dict.
+            fn update(&self, py: Python<'_>) -> PyResult<PyObject> {
+                let report: UpdateReport = py
+                    .allow_threads(|| self.runtime.block_on(self.updater.generate_meta_cache()))
+                    .map_err(|e| PyOSError::new_err(e.to_string()))?;
+                to_python(py, &report)
+            }
+
+            fn __repr__(&self) -> String {
+                format!("<{}>", $name)
+            }
+        }
+    };
+}
+
+updater_class!(PyMojangUpdater, "MojangUpdater", MojangUpdater);
+updater_class!(PyForgeUpdater, "ForgeUpdater", ForgeUpdater);
+updater_class!(PyFabricUpdater, "FabricUpdater", FabricUpdater);
+updater_class!(PyLiteloaderUpdater, "LiteloaderUpdater", LiteloaderUpdater);
+
+#[pymodule]
+fn morphosis_meta(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyMojangIndex>()?;
+    module.add_class::<PyMojangVersionFile>()?;
+    module.add_class::<PyDerivedForgeIndex>()?;
+    module.add_class::<PyInstallerInfo>()?;
+    module.add_class::<PyForgeInstallerProfile>()?;
+    module.add_class::<PyForgeInstallerProfileV1_5>()?;
+    module.add_class::<PyForgeInstallerProfileV2>()?;
+    module.add_class::<PyForgeLegacyInfoList>()?;
+    module.add_class::<PyFabricInstallerDataV1>()?;
+    module.add_class::<PyFabricJarInfo>()?;
+    module.add_class::<PyLiteloaderIndex>()?;
+    module.add_class::<PyPolyMCVersionFile>()?;
+    module.add_class::<PyPolyMCSharedPackageData>()?;
+    module.add_class::<PyPolyMCVersionIndex>()?;
+    module.add_class::<PyPolyMCPackageIndex>()?;
+    module.add_class::<PyMojangUpdater>()?;
+    module.add_class::<PyForgeUpdater>()?;
+    module.add_class::<PyFabricUpdater>()?;
+    module.add_class::<PyLiteloaderUpdater>()?;
+    Ok(())
+}
@@ -0,0 +1,545 @@
+//! Resolves the components requested for an instance to a stack of concrete versions, the way PolyMC does it.
+//!
+//! Versions are chosen in this order: the requested version, a version pinned with `equal` by another component,
+//! the version `suggests`ted by the requiring component, the newest recommended version and the newest version.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::models::polymc::{DependencyEntry, PolyMCVersionFile, PolyMCVersionIndex};
+
+custom_error! {
+    /// Errors that can occur when resolving a component stack.
+    pub ResolverError
+        InvalidFile { path: String, reason: String } = "Failed to read {path}: {reason}",
+        InvalidRequest { request: String } = "Invalid component '{request}', expected uid or uid=version",
+        UnknownPackage { uid: String } = "Unknown package {uid}",
+        UnknownVersion { uid: String, version: String } = "{uid} has no version {version}",
+        NoVersions { uid: String } = "{uid} has no versions",
+        VersionConflict { uid: String, version: String, pinned_by: String, required: String, required_by: String } = "{required_by} requires {uid} {required}, but {pinned_by} requires {version}",
+        Conflict { uid: String, version: String, conflicting: String } = "{uid} {version} conflicts with {conflicting}",
+}
+
+/// A component requested for an instance, with its version if one was chosen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentRequest {
+    pub uid: String,
+    pub version: Option<String>,
+}
+
+impl ComponentRequest {
+    pub fn new<U>(uid: U, version: Option<String>) -> Self
+    where
+        U: Into<String>,
+    {
+        Self {
+            uid: uid.into(),
+            version,
+        }
+    }
+}
+
+impl FromStr for ComponentRequest {
+    type Err = ResolverError;
+
+    /// Parses `uid` or `uid=version`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (uid, version) = match s.split_once('=') {
+            Some((uid, version)) => (uid, Some(version.to_string())),
+            None => (s, None),
+        };
+        if uid.is_empty() || version.as_deref() == Some("") {
+            return Err(ResolverError::InvalidRequest {
+                request: s.to_string(),
+            });
+        }
+
+        Ok(Self::new(uid, version))
+    }
+}
+
+/// A component of a resolved stack.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedComponent {
+    pub uid: String,
+    pub version: String,
+    /// Position of the component in the stack, lower orders are applied first.
+    pub order: i32,
+    /// Set if the component wasn't requested, but is required by another component.
+    pub dependency_only: bool,
+    #[serde(skip)]
+    pub file: PolyMCVersionFile,
+}
+
+/// Resolves component stacks from PolyMC metadata in a directory.
+pub struct Resolver {
+    meta_directory: PathBuf,
+    version_indexes: HashMap<String, PolyMCVersionIndex>,
+}
+
+impl Resolver {
+    pub fn new<P>(meta_directory: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            meta_directory: meta_directory.as_ref().to_path_buf(),
+            version_indexes: HashMap::new(),
+        }
+    }
+
+    /// Resolves the requested components and everything they require, and returns the stack sorted by `order`.
+    pub fn resolve(
+        &mut self,
+        requests: &[ComponentRequest],
+    ) -> Result<Vec<ResolvedComponent>, ResolverError> {
+        // uid -> (version, the component which pinned it)
+        let mut pins: HashMap<String, (String, String)> = HashMap::new();
+        for request in requests {
+            if let Some(version) = &request.version {
+                let pin = (version.clone(), "the request".to_string());
+                if let Some((pinned, _)) = pins.insert(request.uid.clone(), pin) {
+                    if &pinned != version {
+                        return Err(ResolverError::VersionConflict {
+                            uid: request.uid.clone(),
+                            version: pinned,
+                            pinned_by: "the request".to_string(),
+                            required: version.clone(),
+                            required_by: "the request".to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        // an `equal` requirement found late may change a version chosen before, which starts the resolution over
+        let mut components = loop {
+            if let Some(components) = self.resolve_with_pins(requests, &mut pins)? {
+                break components;
+            }
+        };
+
+        for component in &components {
+            for conflict in component.file.conflicts.iter().flatten() {
+                let conflicting = components.iter().find(|other| {
+                    other.uid == conflict.uid
+                        && conflict
+                            .equal
+                            .as_ref()
+                            .map(|equal| equal == &other.version)
+                            .unwrap_or(true)
+                });
+                if let Some(conflicting) = conflicting {
+                    return Err(ResolverError::Conflict {
+                        uid: component.uid.clone(),
+                        version: component.version.clone(),
+                        conflicting: format!("{} {}", conflicting.uid, conflicting.version),
+                    });
+                }
+            }
+        }
+
+        // a stable sort, so components with the same order stay in the order they were added
+        components.sort_by_key(|component| component.order);
+        Ok(components)
+    }
+
+    /// Resolves the stack once, returning `None` if a new pin changed a version chosen before.
+    fn resolve_with_pins(
+        &mut self,
+        requests: &[ComponentRequest],
+        pins: &mut HashMap<String, (String, String)>,
+    ) -> Result<Option<Vec<ResolvedComponent>>, ResolverError> {
+        let mut components: Vec<ResolvedComponent> = Vec::new();
+        for request in requests {
+            if components
+                .iter()
+                .any(|component| component.uid == request.uid)
+            {
+                continue;
+            }
+            let version = match pins.get(&request.uid) {
+                Some((version, _)) => version.clone(),
+                None => self.default_version(&request.uid, None)?,
+            };
+            components.push(self.load_component(&request.uid, &version, false)?);
+        }
+
+        let mut i = 0;
+        while i < components.len() {
+            let required_by = format!("{} {}", components[i].uid, components[i].version);
+            let requirements = components[i].file.requires.clone().unwrap_or_default();
+            for requirement in requirements {
+                if let Some(equal) = &requirement.equal {
+                    match pins.get(&requirement.uid) {
+                        Some((pinned, pinned_by)) if pinned != equal => {
+                            return Err(ResolverError::VersionConflict {
+                                uid: requirement.uid.clone(),
+                                version: pinned.clone(),
+                                pinned_by: pinned_by.clone(),
+                                required: equal.clone(),
+                                required_by,
+                            });
+                        }
+                        Some(_) => {}
+                        None => {
+                            pins.insert(
+                                requirement.uid.clone(),
+                                (equal.clone(), required_by.clone()),
+                            );
+                            let chosen_before = components.iter().any(|component| {
+                                component.uid == requirement.uid && &component.version != equal
+                            });
+                            if chosen_before {
+                                return Ok(None);
+                            }
+                        }
+                    }
+                }
+
+                if !components
+                    .iter()
+                    .any(|component| component.uid == requirement.uid)
+                {
+                    let version = self.required_version(&requirement, pins)?;
+                    components.push(self.load_component(&requirement.uid, &version, true)?);
+                }
+            }
+            i += 1;
+        }
+
+        Ok(Some(components))
+    }
+
+    fn required_version(
+        &mut self,
+        requirement: &DependencyEntry,
+        pins: &HashMap<String, (String, String)>,
+    ) -> Result<String, ResolverError> {
+        match pins.get(&requirement.uid) {
+            Some((version, _)) => Ok(version.clone()),
+            None => self.default_version(&requirement.uid, requirement.suggests.as_deref()),
+        }
+    }
+
+    /// Chooses the suggested version if it exists, otherwise the newest recommended or the newest version.
+    fn default_version(
+        &mut self,
+        uid: &str,
+        suggests: Option<&str>,
+    ) -> Result<String, ResolverError> {
+        let version_index = self.version_index(uid)?;
+        if let Some(suggests) = suggests {
+            if version_index
+                .versions
+                .iter()
+                .any(|version| version.version == suggests)
+            {
+                return Ok(suggests.to_string());
+            }
+        }
+
+        // the versions are sorted newest first
+        version_index
+            .versions
+            .iter()
+            .find(|version| version.recommended == Some(true))
+            .or_else(|| version_index.versions.first())
+            .map(|version| version.version.clone())
+            .ok_or_else(|| ResolverError::NoVersions {
+                uid: uid.to_string(),
+            })
+    }
+
+    fn load_component(
+        &mut self,
+        uid: &str,
+        version: &str,
+        dependency_only: bool,
+    ) -> Result<ResolvedComponent, ResolverError> {
+        let known = self
+            .version_index(uid)?
+            .versions
+            .iter()
+            .any(|entry| entry.version == version);
+        if !known {
+            return Err(ResolverError::UnknownVersion {
+                uid: uid.to_string(),
+                version: version.to_string(),
+            });
+        }
+
+        let file: PolyMCVersionFile =
+            self.read_json(&Path::new(uid).join(format!("{}.json", version)))?;
+        Ok(ResolvedComponent {
+            uid: uid.to_string(),
+            version: version.to_string(),
+            order: file.order.unwrap_or_default(),
+            dependency_only,
+            file,
+        })
+    }
+
+    /// Returns the version index of a package, reading it on first use.
+    fn version_index(&mut self, uid: &str) -> Result<&PolyMCVersionIndex, ResolverError> {
+        if !self.version_indexes.contains_key(uid) {
+            let path = Path::new(uid).join("index.json");
+            if !self.meta_directory.join(&path).is_file() {
+                return Err(ResolverError::UnknownPackage {
+                    uid: uid.to_string(),
+                });
+            }
+            let version_index = self.read_json(&path)?;
+            self.version_indexes.insert(uid.to_string(), version_index);
+        }
+
+        Ok(&self.version_indexes[uid])
+    }
+
+    fn read_json<T>(&self, path: &Path) -> Result<T, ResolverError>
+    where
+        T: DeserializeOwned,
+    {
+        let path = self.meta_directory.join(path);
+        let invalid_file = |reason: String| ResolverError::InvalidFile {
+            path: path.display().to_string(),
+            reason,
+        };
+        let file = std::fs::File::open(&path).map_err(|e| invalid_file(e.to_string()))?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| invalid_file(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// A package of the test metadata: its uid and `(version, recommended, extra fields of the version file)`,
+    /// newest first.
+    type Package<'a> = (&'a str, Vec<(&'a str, bool, Value)>);
+
+    fn write_json(path: &Path, value: &Value) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, serde_json::to_vec_pretty(value).unwrap()).unwrap();
+    }
+
+    fn write_metadata(name: &str, packages: &[Package]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "morphosis-meta-resolve-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        for (uid, versions) in packages {
+            let mut entries = Vec::new();
+            for (version, recommended, fields) in versions {
+                let mut file = json!({
+                    "formatVersion": 1,
+                    "name": uid,
+                    "uid": uid,
+                    "version": version
+                });
+                file.as_object_mut()
+                    .unwrap()
+                    .extend(fields.as_object().unwrap().clone());
+                write_json(
+                    &directory.join(uid).join(format!("{}.json", version)),
+                    &file,
+                );
+                entries.push(json!({
+                    "version": version,
+                    "recommended": recommended,
+                    "sha256": ""
+                }));
+            }
+            write_json(
+                &directory.join(uid).join("index.json"),
+                &json!({ "formatVersion": 1, "name": uid, "uid": uid, "versions": entries }),
+            );
+        }
+
+        directory
+    }
+
+    /// Minecraft 1.19 and 1.18.2 (recommended) with their LWJGL versions, and Fabric, whose intermediary pins
+    /// Minecraft with `equal`.
+    fn packages() -> Vec<Package<'static>> {
+        vec![
+            (
+                "net.minecraft",
+                vec![
+                    (
+                        "1.19",
+                        false,
+                        json!({ "order": -2, "requires": [{ "uid": "org.lwjgl3", "suggests": "3.3.1" }] }),
+                    ),
+                    (
+                        "1.18.2",
+                        true,
+                        json!({ "order": -2, "requires": [{ "uid": "org.lwjgl3", "suggests": "3.2.2" }] }),
+                    ),
+                ],
+            ),
+            (
+                "org.lwjgl3",
+                vec![
+                    ("3.3.1", false, json!({ "order": -1 })),
+                    ("3.2.2", false, json!({ "order": -1 })),
+                ],
+            ),
+            (
+                "net.fabricmc.intermediary",
+                vec![
+                    (
+                        "1.19",
+                        false,
+                        json!({ "order": 11, "requires": [{ "uid": "net.minecraft", "equal": "1.19" }] }),
+                    ),
+                    (
+                        "1.18.2",
+                        false,
+                        json!({ "order": 11, "requires": [{ "uid": "net.minecraft", "equal": "1.18.2" }] }),
+                    ),
+                ],
+            ),
+            (
+                "net.fabricmc.fabric-loader",
+                vec![(
+                    "0.14.8",
+                    false,
+                    json!({ "order": 10, "requires": [{ "uid": "net.fabricmc.intermediary" }] }),
+                )],
+            ),
+            (
+                "com.mumfrey.liteloader",
+                vec![(
+                    "1.12.2",
+                    false,
+                    json!({ "order": 10, "conflicts": [{ "uid": "net.fabricmc.fabric-loader" }] }),
+                )],
+            ),
+        ]
+    }
+
+    fn resolve(
+        name: &str,
+        requests: &[&str],
+    ) -> Result<Vec<(String, String, bool)>, ResolverError> {
+        let directory = write_metadata(name, &packages());
+        let requests: Vec<ComponentRequest> = requests
+            .iter()
+            .map(|request| request.parse().unwrap())
+            .collect();
+        let result = Resolver::new(&directory).resolve(&requests);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        result.map(|components| {
+            components
+                .into_iter()
+                .map(|component| (component.uid, component.version, component.dependency_only))
+                .collect()
+        })
+    }
+
+    fn component(uid: &str, version: &str, dependency_only: bool) -> (String, String, bool) {
+        (uid.to_string(), version.to_string(), dependency_only)
+    }
+
+    #[test]
+    fn parses_requests() {
+        assert_eq!(
+            "net.minecraft=1.19".parse::<ComponentRequest>().unwrap(),
+            ComponentRequest::new("net.minecraft", Some("1.19".to_string()))
+        );
+        assert_eq!(
+            "net.minecraft".parse::<ComponentRequest>().unwrap(),
+            ComponentRequest::new("net.minecraft", None)
+        );
+        assert!("net.minecraft=".parse::<ComponentRequest>().is_err());
+    }
+
+    #[test]
+    fn resolves_requested_version() {
+        assert_eq!(
+            resolve("requested", &["net.minecraft=1.19"]).unwrap(),
+            vec![
+                component("net.minecraft", "1.19", false),
+                component("org.lwjgl3", "3.3.1", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_recommended_and_newest_versions() {
+        assert_eq!(
+            resolve("recommended", &["net.minecraft"]).unwrap(),
+            vec![
+                component("net.minecraft", "1.18.2", false),
+                component("org.lwjgl3", "3.2.2", true),
+            ]
+        );
+        // org.lwjgl3 has no recommended version
+        assert_eq!(
+            resolve("newest", &["org.lwjgl3"]).unwrap(),
+            vec![component("org.lwjgl3", "3.3.1", false)]
+        );
+    }
+
+    #[test]
+    fn restarts_on_late_equal_pin() {
+        // net.minecraft starts at the recommended 1.18.2, until the newest intermediary pins 1.19
+        assert_eq!(
+            resolve("restart", &["net.minecraft", "net.fabricmc.fabric-loader"]).unwrap(),
+            vec![
+                component("net.minecraft", "1.19", false),
+                component("org.lwjgl3", "3.3.1", true),
+                component("net.fabricmc.fabric-loader", "0.14.8", false),
+                component("net.fabricmc.intermediary", "1.19", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn fails_on_version_conflict() {
+        let error = resolve(
+            "version-conflict",
+            &["net.minecraft=1.18.2", "net.fabricmc.intermediary=1.19"],
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            error,
+            ResolverError::VersionConflict { ref uid, ref version, ref required, .. }
+                if uid == "net.minecraft" && version == "1.18.2" && required == "1.19"
+        ));
+    }
+
+    #[test]
+    fn fails_on_conflict() {
+        let error = resolve(
+            "conflict",
+            &[
+                "net.minecraft",
+                "com.mumfrey.liteloader",
+                "net.fabricmc.fabric-loader",
+            ],
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            error,
+            ResolverError::Conflict { ref uid, ref conflicting, .. }
+                if uid == "com.mumfrey.liteloader" && conflicting == "net.fabricmc.fabric-loader 0.14.8"
+        ));
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "polymc")]
+pub mod polymc;
+
 /// Validation module for serializing and deserializing Mojang format versions.
 pub mod mojang_version_validation {
     use serde::Deserialize;
@@ -18,11 +21,9 @@ pub mod mojang_version_validation {
                     "mojang format version {} is not supported, max supported version is {}",
                     v, *MAX_MOJANG_SUPPORTED_VERSION
                 )));
-            } else {
-                return Ok(Some(v));
             }
         }
-        Ok(None)
+        Ok(v)
     }
 
     /// Serializes the Mojang format version.
@@ -34,10 +35,10 @@ pub mod mojang_version_validation {
     {
         if let Some(v) = version {
             if v > &*MAX_MOJANG_SUPPORTED_VERSION {
-                return Err(serde::ser::Error::custom(format!(
+                Err(serde::ser::Error::custom(format!(
                     "mojang format version {} is not supported, max supported version is {}",
                     v, *MAX_MOJANG_SUPPORTED_VERSION
-                )));
+                )))
             } else {
                 serializer.serialize_i32(*v)
             }
@@ -45,6 +46,16 @@ pub mod mojang_version_validation {
             serializer.serialize_none()
         }
     }
+
+    /// Returns the JSON Schema of the Mojang format version, limited to the supported versions.
+    #[cfg(feature = "schemars")]
+    pub fn schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": ["integer", "null"],
+            "format": "int32",
+            "maximum": *MAX_MOJANG_SUPPORTED_VERSION,
+        })
+    }
 }
 
 /// Validation module for serializing and deserializing PolyMC format versions.
@@ -62,10 +73,10 @@ pub mod polymc_version_validation {
     {
         let v = u8::deserialize(deserializer)?;
         if v > *CURRENT_POLYMC_FORMAT_VERSION {
-            return Err(serde::de::Error::custom(format!(
+            Err(serde::de::Error::custom(format!(
                 "polymc format version {} is not supported, max supported version is {}",
                 v, *CURRENT_POLYMC_FORMAT_VERSION
-            )));
+            )))
         } else {
             Ok(v)
         }
@@ -79,12 +90,23 @@ pub mod polymc_version_validation {
         S: serde::Serializer,
     {
         if version > &*CURRENT_POLYMC_FORMAT_VERSION {
-            return Err(serde::ser::Error::custom(format!(
+            Err(serde::ser::Error::custom(format!(
                 "polymc format version {} is not supported, max supported version is {}",
                 version, *CURRENT_POLYMC_FORMAT_VERSION
-            )));
+            )))
         } else {
             serializer.serialize_u8(*version)
         }
     }
+
+    /// Returns the JSON Schema of the PolyMC format version, limited to the supported versions.
+    #[cfg(feature = "schemars")]
+    pub fn schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "integer",
+            "format": "uint8",
+            "minimum": 0,
+            "maximum": *CURRENT_POLYMC_FORMAT_VERSION,
+        })
+    }
 }
@@ -0,0 +1,521 @@
+//! Semantic checks of PolyMC metadata, beyond what parsing the files with their models checks.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::models::polymc::{
+    PolyMCLibrary, PolyMCPackageIndex, PolyMCSharedPackageData, PolyMCVersionFile,
+    PolyMCVersionIndex, MINECRAFT_UID,
+};
+
+/// How bad a diagnostic is.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The launcher can't use the metadata.
+    Error,
+    /// The metadata works, but probably not as intended.
+    Warning,
+}
+
+/// What a diagnostic is about.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticKind {
+    /// The file can't be read or doesn't match its model.
+    InvalidFile,
+    /// The SHA-256 hash of the file isn't the one listed in its index.
+    Sha256Mismatch,
+    /// `uid` isn't the package the file is stored in.
+    UidMismatch,
+    /// `version` isn't the version the file is stored as.
+    VersionMismatch,
+    /// A required package isn't in the package index.
+    UnknownRequirement,
+    /// The `equal` version of a requirement isn't in the version index of the required package.
+    UnknownRequiredVersion,
+    /// A library has neither a `url` nor `downloads`, so it's downloaded from Mojang's library repository.
+    LibraryWithoutSource,
+    /// A Minecraft version has no `mainJar`.
+    MissingMainJar,
+}
+
+/// A problem found in a file of the metadata.
+#[derive(Serialize, Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    /// The file, relative to the metadata directory.
+    pub file: PathBuf,
+    /// The part of the file, e.g. `requires[0]`, unless the diagnostic is about the whole file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error<P, M>(kind: DiagnosticKind, file: P, location: Option<String>, message: M) -> Self
+    where
+        P: AsRef<Path>,
+        M: Into<String>,
+    {
+        Self {
+            severity: Severity::Error,
+            kind,
+            file: file.as_ref().to_path_buf(),
+            location,
+            message: message.into(),
+        }
+    }
+
+    fn warning<P, M>(kind: DiagnosticKind, file: P, location: Option<String>, message: M) -> Self
+    where
+        P: AsRef<Path>,
+        M: Into<String>,
+    {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(kind, file, location, message)
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(location) = &self.location {
+            write!(f, " ({})", location)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Checks a version file on its own, given the package and version it's stored as.
+pub fn validate_version_file(
+    file: &PolyMCVersionFile,
+    uid: &str,
+    version: &str,
+) -> Vec<Diagnostic> {
+    let path = version_file_path(uid, version);
+    let mut diagnostics = Vec::new();
+    if file.uid != uid {
+        diagnostics.push(Diagnostic::error(
+            DiagnosticKind::UidMismatch,
+            &path,
+            Some("uid".to_string()),
+            format!("uid is {}, but the file belongs to {}", file.uid, uid),
+        ));
+    }
+    if file.version != version {
+        diagnostics.push(Diagnostic::error(
+            DiagnosticKind::VersionMismatch,
+            &path,
+            Some("version".to_string()),
+            format!(
+                "version is {}, but the file is stored as {}",
+                file.version, version
+            ),
+        ));
+    }
+    if uid == MINECRAFT_UID && file.main_jar.is_none() {
+        diagnostics.push(Diagnostic::error(
+            DiagnosticKind::MissingMainJar,
+            &path,
+            None,
+            "Minecraft versions need a mainJar",
+        ));
+    }
+
+    let library_lists = [
+        ("libraries", &file.libraries),
+        ("mavenFiles", &file.maven_files),
+        ("jarMods", &file.jar_mods),
+    ];
+    for (field, libraries) in library_lists {
+        for (i, library) in libraries.iter().flatten().enumerate() {
+            check_library_source(
+                library,
+                &path,
+                format!("{}[{}]", field, i),
+                &mut diagnostics,
+            );
+        }
+    }
+    if let Some(main_jar) = &file.main_jar {
+        check_library_source(main_jar, &path, "mainJar".to_string(), &mut diagnostics);
+    }
+
+    diagnostics
+}
+
+/// Checks the metadata in a directory: the files listed by the indexes, their hashes and the requirements between
+/// packages.
+pub fn validate_directory(directory: &Path) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let package_index_path = Path::new("index.json");
+    let package_index = match read_json::<PolyMCPackageIndex>(directory, package_index_path) {
+        Ok((package_index, _)) => package_index,
+        Err(diagnostic) => return vec![diagnostic],
+    };
+
+    // every version index is read first, requirements may point to any package
+    let mut version_indexes = HashMap::new();
+    for package in &package_index.packages {
+        let version_index_path = Path::new(&package.uid).join("index.json");
+        match read_json::<PolyMCVersionIndex>(directory, &version_index_path) {
+            Ok((version_index, sha256)) => {
+                check_sha256(
+                    &version_index_path,
+                    &sha256,
+                    &package.sha256,
+                    &mut diagnostics,
+                );
+                check_uid(
+                    &version_index_path,
+                    &version_index.uid,
+                    &package.uid,
+                    &mut diagnostics,
+                );
+                version_indexes.insert(package.uid.clone(), version_index);
+            }
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+
+        let package_path = Path::new(&package.uid).join("package.json");
+        match read_json::<PolyMCSharedPackageData>(directory, &package_path) {
+            Ok((package_data, _)) => check_uid(
+                &package_path,
+                &package_data.uid,
+                &package.uid,
+                &mut diagnostics,
+            ),
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    }
+    let versions_by_uid: HashMap<&str, HashSet<&str>> = version_indexes
+        .iter()
+        .map(|(uid, version_index)| {
+            let versions = version_index
+                .versions
+                .iter()
+                .map(|version| version.version.as_str())
+                .collect();
+            (uid.as_str(), versions)
+        })
+        .collect();
+
+    for package in &package_index.packages {
+        let version_index = match version_indexes.get(&package.uid) {
+            Some(version_index) => version_index,
+            None => continue,
+        };
+        for entry in &version_index.versions {
+            let path = version_file_path(&package.uid, &entry.version);
+            let (file, sha256) = match read_json::<PolyMCVersionFile>(directory, &path) {
+                Ok(file) => file,
+                Err(diagnostic) => {
+                    diagnostics.push(diagnostic);
+                    continue;
+                }
+            };
+            check_sha256(&path, &sha256, &entry.sha256, &mut diagnostics);
+            diagnostics.extend(validate_version_file(&file, &package.uid, &entry.version));
+
+            for (i, requirement) in file.requires.iter().flatten().enumerate() {
+                let location = Some(format!("requires[{}]", i));
+                match versions_by_uid.get(requirement.uid.as_str()) {
+                    None => diagnostics.push(Diagnostic::error(
+                        DiagnosticKind::UnknownRequirement,
+                        &path,
+                        location,
+                        format!("{} isn't in the package index", requirement.uid),
+                    )),
+                    Some(versions) => {
+                        if let Some(equal) = &requirement.equal {
+                            if !versions.contains(equal.as_str()) {
+                                diagnostics.push(Diagnostic::error(
+                                    DiagnosticKind::UnknownRequiredVersion,
+                                    &path,
+                                    location,
+                                    format!("{} has no version {}", requirement.uid, equal),
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+fn version_file_path(uid: &str, version: &str) -> PathBuf {
+    Path::new(uid).join(format!("{}.json", version))
+}
+
+/// Reads a file of the metadata directory and returns it together with its SHA-256 hash.
+fn read_json<T>(directory: &Path, path: &Path) -> Result<(T, String), Diagnostic>
+where
+    T: DeserializeOwned,
+{
+    let invalid_file =
+        |message: String| Diagnostic::error(DiagnosticKind::InvalidFile, path, None, message);
+    let contents = std::fs::read(directory.join(path)).map_err(|e| invalid_file(e.to_string()))?;
+    let value = serde_json::from_slice(&contents).map_err(|e| invalid_file(e.to_string()))?;
+    let sha256 = data_encoding::HEXLOWER
+        .encode(ring::digest::digest(&ring::digest::SHA256, &contents).as_ref());
+
+    Ok((value, sha256))
+}
+
+fn check_sha256(path: &Path, sha256: &str, expected: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if sha256 != expected {
+        diagnostics.push(Diagnostic::error(
+            DiagnosticKind::Sha256Mismatch,
+            path,
+            None,
+            format!("SHA-256 is {}, but the index lists {}", sha256, expected),
+        ));
+    }
+}
+
+fn check_uid(path: &Path, uid: &str, expected: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if uid != expected {
+        diagnostics.push(Diagnostic::error(
+            DiagnosticKind::UidMismatch,
+            path,
+            Some("uid".to_string()),
+            format!("uid is {}, but the file belongs to {}", uid, expected),
+        ));
+    }
+}
+
+fn check_library_source(
+    library: &PolyMCLibrary,
+    path: &Path,
+    location: String,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let has_downloads = library
+        .library
+        .downloads
+        .as_ref()
+        .map(|downloads| downloads.artifact.is_some() || downloads.classifiers.is_some())
+        .unwrap_or(false);
+    // local libraries are provided by the user
+    let is_local = library.mmc_hint.as_deref() == Some("local");
+    if library.url.is_none() && !has_downloads && !is_local {
+        diagnostics.push(Diagnostic::warning(
+            DiagnosticKind::LibraryWithoutSource,
+            path,
+            Some(location),
+            format!(
+                "{} has neither a url nor downloads, so Mojang's library repository is used",
+                library.library.name
+            ),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn sha256(contents: &[u8]) -> String {
+        data_encoding::HEXLOWER
+            .encode(ring::digest::digest(&ring::digest::SHA256, contents).as_ref())
+    }
+
+    fn write_file(path: &Path, value: &Value) -> String {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let contents = serde_json::to_vec_pretty(value).unwrap();
+        std::fs::write(path, &contents).unwrap();
+        sha256(&contents)
+    }
+
+    fn version_file(uid: &str, version: &str) -> Value {
+        let mut file = json!({
+            "formatVersion": 1,
+            "name": uid,
+            "uid": uid,
+            "version": version,
+            "libraries": [
+                {
+                    "name": "org.example:library:1.0",
+                    "url": "https://maven.example.org/"
+                }
+            ]
+        });
+        if uid == MINECRAFT_UID {
+            file["mainJar"] = json!({
+                "name": format!("com.mojang:minecraft:{}:client", version),
+                "downloads": {
+                    "artifact": {
+                        "sha1": "7e46fb47609401970e2818989fa584fd467cd036",
+                        "size": 1,
+                        "url": "https://launcher.mojang.com/client.jar"
+                    }
+                }
+            });
+        }
+        file
+    }
+
+    /// Writes a metadata directory with the version files of each package and indexes listing their hashes.
+    fn write_metadata(name: &str, packages: &[(&str, Vec<Value>)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "morphosis-meta-validate-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let mut package_entries = Vec::new();
+        for (uid, files) in packages {
+            let versions: Vec<Value> = files
+                .iter()
+                .map(|file| {
+                    let version = file["version"].as_str().unwrap();
+                    let sha256 = write_file(&directory.join(version_file_path(uid, version)), file);
+                    json!({ "version": version, "sha256": sha256 })
+                })
+                .collect();
+            let version_index = json!({
+                "formatVersion": 1,
+                "name": uid,
+                "uid": uid,
+                "versions": versions
+            });
+            let sha256 = write_file(&directory.join(uid).join("index.json"), &version_index);
+            write_file(
+                &directory.join(uid).join("package.json"),
+                &json!({ "formatVersion": 1, "name": uid, "uid": uid }),
+            );
+            package_entries.push(json!({ "name": uid, "uid": uid, "sha256": sha256 }));
+        }
+        write_file(
+            &directory.join("index.json"),
+            &json!({ "formatVersion": 1, "packages": package_entries }),
+        );
+
+        directory
+    }
+
+    fn validate(directory: PathBuf) -> Vec<DiagnosticKind> {
+        let diagnostics = validate_directory(&directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        diagnostics
+            .into_iter()
+            .map(|diagnostic| diagnostic.kind)
+            .collect()
+    }
+
+    fn minecraft_requiring(requirement: Value) -> Value {
+        let mut file = version_file(MINECRAFT_UID, "1.18.1");
+        file["requires"] = json!([requirement]);
+        file
+    }
+
+    #[test]
+    fn valid_metadata() {
+        let directory = write_metadata(
+            "valid",
+            &[
+                (
+                    MINECRAFT_UID,
+                    vec![minecraft_requiring(
+                        json!({ "uid": "org.lwjgl3", "equal": "3.2.2" }),
+                    )],
+                ),
+                ("org.lwjgl3", vec![version_file("org.lwjgl3", "3.2.2")]),
+            ],
+        );
+
+        assert_eq!(validate(directory), vec![]);
+    }
+
+    #[test]
+    fn sha256_mismatch() {
+        let directory = write_metadata(
+            "sha256",
+            &[(MINECRAFT_UID, vec![version_file(MINECRAFT_UID, "1.18.1")])],
+        );
+        // same content, different bytes
+        let path = directory.join(version_file_path(MINECRAFT_UID, "1.18.1"));
+        let file: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+
+        assert_eq!(validate(directory), vec![DiagnosticKind::Sha256Mismatch]);
+    }
+
+    #[test]
+    fn unknown_requirement() {
+        let directory = write_metadata(
+            "requirement",
+            &[(
+                MINECRAFT_UID,
+                vec![minecraft_requiring(json!({ "uid": "org.lwjgl3" }))],
+            )],
+        );
+
+        assert_eq!(
+            validate(directory),
+            vec![DiagnosticKind::UnknownRequirement]
+        );
+    }
+
+    #[test]
+    fn unknown_required_version() {
+        let directory = write_metadata(
+            "required-version",
+            &[
+                (
+                    MINECRAFT_UID,
+                    vec![minecraft_requiring(
+                        json!({ "uid": "org.lwjgl3", "equal": "3.3.1" }),
+                    )],
+                ),
+                ("org.lwjgl3", vec![version_file("org.lwjgl3", "3.2.2")]),
+            ],
+        );
+
+        assert_eq!(
+            validate(directory),
+            vec![DiagnosticKind::UnknownRequiredVersion]
+        );
+    }
+
+    #[test]
+    fn missing_main_jar() {
+        let mut file = version_file(MINECRAFT_UID, "1.18.1");
+        file.as_object_mut().unwrap().remove("mainJar");
+        let file: PolyMCVersionFile = serde_json::from_value(file).unwrap();
+
+        let kinds: Vec<_> = validate_version_file(&file, MINECRAFT_UID, "1.18.1")
+            .into_iter()
+            .map(|diagnostic| diagnostic.kind)
+            .collect();
+        assert_eq!(kinds, vec![DiagnosticKind::MissingMainJar]);
+    }
+
+    #[test]
+    fn library_without_source() {
+        let mut file = version_file("org.lwjgl3", "3.2.2");
+        file["libraries"][0].as_object_mut().unwrap().remove("url");
+        let file: PolyMCVersionFile = serde_json::from_value(file).unwrap();
+
+        let diagnostics = validate_version_file(&file, "org.lwjgl3", "3.2.2");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::LibraryWithoutSource);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].location.as_deref(), Some("libraries[0]"));
+    }
+}
@@ -0,0 +1,1034 @@
+//! Version comparison for Maven artifacts and Minecraft versions.
+
+use std::{cmp::Ordering, collections::HashSet, fmt::Display, str::FromStr, sync::Mutex};
+
+lazy_static! {
+    static ref SNAPSHOT_REGEX: regex::Regex =
+        regex::Regex::new("^(?P<year>[0-9]{2})w(?P<week>[0-9]{2})(?P<suffix>[a-z~_]+)$").unwrap();
+    static ref LEGACY_REGEX: regex::Regex =
+        regex::Regex::new("^(?P<era>rd|c|inf|a|b)-?(?P<rest>[0-9].*)$").unwrap();
+    static ref RELEASE_REGEX: regex::Regex = regex::Regex::new(
+        "^(?P<release>[0-9]+(\\.[0-9]+)*)([-_ ]+(?P<stage>pre|pre-release|rc|release candidate)[-_ ]?(?P<number>[0-9]+))?$"
+    )
+    .unwrap();
+    static ref VARIANT_REGEX: regex::Regex = regex::Regex::new(
+        "^(?P<release>[0-9]+(\\.[0-9]+)+)[-_ ]+(?P<label>.*[a-z].*)$"
+    )
+    .unwrap();
+
+    /// The last snapshot week of every development cycle and the release it led up to.
+    ///
+    /// This is only used to order snapshots relative to releases. Snapshots after the last entry are
+    /// considered to target the release after the newest listed one, with a warning, and
+    /// [`MinecraftVersion::snapshot_target`] rejects them, so the table has to be extended.
+    static ref SNAPSHOT_TARGETS: Vec<((u32, u32), Vec<u32>)> = vec![
+        ((12, 30), vec![1, 3]),
+        ((12, 42), vec![1, 4]),
+        ((12, 50), vec![1, 4, 6]),
+        ((13, 10), vec![1, 5]),
+        ((13, 12), vec![1, 5, 1]),
+        ((13, 26), vec![1, 6]),
+        ((13, 43), vec![1, 7]),
+        ((13, 49), vec![1, 7, 4]),
+        ((14, 34), vec![1, 8]),
+        ((16, 7), vec![1, 9]),
+        ((16, 15), vec![1, 9, 3]),
+        ((16, 21), vec![1, 10]),
+        ((16, 44), vec![1, 11]),
+        ((17, 18), vec![1, 12]),
+        ((18, 22), vec![1, 13]),
+        ((18, 33), vec![1, 13, 1]),
+        ((19, 14), vec![1, 14]),
+        ((19, 46), vec![1, 15]),
+        ((20, 22), vec![1, 16]),
+        ((20, 30), vec![1, 16, 2]),
+        ((21, 20), vec![1, 17]),
+        ((21, 44), vec![1, 18]),
+        ((22, 7), vec![1, 18, 2]),
+        ((22, 19), vec![1, 19]),
+        ((22, 24), vec![1, 19, 1]),
+        ((22, 46), vec![1, 19, 3]),
+        ((23, 7), vec![1, 19, 4]),
+        ((23, 18), vec![1, 20]),
+        ((23, 35), vec![1, 20, 2]),
+        ((23, 46), vec![1, 20, 3]),
+        ((24, 14), vec![1, 20, 5]),
+        ((24, 21), vec![1, 21]),
+        ((24, 40), vec![1, 21, 2]),
+        ((24, 46), vec![1, 21, 4]),
+        ((25, 10), vec![1, 21, 5]),
+        ((25, 21), vec![1, 21, 6]),
+        ((25, 37), vec![1, 21, 9]),
+        ((25, 46), vec![1, 21, 11]),
+    ];
+    /// Snapshot weeks after [`SNAPSHOT_TARGETS`] which were already warned about.
+    static ref UNKNOWN_SNAPSHOT_WEEKS: Mutex<HashSet<(u32, u32)>> = Mutex::new(HashSet::new());
+}
+
+/// April Fools versions which don't look like a snapshot, they are ordered like a snapshot of the week they were
+/// released in.
+const APRIL_FOOLS_WEEKS: [(&str, (u32, u32)); 5] = [
+    ("2point0_red", (13, 14)),
+    ("2point0_blue", (13, 14)),
+    ("2point0_purple", (13, 14)),
+    ("1.rv-pre1", (16, 14)),
+    ("3d shareware v1.34", (19, 14)),
+];
+
+/// Qualifiers with a well-known ordering, every other qualifier is sorted after them.
+const MAVEN_QUALIFIERS: [&str; 7] = ["alpha", "beta", "milestone", "rc", "snapshot", "", "sp"];
+
+/// Index of the release qualifier (the empty string) inside [`MAVEN_QUALIFIERS`].
+const MAVEN_RELEASE_QUALIFIER: usize = 5;
+
+#[derive(Debug, Clone)]
+enum MavenItem {
+    /// A numeric item, stored without leading zeros so arbitrarily large numbers compare correctly.
+    Int(String),
+    String(String),
+    List(Vec<MavenItem>),
+}
+
+impl MavenItem {
+    fn parse(is_digit: bool, value: &str) -> Self {
+        if is_digit {
+            Self::Int(value.trim_start_matches('0').to_string())
+        } else {
+            Self::string(value, false)
+        }
+    }
+
+    fn string(value: &str, followed_by_digit: bool) -> Self {
+        let value = match value {
+            "a" if followed_by_digit => "alpha",
+            "b" if followed_by_digit => "beta",
+            "m" if followed_by_digit => "milestone",
+            "ga" | "final" | "release" => "",
+            "cr" => "rc",
+            _ => value,
+        };
+
+        Self::String(value.to_string())
+    }
+
+    fn comparable_qualifier(qualifier: &str) -> String {
+        match MAVEN_QUALIFIERS.iter().position(|q| *q == qualifier) {
+            Some(index) => index.to_string(),
+            None => format!("{}-{}", MAVEN_QUALIFIERS.len(), qualifier),
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        match self {
+            Self::Int(value) => value.is_empty(),
+            Self::String(value) => {
+                Self::comparable_qualifier(value) == MAVEN_RELEASE_QUALIFIER.to_string()
+            }
+            Self::List(items) => items.is_empty(),
+        }
+    }
+
+    /// Compares this item to the given one, `None` meaning a missing item.
+    fn compare(&self, other: Option<&MavenItem>) -> Ordering {
+        match (self, other) {
+            (Self::Int(value), None) => {
+                if value.is_empty() {
+                    Ordering::Equal
+                } else {
+                    Ordering::Greater
+                }
+            }
+            (Self::Int(left), Some(Self::Int(right))) => {
+                left.len().cmp(&right.len()).then_with(|| left.cmp(right))
+            }
+            (Self::Int(_), Some(_)) => Ordering::Greater,
+            (Self::String(value), None) => Self::comparable_qualifier(value)
+                .as_str()
+                .cmp(MAVEN_RELEASE_QUALIFIER.to_string().as_str()),
+            (Self::String(_), Some(Self::Int(_))) => Ordering::Less,
+            (Self::String(left), Some(Self::String(right))) => {
+                Self::comparable_qualifier(left).cmp(&Self::comparable_qualifier(right))
+            }
+            (Self::String(_), Some(Self::List(_))) => Ordering::Less,
+            (Self::List(items), None) => items
+                .first()
+                .map(|first| first.compare(None))
+                .unwrap_or(Ordering::Equal),
+            (Self::List(_), Some(Self::Int(_))) => Ordering::Less,
+            (Self::List(_), Some(Self::String(_))) => Ordering::Greater,
+            (Self::List(left), Some(Self::List(right))) => compare_maven_items(left, right),
+        }
+    }
+}
+
+fn compare_maven_items(left: &[MavenItem], right: &[MavenItem]) -> Ordering {
+    for index in 0..left.len().max(right.len()) {
+        let result = match (left.get(index), right.get(index)) {
+            (Some(l), r) => l.compare(r),
+            (None, Some(r)) => r.compare(None).reverse(),
+            (None, None) => Ordering::Equal,
+        };
+        if result != Ordering::Equal {
+            return result;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Removes trailing null items, stopping at the first item that isn't a list.
+fn normalize_maven_items(items: &mut Vec<MavenItem>) {
+    for index in (0..items.len()).rev() {
+        if items[index].is_null() {
+            items.remove(index);
+        } else if !matches!(items[index], MavenItem::List(_)) {
+            break;
+        }
+    }
+}
+
+/// A version following Maven's `ComparableVersion` semantics.
+///
+/// `1.0 == 1.0.0 == 1-ga`, `1.0-alpha1 < 1.0-beta < 1.0-rc1 < 1.0-SNAPSHOT < 1.0 < 1.0-sp1`
+/// and unknown qualifiers are sorted after all known ones.
+#[derive(Debug, Clone)]
+pub struct MavenVersion {
+    raw: String,
+    items: Vec<MavenItem>,
+}
+
+impl MavenVersion {
+    pub fn new(version: &str) -> Self {
+        let lower = version.to_lowercase();
+        let chars = lower.chars().collect::<Vec<char>>();
+
+        // each entry holds the items of one nesting level, the first one is the root list
+        let mut stack: Vec<Vec<MavenItem>> = vec![Vec::new()];
+        let mut is_digit = false;
+        let mut start_index = 0;
+
+        for (i, c) in chars.iter().enumerate() {
+            let current = || chars[start_index..i].iter().collect::<String>();
+            if *c == '.' {
+                let item = if i == start_index {
+                    MavenItem::Int(String::new())
+                } else {
+                    MavenItem::parse(is_digit, &current())
+                };
+                stack.last_mut().unwrap().push(item);
+                start_index = i + 1;
+            } else if *c == '-' {
+                let item = if i == start_index {
+                    MavenItem::Int(String::new())
+                } else {
+                    MavenItem::parse(is_digit, &current())
+                };
+                stack.last_mut().unwrap().push(item);
+                start_index = i + 1;
+                stack.push(Vec::new());
+            } else if c.is_ascii_digit() {
+                if !is_digit && i > start_index {
+                    let item = MavenItem::string(&current(), true);
+                    stack.last_mut().unwrap().push(item);
+                    start_index = i;
+                    stack.push(Vec::new());
+                }
+                is_digit = true;
+            } else {
+                if is_digit && i > start_index {
+                    let item = MavenItem::parse(true, &current());
+                    stack.last_mut().unwrap().push(item);
+                    start_index = i;
+                    stack.push(Vec::new());
+                }
+                is_digit = false;
+            }
+        }
+
+        if chars.len() > start_index {
+            let rest = chars[start_index..].iter().collect::<String>();
+            let item = if is_digit {
+                MavenItem::parse(true, &rest)
+            } else {
+                MavenItem::string(&rest, false)
+            };
+            stack.last_mut().unwrap().push(item);
+        }
+
+        // fold the nested lists back into their parents, normalizing each of them
+        while stack.len() > 1 {
+            let mut items = stack.pop().unwrap();
+            normalize_maven_items(&mut items);
+            stack.last_mut().unwrap().push(MavenItem::List(items));
+        }
+        let mut items = stack.pop().unwrap();
+        normalize_maven_items(&mut items);
+
+        Self {
+            raw: version.to_string(),
+            items,
+        }
+    }
+
+    /// Returns the version as it was parsed.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl FromStr for MavenVersion {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+impl Display for MavenVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl Ord for MavenVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_maven_items(&self.items, &other.items)
+    }
+}
+
+impl PartialOrd for MavenVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MavenVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MavenVersion {}
+
+custom_error! {
+    /// Errors that can occur when parsing a version range.
+    pub VersionRangeError
+        InvalidRange { range: String, reason: String } = "Invalid version range '{range}': {reason}",
+}
+
+/// One interval of a [`MavenVersionRange`], bounds are `(version, inclusive)` and missing bounds are unbounded.
+#[derive(Debug, Clone)]
+struct MavenInterval {
+    lower: Option<(MavenVersion, bool)>,
+    upper: Option<(MavenVersion, bool)>,
+}
+
+impl MavenInterval {
+    fn contains(&self, version: &MavenVersion) -> bool {
+        let above_lower = match &self.lower {
+            Some((lower, true)) => version >= lower,
+            Some((lower, false)) => version > lower,
+            None => true,
+        };
+        let below_upper = match &self.upper {
+            Some((upper, true)) => version <= upper,
+            Some((upper, false)) => version < upper,
+            None => true,
+        };
+        above_lower && below_upper
+    }
+}
+
+/// A set of versions in Maven's range syntax.
+///
+/// `1.0` and `[1.0]` only contain `1.0`, `[1.0,2.0)` contains `1.0 <= x < 2.0`, `(,1.0]` contains `x <= 1.0`
+/// and `[1.0,2.0),[3.0,)` is the union of both intervals. `*` contains every version.
+#[derive(Debug, Clone)]
+pub struct MavenVersionRange {
+    raw: String,
+    intervals: Vec<MavenInterval>,
+}
+
+impl MavenVersionRange {
+    /// Returns the range which contains every version.
+    pub fn any() -> Self {
+        Self {
+            raw: "*".to_string(),
+            intervals: vec![MavenInterval {
+                lower: None,
+                upper: None,
+            }],
+        }
+    }
+
+    pub fn contains(&self, version: &MavenVersion) -> bool {
+        self.intervals
+            .iter()
+            .any(|interval| interval.contains(version))
+    }
+
+    /// Returns the range as it was parsed.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl FromStr for MavenVersionRange {
+    type Err = VersionRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid_range = |reason: &str| VersionRangeError::InvalidRange {
+            range: s.to_string(),
+            reason: reason.to_string(),
+        };
+        let trimmed = s.trim();
+        if trimmed.is_empty() {
+            return Err(invalid_range("the range is empty"));
+        }
+        if trimmed == "*" {
+            return Ok(Self {
+                raw: s.to_string(),
+                ..Self::any()
+            });
+        }
+        if !trimmed.starts_with(['[', '(']) {
+            if trimmed.contains([',', '[', ']', '(', ')']) {
+                return Err(invalid_range("intervals have to start with '[' or '('"));
+            }
+            let version = MavenVersion::new(trimmed);
+            return Ok(Self {
+                raw: s.to_string(),
+                intervals: vec![MavenInterval {
+                    lower: Some((version.clone(), true)),
+                    upper: Some((version, true)),
+                }],
+            });
+        }
+
+        let mut intervals = Vec::new();
+        let mut rest = trimmed;
+        while !rest.is_empty() {
+            let lower_inclusive = match rest.chars().next() {
+                Some('[') => true,
+                Some('(') => false,
+                _ => return Err(invalid_range("expected '[' or '('")),
+            };
+            let end = rest
+                .find([']', ')'])
+                .ok_or_else(|| invalid_range("missing ']' or ')'"))?;
+            let upper_inclusive = rest[end..].starts_with(']');
+            let inner = &rest[1..end];
+
+            let interval = match inner.split_once(',') {
+                Some((lower, upper)) => {
+                    let bound = |version: &str, inclusive: bool| {
+                        let version = version.trim();
+                        (!version.is_empty()).then(|| (MavenVersion::new(version), inclusive))
+                    };
+                    MavenInterval {
+                        lower: bound(lower, lower_inclusive),
+                        upper: bound(upper, upper_inclusive),
+                    }
+                }
+                None if lower_inclusive && upper_inclusive && !inner.trim().is_empty() => {
+                    let version = MavenVersion::new(inner.trim());
+                    MavenInterval {
+                        lower: Some((version.clone(), true)),
+                        upper: Some((version, true)),
+                    }
+                }
+                None => return Err(invalid_range("a single version has to be written as [x]")),
+            };
+            if let (Some((lower, _)), Some((upper, _))) = (&interval.lower, &interval.upper) {
+                if lower > upper {
+                    return Err(invalid_range(
+                        "the lower bound is greater than the upper bound",
+                    ));
+                }
+            }
+            intervals.push(interval);
+
+            rest = rest[end + 1..].trim_start();
+            if let Some(next) = rest.strip_prefix(',') {
+                rest = next.trim_start();
+                if rest.is_empty() {
+                    return Err(invalid_range("trailing ','"));
+                }
+            }
+        }
+
+        Ok(Self {
+            raw: s.to_string(),
+            intervals,
+        })
+    }
+}
+
+impl Display for MavenVersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl serde::Serialize for MavenVersionRange {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for MavenVersionRange {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for MavenVersionRange {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "MavenVersionRange".into()
+    }
+
+    /// A version, `*` or intervals like `[1.0,2.0)`, with the custom `maven-version-range` format.
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "string",
+            "format": "maven-version-range",
+        })
+    }
+}
+
+custom_error! {
+    /// Errors that can occur when placing a Minecraft version.
+    pub MinecraftVersionError
+        UnknownSnapshot { version: String } = "Snapshot {version} is newer than every known development cycle, the release it targets has to be added to the snapshot table",
+}
+
+/// The development stage of a Minecraft release, in ascending order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MinecraftStage {
+    Snapshot,
+    PreRelease,
+    ReleaseCandidate,
+    Release,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MinecraftVersionKind {
+    /// Pre-release eras like `rd-132211`, `c0.30_01c`, `inf-20100618`, `a1.0.4` and `b1.7.3`.
+    Legacy { era: u8, version: String },
+    /// Weekly snapshots like `22w13a`.
+    Snapshot {
+        year: u32,
+        week: u32,
+        suffix: String,
+    },
+    /// Releases, pre-releases and release candidates like `1.19`, `1.19-pre1`, `1.7.10_pre4` or `1.19-rc1`.
+    Release {
+        release: Vec<u32>,
+        stage: MinecraftStage,
+        number: u32,
+    },
+    /// Combat tests and experimental snapshots of a release like `1.14_combat-212796` or
+    /// `1.18_experimental-snapshot-1`.
+    Variant {
+        release: Vec<u32>,
+        experimental: bool,
+    },
+    /// Anything else. April Fools versions listed in [`APRIL_FOOLS_WEEKS`] are ordered like snapshots, the rest after
+    /// every other version using Maven semantics.
+    Other,
+}
+
+/// Where a version falls inside the development cycle of its release, in ascending order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CycleStep {
+    Experimental,
+    Snapshot,
+    PreRelease,
+    ReleaseCandidate,
+    Release,
+    Variant,
+}
+
+/// The position of a version in the release history, versions are ordered by comparing their positions.
+///
+/// Every version maps to exactly one position, which keeps the ordering total even across kinds.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum MinecraftPosition {
+    Legacy {
+        era: u8,
+        version: MavenVersion,
+    },
+    Cycle {
+        /// The release the cycle led up to, without trailing zeros.
+        target: Vec<u32>,
+        /// Set for snapshots newer than every known cycle, which are placed after its target.
+        unknown_cycle: bool,
+        step: CycleStep,
+        week: (u32, u32),
+        number: u32,
+        label: String,
+    },
+    Other(MavenVersion),
+}
+
+/// A Minecraft version ID, ordered the way Mojang released them.
+#[derive(Debug, Clone)]
+pub struct MinecraftVersion {
+    raw: String,
+    kind: MinecraftVersionKind,
+}
+
+impl MinecraftVersion {
+    pub fn new(version: &str) -> Self {
+        let lower = version.trim().to_lowercase();
+
+        let kind = if let Some(captures) = SNAPSHOT_REGEX.captures(&lower) {
+            MinecraftVersionKind::Snapshot {
+                year: captures["year"].parse().unwrap(),
+                week: captures["week"].parse().unwrap(),
+                suffix: captures["suffix"].to_string(),
+            }
+        } else if let Some(captures) = RELEASE_REGEX.captures(&lower) {
+            let stage = match captures.name("stage").map(|s| s.as_str()) {
+                Some("pre") | Some("pre-release") => MinecraftStage::PreRelease,
+                Some(_) => MinecraftStage::ReleaseCandidate,
+                None => MinecraftStage::Release,
+            };
+            MinecraftVersionKind::Release {
+                release: parse_release(&captures["release"]),
+                stage,
+                number: captures
+                    .name("number")
+                    .and_then(|n| n.as_str().parse().ok())
+                    .unwrap_or(0),
+            }
+        } else if let Some(captures) = VARIANT_REGEX.captures(&lower) {
+            MinecraftVersionKind::Variant {
+                release: parse_release(&captures["release"]),
+                experimental: captures["label"].contains("experimental"),
+            }
+        } else if let Some(captures) = LEGACY_REGEX.captures(&lower) {
+            let era = match &captures["era"] {
+                "rd" => 0,
+                "c" => 1,
+                "inf" => 2,
+                "a" => 3,
+                _ => 4,
+            };
+            MinecraftVersionKind::Legacy {
+                era,
+                version: captures["rest"].to_string(),
+            }
+        } else {
+            MinecraftVersionKind::Other
+        };
+
+        Self {
+            raw: version.to_string(),
+            kind,
+        }
+    }
+
+    /// Returns the version as it was parsed.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// Returns `true` if this is a weekly snapshot.
+    pub fn is_snapshot(&self) -> bool {
+        matches!(self.kind, MinecraftVersionKind::Snapshot { .. })
+    }
+
+    /// Returns the development stage, if this is a release, pre-release or release candidate.
+    pub fn stage(&self) -> Option<MinecraftStage> {
+        match &self.kind {
+            MinecraftVersionKind::Release { stage, .. } => Some(*stage),
+            _ => None,
+        }
+    }
+
+    /// Returns the release a weekly snapshot led up to, or `None` if this isn't a snapshot.
+    pub fn snapshot_target(&self) -> Result<Option<String>, MinecraftVersionError> {
+        let (year, week) = match &self.kind {
+            MinecraftVersionKind::Snapshot { year, week, .. } => (*year, *week),
+            _ => return Ok(None),
+        };
+        let target =
+            snapshot_target(year, week).ok_or_else(|| MinecraftVersionError::UnknownSnapshot {
+                version: self.raw.clone(),
+            })?;
+
+        Ok(Some(
+            target
+                .iter()
+                .map(|part| part.to_string())
+                .collect::<Vec<_>>()
+                .join("."),
+        ))
+    }
+
+    fn position(&self) -> MinecraftPosition {
+        let lower = self.raw.trim().to_lowercase();
+        let cycle = |target: &[u32], step, week, number, label: &str| MinecraftPosition::Cycle {
+            target: trim_release(target),
+            unknown_cycle: false,
+            step,
+            week,
+            number,
+            label: label.to_string(),
+        };
+
+        match &self.kind {
+            MinecraftVersionKind::Legacy { era, version } => MinecraftPosition::Legacy {
+                era: *era,
+                version: MavenVersion::new(version),
+            },
+            MinecraftVersionKind::Snapshot { year, week, suffix } => {
+                snapshot_position(*year, *week, suffix)
+            }
+            MinecraftVersionKind::Release {
+                release,
+                stage,
+                number,
+            } => {
+                let step = match stage {
+                    MinecraftStage::Snapshot => CycleStep::Snapshot,
+                    MinecraftStage::PreRelease => CycleStep::PreRelease,
+                    MinecraftStage::ReleaseCandidate => CycleStep::ReleaseCandidate,
+                    MinecraftStage::Release => CycleStep::Release,
+                };
+                cycle(release, step, (0, 0), *number, "")
+            }
+            // experimental snapshots came before the regular snapshots of their release, combat tests after it
+            MinecraftVersionKind::Variant {
+                release,
+                experimental,
+            } => {
+                let step = if *experimental {
+                    CycleStep::Experimental
+                } else {
+                    CycleStep::Variant
+                };
+                cycle(release, step, (0, 0), 0, &lower)
+            }
+            MinecraftVersionKind::Other => APRIL_FOOLS_WEEKS
+                .iter()
+                .find(|(version, _)| *version == lower)
+                .map(|(_, (year, week))| snapshot_position(*year, *week, &lower))
+                .unwrap_or_else(|| MinecraftPosition::Other(MavenVersion::new(&self.raw))),
+        }
+    }
+}
+
+fn parse_release(release: &str) -> Vec<u32> {
+    release
+        .split('.')
+        .map(|part| part.parse().unwrap_or(u32::MAX))
+        .collect()
+}
+
+/// Removes the trailing zeros of a dotted release number, so `1.0` and `1` compare equal.
+fn trim_release(release: &[u32]) -> Vec<u32> {
+    let length = release
+        .iter()
+        .rposition(|part| *part != 0)
+        .map_or(0, |index| index + 1);
+    release[..length].to_vec()
+}
+
+fn snapshot_target(year: u32, week: u32) -> Option<&'static [u32]> {
+    SNAPSHOT_TARGETS
+        .iter()
+        .find(|(last_week, _)| (year, week) <= *last_week)
+        .map(|(_, target)| target.as_slice())
+}
+
+/// Places a snapshot before every pre-release and release of its target.
+fn snapshot_position(year: u32, week: u32, label: &str) -> MinecraftPosition {
+    let (target, unknown_cycle) = match snapshot_target(year, week) {
+        Some(target) => (target, false),
+        None => {
+            if UNKNOWN_SNAPSHOT_WEEKS.lock().unwrap().insert((year, week)) {
+                log::warn!(
+                    "Snapshot week {}w{:02} is newer than every known development cycle, it is ordered after every release up to the newest known one",
+                    year, week
+                );
+            }
+            (SNAPSHOT_TARGETS.last().unwrap().1.as_slice(), true)
+        }
+    };
+
+    MinecraftPosition::Cycle {
+        target: trim_release(target),
+        unknown_cycle,
+        step: CycleStep::Snapshot,
+        week: (year, week),
+        number: 0,
+        label: label.to_string(),
+    }
+}
+
+impl FromStr for MinecraftVersion {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+impl Display for MinecraftVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl Ord for MinecraftVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // fall back to the raw ID, so different spellings of the same version still have a stable order
+        self.position()
+            .cmp(&other.position())
+            .then_with(|| self.raw.cmp(&other.raw))
+    }
+}
+
+impl PartialOrd for MinecraftVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MinecraftVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MinecraftVersion {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_ascending<T, F>(versions: &[&str], parse: F)
+    where
+        T: Ord + std::fmt::Debug,
+        F: Fn(&str) -> T,
+    {
+        for pair in versions.windows(2) {
+            let (lower, higher) = (parse(pair[0]), parse(pair[1]));
+            assert!(lower < higher, "{:?} < {:?}", lower, higher);
+            assert!(higher > lower, "{:?} > {:?}", higher, lower);
+        }
+    }
+
+    #[test]
+    fn maven_qualifiers_are_ordered() {
+        assert_ascending(
+            &[
+                "1.0-alpha1",
+                "1.0-beta",
+                "1.0-milestone",
+                "1.0-rc1",
+                "1.0-SNAPSHOT",
+                "1.0",
+                "1.0-sp1",
+                "1.0-unknown",
+                "1.0.1",
+            ],
+            MavenVersion::new,
+        );
+    }
+
+    #[test]
+    fn maven_versions_are_normalized() {
+        assert_eq!(MavenVersion::new("1.0"), MavenVersion::new("1.0.0"));
+        assert_eq!(MavenVersion::new("1.0"), MavenVersion::new("1-ga"));
+        assert_eq!(
+            MavenVersion::new("1.0-a1"),
+            MavenVersion::new("1.0-alpha-1")
+        );
+        assert_ascending(&["1.9", "1.10", "1.10.1"], MavenVersion::new);
+    }
+
+    #[test]
+    fn minecraft_releases_are_ordered() {
+        assert_ascending(
+            &[
+                "rd-132211",
+                "c0.30_01c",
+                "inf-20100618",
+                "a1.0.4",
+                "b1.7.3",
+                "1.0",
+                "1.7.10_pre4",
+                "1.7.10",
+                "1.18.2",
+                "22w13a",
+                "1.19-pre1",
+                "1.19-rc1",
+                "1.19",
+                "1.19.1",
+            ],
+            MinecraftVersion::new,
+        );
+    }
+
+    #[test]
+    fn snapshots_are_ordered_by_target() {
+        assert_ascending(
+            &["1.21.3", "24w46a", "1.21.4", "25w02a", "1.21.5"],
+            MinecraftVersion::new,
+        );
+        assert_ascending(&["22w13a", "22w13b", "22w14a"], MinecraftVersion::new);
+    }
+
+    #[test]
+    fn special_versions_are_placed_in_their_cycle() {
+        assert_ascending(
+            &[
+                "19w13b",
+                "3D Shareware v1.34",
+                "19w14a",
+                "1.14",
+                "1.14_combat-212796",
+                "1.14.1",
+            ],
+            MinecraftVersion::new,
+        );
+        assert_ascending(
+            &[
+                "20w13b",
+                "20w14a",
+                "20w14infinite",
+                "1.16",
+                "1.16_combat-0",
+                "1.16.1",
+            ],
+            MinecraftVersion::new,
+        );
+        assert_ascending(
+            &["1.17.1", "1.18_experimental-snapshot-1", "21w37a", "1.18"],
+            MinecraftVersion::new,
+        );
+        assert_ascending(
+            &[
+                "1.9.2",
+                "1.RV-Pre1",
+                "16w15a",
+                "1.9.3-pre1",
+                "1.19.4",
+                "23w13a_or_b",
+                "1.20",
+            ],
+            MinecraftVersion::new,
+        );
+        assert_ascending(&["1.21.11", "unknown"], MinecraftVersion::new);
+    }
+
+    #[test]
+    fn manifest_versions_are_totally_ordered() {
+        let mut versions: Vec<MinecraftVersion> =
+            include_str!("../tests/data/mojang/version_ids.txt")
+                .lines()
+                .map(MinecraftVersion::new)
+                .collect();
+        versions.sort();
+
+        // a cycle would leave a pair in the sorted list which doesn't compare ascending
+        for (index, lower) in versions.iter().enumerate() {
+            for higher in &versions[index + 1..] {
+                assert_eq!(lower.cmp(higher), Ordering::Less, "{} < {}", lower, higher);
+                assert_eq!(
+                    higher.cmp(lower),
+                    Ordering::Greater,
+                    "{} > {}",
+                    higher,
+                    lower
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn snapshot_targets_are_known() {
+        assert_eq!(
+            MinecraftVersion::new("22w13a").snapshot_target().unwrap(),
+            Some("1.19".to_string())
+        );
+        assert_eq!(
+            MinecraftVersion::new("25w46a").snapshot_target().unwrap(),
+            Some("1.21.11".to_string())
+        );
+        assert_eq!(
+            MinecraftVersion::new("1.19").snapshot_target().unwrap(),
+            None
+        );
+        assert!(MinecraftVersion::new("99w01a").snapshot_target().is_err());
+    }
+
+    fn range(range: &str) -> MavenVersionRange {
+        range.parse().unwrap()
+    }
+
+    fn contains(range: &MavenVersionRange, version: &str) -> bool {
+        range.contains(&MavenVersion::new(version))
+    }
+
+    #[test]
+    fn parses_bounded_ranges() {
+        let bounded = range("[1.0,2.0)");
+        assert!(!contains(&bounded, "0.9"));
+        assert!(contains(&bounded, "1.0"));
+        assert!(contains(&bounded, "1.9.9"));
+        assert!(!contains(&bounded, "2.0"));
+
+        let at_most = range("(,1.2]");
+        assert!(contains(&at_most, "0.1"));
+        assert!(contains(&at_most, "1.2"));
+        assert!(!contains(&at_most, "1.2.1"));
+
+        let above = range("(1.0,)");
+        assert!(!contains(&above, "1.0"));
+        assert!(contains(&above, "1.0.1"));
+    }
+
+    #[test]
+    fn parses_single_versions() {
+        for single in [range("1.5"), range("[1.5]")] {
+            assert!(contains(&single, "1.5"));
+            assert!(contains(&single, "1.5.0"));
+            assert!(!contains(&single, "1.5.1"));
+            assert!(!contains(&single, "1.4"));
+        }
+        assert!(contains(&range("*"), "0.0.1-SNAPSHOT"));
+    }
+
+    #[test]
+    fn parses_multiple_ranges() {
+        let multiple = range("(,1.0],[1.2,1.4), [2.0,)");
+        assert_eq!(multiple.as_str(), "(,1.0],[1.2,1.4), [2.0,)");
+        assert!(contains(&multiple, "1.0"));
+        assert!(!contains(&multiple, "1.1"));
+        assert!(contains(&multiple, "1.2"));
+        assert!(!contains(&multiple, "1.4"));
+        assert!(contains(&multiple, "2.0"));
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        for invalid in ["", "[1.0", "[2.0,1.0]", "(1.0)", "[1.0,2.0),", "1.0,2.0]"] {
+            assert!(
+                invalid.parse::<MavenVersionRange>().is_err(),
+                "{:?} was accepted",
+                invalid
+            );
+        }
+    }
+}
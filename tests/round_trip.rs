@@ -0,0 +1,180 @@
+//! Parses the upstream and generated files in `tests/data` with their models and checks that serializing them
+//! again doesn't lose anything. The files follow the upstream formats, trimmed to a few entries each.
+//!
+//! Without `extra-fields` unknown fields are dropped, so the written files are only checked to be a subset of the
+//! originals: no key is renamed or added, and no value changes.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use morphosis_meta::models::{
+    fabric::FabricInstallerDataV1,
+    forge::{ForgeInstallerProfile, ForgeInstallerProfileV2},
+    liteloader::LiteloaderIndex,
+    mojang::{MojangIndex, MojangVersionFile},
+    polymc::{PolyMCPackageIndex, PolyMCSharedPackageData, PolyMCVersionFile, PolyMCVersionIndex},
+};
+
+fn read_corpus_file(path: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(path);
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+/// Rewrites every timestamp to RFC 3339 in UTC.
+///
+/// The models store timestamps as UTC, so `-0400` or `Z` offsets are written back as the same instant in `+00:00`.
+fn normalize_timestamps(value: Value) -> Value {
+    match value {
+        Value::String(s) => {
+            let timestamp = DateTime::parse_from_rfc3339(&s)
+                .or_else(|_| DateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%S%z"));
+            match timestamp {
+                Ok(timestamp) => Value::String(timestamp.with_timezone(&Utc).to_rfc3339()),
+                Err(_) => Value::String(s),
+            }
+        }
+        Value::Array(values) => {
+            Value::Array(values.into_iter().map(normalize_timestamps).collect())
+        }
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, normalize_timestamps(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Checks that every key of `written` is in `original` with the same value, recursively.
+#[cfg(not(feature = "extra-fields"))]
+fn assert_subset(written: &Value, original: &Value, path: &str) {
+    match (written, original) {
+        (Value::Object(written), Value::Object(original)) => {
+            for (key, value) in written {
+                let original_value = original
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{}/{} isn't in the original file", path, key));
+                assert_subset(value, original_value, &format!("{}/{}", path, key));
+            }
+        }
+        (Value::Array(written), Value::Array(original)) => {
+            assert_eq!(written.len(), original.len(), "{}", path);
+            for (i, (value, original_value)) in written.iter().zip(original).enumerate() {
+                assert_subset(value, original_value, &format!("{}/{}", path, i));
+            }
+        }
+        (written, original) => assert_eq!(written, original, "{}", path),
+    }
+}
+
+/// Parses a file with a model, serializes it again and compares the result with the original file.
+fn assert_round_trip<T>(json: &str)
+where
+    T: DeserializeOwned + Serialize,
+{
+    let original: Value = serde_json::from_str(json).unwrap();
+    let model: T = serde_json::from_str(json).unwrap();
+    let written = serde_json::to_value(&model).unwrap();
+
+    #[cfg(feature = "extra-fields")]
+    assert_eq!(
+        normalize_timestamps(written),
+        normalize_timestamps(original)
+    );
+    #[cfg(not(feature = "extra-fields"))]
+    assert_subset(
+        &normalize_timestamps(written),
+        &normalize_timestamps(original),
+        "",
+    );
+}
+
+#[test]
+fn mojang_index() {
+    assert_round_trip::<MojangIndex>(&read_corpus_file("mojang/version_manifest_v2.json"));
+}
+
+#[test]
+fn mojang_version_file() {
+    assert_round_trip::<MojangVersionFile>(&read_corpus_file("mojang/versions/1.18.1.json"));
+}
+
+#[test]
+fn legacy_mojang_version_file() {
+    assert_round_trip::<MojangVersionFile>(&read_corpus_file("mojang/versions/1.7.10.json"));
+}
+
+#[test]
+fn forge_installer_profile() {
+    assert_round_trip::<ForgeInstallerProfile>(&read_corpus_file(
+        "forge/installer_manifests/1.7.10-10.13.4.1614-1.7.10.json",
+    ));
+}
+
+#[test]
+fn forge_installer_profile_v2() {
+    assert_round_trip::<ForgeInstallerProfileV2>(&read_corpus_file(
+        "forge/installer_manifests/1.18.1-39.0.5.json",
+    ));
+}
+
+#[test]
+fn forge_installer_profile_v2_spec_1() {
+    assert_round_trip::<ForgeInstallerProfileV2>(&read_corpus_file(
+        "forge/installer_manifests/1.18.2-40.1.0.json",
+    ));
+}
+
+#[test]
+fn forge_version_manifest() {
+    assert_round_trip::<MojangVersionFile>(&read_corpus_file(
+        "forge/version_manifests/1.18.1-39.0.5.json",
+    ));
+}
+
+#[test]
+fn fabric_installer_data() {
+    assert_round_trip::<FabricInstallerDataV1>(&read_corpus_file(
+        "fabric/loader-installer-json/0.12.12.json",
+    ));
+}
+
+#[test]
+fn legacy_fabric_installer_data() {
+    assert_round_trip::<FabricInstallerDataV1>(&read_corpus_file(
+        "fabric/loader-installer-json/0.7.2+build.175.json",
+    ));
+}
+
+#[test]
+fn liteloader_index() {
+    assert_round_trip::<LiteloaderIndex>(&read_corpus_file("liteloader/versions.json"));
+}
+
+#[test]
+fn polymc_files() {
+    assert_round_trip::<PolyMCPackageIndex>(&read_corpus_file("polymc/index.json"));
+    assert_round_trip::<PolyMCVersionIndex>(&read_corpus_file("polymc/net.minecraft/index.json"));
+    assert_round_trip::<PolyMCSharedPackageData>(&read_corpus_file(
+        "polymc/net.minecraft/package.json",
+    ));
+    assert_round_trip::<PolyMCVersionFile>(&read_corpus_file("polymc/net.minecraft/1.18.1.json"));
+}
+
+#[test]
+#[cfg(feature = "extra-fields")]
+fn unknown_fields_are_kept() {
+    let mut file: Value =
+        serde_json::from_str(&read_corpus_file("mojang/versions/1.18.1.json")).unwrap();
+    file["newField"] = serde_json::json!({ "nested": [1, 2, 3] });
+    let json = serde_json::to_string(&file).unwrap();
+
+    let version_file: MojangVersionFile = serde_json::from_str(&json).unwrap();
+    assert_eq!(version_file.extra["newField"]["nested"][2], 3);
+    assert_round_trip::<MojangVersionFile>(&json);
+}